@@ -1,4 +1,14 @@
-use std::env;
+use std::{env, net::IpAddr};
+
+#[derive(Clone)]
+pub struct OidcProviderConfig {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+}
 
 #[derive(Clone)]
 pub struct AppConfig {
@@ -6,9 +16,110 @@ pub struct AppConfig {
     pub port: u16,
     pub mongodb_uri: String,
     pub mongodb_db: String,
-    pub jwt_secret: String,
+    pub jwt_algorithm: String, // "HS256" | "RS256" | "EdDSA"
+    pub jwt_secret: Option<String>,
+    pub jwt_private_key_file: Option<String>,
+    pub jwt_public_key_file: Option<String>,
+    pub jwt_key_id: String,
+    // Llaves públicas anteriores que siguen validando tokens durante la rotación
+    pub jwt_previous_public_keys: Vec<(String, String)>, // (kid, ruta PEM)
+    pub jwt_issuer: String,
+    pub jwt_audience: String,
     pub jwt_exp_minutes: i64,
+    #[allow(dead_code)] // el CORS de desarrollo aún no lo usa
     pub cors_origin: String,
+    // Proxies cuyo X-Forwarded-For se cree; sin ninguno, la IP es la del socket
+    pub trusted_proxies: Vec<IpAddr>,
+    pub oidc_providers: Vec<OidcProviderConfig>,
+    pub oidc_success_redirect: Option<String>,
+    pub public_url: String,
+    pub smtp: Option<SmtpConfig>,
+    pub mail_from: String,
+    pub login_guard: LoginGuardConfig,
+    pub password: PasswordConfig,
+    pub bootstrap_admin_email: Option<String>,
+    pub impersonation_minutes: i64,
+    pub org_default_quota_bytes: Option<i64>,
+    pub webhooks: WebhookConfig,
+    pub archive_extract: ExtractConfig,
+    pub search: SearchConfig,
+    pub ocr: Option<OcrConfig>,
+    pub office_preview: Option<OfficePreviewConfig>,
+}
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct WebhookConfig {
+    pub poll_secs: u64,
+    pub max_attempts: u32,
+    pub retry_base_secs: i64,
+    // Solo para desarrollo: permite URLs que resuelven a la red local
+    pub allow_private_targets: bool,
+}
+
+// Límites al descomprimir archivos subidos (contra zip bombs)
+#[derive(Clone)]
+pub struct ExtractConfig {
+    pub max_entries: usize,
+    pub max_total_bytes: u64,
+    pub max_ratio: u64, // bytes descomprimidos por byte del archivo subido
+}
+
+// Extracción de texto para la búsqueda
+#[derive(Clone)]
+pub struct SearchConfig {
+    pub language: String, // idioma por defecto del índice de texto de Mongo
+    pub poll_secs: u64,
+    pub max_file_bytes: i64, // archivos más grandes no se leen
+    pub max_text_chars: usize,
+}
+
+// OCR opcional con binarios locales (tesseract + pdftoppm para PDFs escaneados)
+#[derive(Clone)]
+pub struct OcrConfig {
+    pub tesseract_bin: String,
+    pub pdftoppm_bin: String,
+    pub languages: String, // paquetes de idioma de Tesseract: "spa+eng"
+    pub dpi: u32,
+    pub max_pdf_pages: u32,
+    pub timeout_secs: u64, // por cada invocación
+}
+
+// Conversión de documentos de oficina a PDF con LibreOffice en modo headless
+#[derive(Clone)]
+pub struct OfficePreviewConfig {
+    pub soffice_bin: String,
+    pub poll_secs: u64,
+    pub timeout_secs: u64,
+    pub max_concurrent: usize,
+    pub max_file_bytes: i64,
+}
+
+#[derive(Clone)]
+pub struct LoginGuardConfig {
+    pub store: String, // "memory" | "mongo"
+    pub max_failures: u32,
+    pub ip_max_failures: u32,
+    pub lockout_minutes: i64,
+    pub backoff_base_secs: i64,
+    pub backoff_max_secs: i64,
+}
+
+#[derive(Clone)]
+pub struct PasswordConfig {
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub min_length: usize,
+    pub max_length: usize,
+    pub breached_list_file: Option<String>, // una contraseña por línea
 }
 
 impl AppConfig {
@@ -18,18 +129,161 @@ impl AppConfig {
             .ok()
             .and_then(|v| v.parse::<u16>().ok())
             .unwrap_or(8000);
+        let public_url =
+            env::var("APP_PUBLIC_URL").unwrap_or_else(|_| format!("http://{}:{}", host, port));
 
         Self {
             host,
             port,
             mongodb_uri: env::var("MONGODB_URI").expect("MONGODB_URI is required"),
             mongodb_db: env::var("MONGODB_DB").unwrap_or_else(|_| "pcosew".into()),
-            jwt_secret: env::var("JWT_SECRET").expect("JWT_SECRET is required"),
+            jwt_algorithm: env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".into()),
+            jwt_secret: env::var("JWT_SECRET").ok(),
+            jwt_private_key_file: env::var("JWT_PRIVATE_KEY_FILE").ok(),
+            jwt_public_key_file: env::var("JWT_PUBLIC_KEY_FILE").ok(),
+            jwt_key_id: env::var("JWT_KEY_ID").unwrap_or_else(|_| "default".into()),
+            jwt_previous_public_keys: previous_keys_from_env(),
+            jwt_issuer: env::var("JWT_ISSUER").unwrap_or_else(|_| "pcosew".into()),
+            jwt_audience: env::var("JWT_AUDIENCE").unwrap_or_else(|_| "pcosew-api".into()),
             jwt_exp_minutes: env::var("JWT_EXP_MINUTES")
                 .ok()
                 .and_then(|v| v.parse::<i64>().ok())
                 .unwrap_or(120),
             cors_origin: env::var("CORS_ORIGIN").unwrap_or_else(|_| "http://localhost:5173".into()),
+            trusted_proxies: trusted_proxies_from_env(),
+            oidc_providers: oidc_providers_from_env(),
+            oidc_success_redirect: env::var("OIDC_SUCCESS_REDIRECT").ok(),
+            public_url,
+            smtp: env::var("SMTP_HOST").ok().map(|smtp_host| SmtpConfig {
+                host: smtp_host,
+                port: env_parse("SMTP_PORT", 587),
+                username: env::var("SMTP_USERNAME").ok(),
+                password: env::var("SMTP_PASSWORD").ok(),
+            }),
+            mail_from: env::var("MAIL_FROM")
+                .unwrap_or_else(|_| "PCOSEW <no-reply@pcosew.local>".into()),
+            login_guard: LoginGuardConfig {
+                store: env::var("LOGIN_GUARD_STORE").unwrap_or_else(|_| "memory".into()),
+                max_failures: env_parse("LOGIN_MAX_FAILURES", 5),
+                ip_max_failures: env_parse("LOGIN_IP_MAX_FAILURES", 20),
+                lockout_minutes: env_parse("LOGIN_LOCKOUT_MINUTES", 15),
+                backoff_base_secs: env_parse("LOGIN_BACKOFF_BASE_SECS", 1),
+                backoff_max_secs: env_parse("LOGIN_BACKOFF_MAX_SECS", 300),
+            },
+            password: PasswordConfig {
+                argon2_memory_kib: env_parse("ARGON2_MEMORY_KIB", 19456),
+                argon2_iterations: env_parse("ARGON2_ITERATIONS", 2),
+                argon2_parallelism: env_parse("ARGON2_PARALLELISM", 1),
+                min_length: env_parse("PASSWORD_MIN_LENGTH", 8),
+                max_length: env_parse("PASSWORD_MAX_LENGTH", 128),
+                breached_list_file: env::var("PASSWORD_BREACHED_LIST_FILE").ok(),
+            },
+            bootstrap_admin_email: env::var("BOOTSTRAP_ADMIN_EMAIL")
+                .ok()
+                .map(|e| e.trim().to_lowercase()),
+            impersonation_minutes: env_parse("IMPERSONATION_MINUTES", 15),
+            // 0 = sin límite
+            org_default_quota_bytes: Some(env_parse("ORG_DEFAULT_QUOTA_BYTES", 10_i64 << 30))
+                .filter(|q| *q > 0),
+            webhooks: WebhookConfig {
+                poll_secs: env_parse("WEBHOOK_POLL_SECS", 5),
+                max_attempts: env_parse("WEBHOOK_MAX_ATTEMPTS", 8),
+                retry_base_secs: env_parse("WEBHOOK_RETRY_BASE_SECS", 30),
+                allow_private_targets: env_parse("WEBHOOK_ALLOW_PRIVATE_TARGETS", false),
+            },
+            archive_extract: ExtractConfig {
+                max_entries: env_parse("ARCHIVE_MAX_ENTRIES", 1000),
+                max_total_bytes: env_parse("ARCHIVE_MAX_TOTAL_BYTES", 2_u64 << 30),
+                max_ratio: env_parse("ARCHIVE_MAX_RATIO", 100),
+            },
+            search: SearchConfig {
+                language: env::var("SEARCH_LANGUAGE").unwrap_or_else(|_| "spanish".into()),
+                poll_secs: env_parse("SEARCH_POLL_SECS", 5),
+                max_file_bytes: env_parse("SEARCH_MAX_FILE_BYTES", 50_i64 << 20),
+                max_text_chars: env_parse("SEARCH_MAX_TEXT_CHARS", 200_000),
+            },
+            ocr: env_parse("OCR_ENABLED", false).then(|| OcrConfig {
+                tesseract_bin: env::var("OCR_TESSERACT_BIN").unwrap_or_else(|_| "tesseract".into()),
+                pdftoppm_bin: env::var("OCR_PDFTOPPM_BIN").unwrap_or_else(|_| "pdftoppm".into()),
+                languages: env::var("OCR_LANGUAGES").unwrap_or_else(|_| "spa+eng".into()),
+                dpi: env_parse("OCR_DPI", 300),
+                max_pdf_pages: env_parse("OCR_MAX_PDF_PAGES", 20),
+                timeout_secs: env_parse("OCR_TIMEOUT_SECS", 120),
+            }),
+            office_preview: env_parse("OFFICE_PREVIEW_ENABLED", false).then(|| {
+                OfficePreviewConfig {
+                    soffice_bin: env::var("OFFICE_PREVIEW_SOFFICE_BIN")
+                        .unwrap_or_else(|_| "soffice".into()),
+                    poll_secs: env_parse("OFFICE_PREVIEW_POLL_SECS", 2),
+                    timeout_secs: env_parse("OFFICE_PREVIEW_TIMEOUT_SECS", 120),
+                    max_concurrent: env_parse("OFFICE_PREVIEW_MAX_CONCURRENT", 2),
+                    max_file_bytes: env_parse("OFFICE_PREVIEW_MAX_FILE_BYTES", 50_i64 << 20),
+                }
+            }),
         }
     }
+
+    pub fn oidc_provider(&self, name: &str) -> Option<&OidcProviderConfig> {
+        self.oidc_providers.iter().find(|p| p.name == name)
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+// TRUSTED_PROXIES=127.0.0.1,10.0.0.5
+fn trusted_proxies_from_env() -> Vec<IpAddr> {
+    env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect()
+}
+
+// JWT_PREVIOUS_PUBLIC_KEYS=kid1=/ruta/old1.pem,kid2=/ruta/old2.pem
+fn previous_keys_from_env() -> Vec<(String, String)> {
+    env::var("JWT_PREVIOUS_PUBLIC_KEYS")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|e| !e.trim().is_empty())
+        .map(|entry| {
+            let (kid, path) = entry.split_once('=').unwrap_or_else(|| {
+                panic!("JWT_PREVIOUS_PUBLIC_KEYS entry must be kid=path: {}", entry)
+            });
+            (kid.trim().to_string(), path.trim().to_string())
+        })
+        .collect()
+}
+
+// OIDC_PROVIDERS=google,local -> OIDC_GOOGLE_ISSUER, OIDC_GOOGLE_CLIENT_ID, ...
+fn oidc_providers_from_env() -> Vec<OidcProviderConfig> {
+    let names = env::var("OIDC_PROVIDERS").unwrap_or_default();
+
+    names
+        .split(',')
+        .map(|n| n.trim().to_lowercase())
+        .filter(|n| !n.is_empty())
+        .map(|name| {
+            let prefix = format!("OIDC_{}_", name.to_uppercase());
+            let var = |key: &str| env::var(format!("{}{}", prefix, key));
+
+            OidcProviderConfig {
+                issuer: var("ISSUER")
+                    .unwrap_or_else(|_| panic!("{}ISSUER is required", prefix))
+                    .trim_end_matches('/')
+                    .to_string(),
+                client_id: var("CLIENT_ID")
+                    .unwrap_or_else(|_| panic!("{}CLIENT_ID is required", prefix)),
+                client_secret: var("CLIENT_SECRET").ok(),
+                redirect_uri: var("REDIRECT_URI")
+                    .unwrap_or_else(|_| panic!("{}REDIRECT_URI is required", prefix)),
+                scopes: var("SCOPES").unwrap_or_else(|_| "openid email profile".into()),
+                name,
+            }
+        })
+        .collect()
 }
@@ -1,15 +1,48 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
 use mongodb::{Client, Database};
 
+use crate::{
+    config::AppConfig,
+    utils::{
+        events::EventBus, jwt::JwtKeys, login_guard::LoginGuard, mailer::Mailer,
+        password::Passwords,
+    },
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Database,
+    pub http: reqwest::Client,
+    pub jwt: Arc<JwtKeys>,
+    pub mailer: Arc<Mailer>,
+    pub login_guard: Arc<LoginGuard>,
+    pub passwords: Arc<Passwords>,
+    pub events: Arc<EventBus>,
 }
 
 impl AppState {
-    pub fn new(client: Client, db_name: &str) -> Self {
-        let db = client.database(db_name);
-        Self { db }
+    pub fn new(client: Client, cfg: &AppConfig) -> Self {
+        let db = client.database(&cfg.mongodb_db);
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .expect("Failed to build HTTP client");
+        let jwt = JwtKeys::from_config(cfg).expect("Invalid JWT key configuration");
+        let mailer = Mailer::from_config(cfg).expect("Invalid mail configuration");
+        let login_guard = LoginGuard::new(&cfg.login_guard, &db);
+        let passwords =
+            Passwords::from_config(&cfg.password).expect("Invalid password configuration");
+        Self {
+            db,
+            http,
+            jwt: Arc::new(jwt),
+            mailer: Arc::new(mailer),
+            login_guard: Arc::new(login_guard),
+            passwords: Arc::new(passwords),
+            events: Arc::new(EventBus::new()),
+        }
     }
 }
 
@@ -18,3 +51,9 @@ pub async fn mongo_client(uri: &str) -> Client {
         .await
         .expect("Failed to connect to MongoDB")
 }
+
+// Las fechas de los modelos se serializan como string RFC 3339 (serde de chrono);
+// para `$set` hay que usar el mismo formato o el documento ya no deserializa.
+pub fn bson_time(dt: DateTime<Utc>) -> bson::Bson {
+    bson::to_bson(&dt).unwrap_or(bson::Bson::Null)
+}
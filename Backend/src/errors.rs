@@ -10,9 +10,15 @@ pub enum ApiError {
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Too many requests, retry in {0}s")]
+    TooManyRequests(u64), // segundos para Retry-After
+
     #[error("Internal server error")]
     Internal,
 }
@@ -31,7 +37,11 @@ impl ResponseError for ApiError {
         match self {
             ApiError::BadRequest(_) => HttpResponse::BadRequest().json(body),
             ApiError::Unauthorized(_) => HttpResponse::Unauthorized().json(body),
+            ApiError::Forbidden(_) => HttpResponse::Forbidden().json(body),
             ApiError::NotFound(_) => HttpResponse::NotFound().json(body),
+            ApiError::TooManyRequests(secs) => HttpResponse::TooManyRequests()
+                .insert_header(("Retry-After", secs.to_string()))
+                .json(body),
             ApiError::Internal => HttpResponse::InternalServerError().json(body),
         }
     }
@@ -20,7 +20,22 @@ async fn main() -> std::io::Result<()> {
     let port = cfg.port;
 
     let mongo = db::mongo_client(&cfg.mongodb_uri).await;
-    let state = db::AppState::new(mongo, &cfg.mongodb_db);
+    let state = db::AppState::new(mongo, &cfg);
+    state.login_guard.ensure_indexes().await;
+    utils::audit::ensure_indexes(&state).await;
+    utils::webhooks::ensure_indexes(&state).await;
+    utils::notify::ensure_indexes(&state).await;
+    routes::comments::ensure_indexes(&state).await;
+    utils::search_index::ensure_indexes(&state, &cfg.search).await;
+    utils::office::ensure_indexes(&state).await;
+    utils::webhooks::spawn_worker(state.clone(), cfg.webhooks.clone());
+    utils::search_index::spawn_worker(state.clone(), cfg.search.clone(), cfg.ocr.clone());
+    if let Some(office_cfg) = cfg.office_preview.clone() {
+        utils::office::spawn_worker(state.clone(), office_cfg);
+    }
+    if let Some(email) = cfg.bootstrap_admin_email.as_deref() {
+        routes::auth::bootstrap_admin(&state, email).await;
+    }
 
     println!("PCOSEW Backend running at http://{}:{}", host, port);
 
@@ -34,8 +49,8 @@ async fn main() -> std::io::Result<()> {
                 origin.as_bytes() == b"http://127.0.0.1:5173"
                     || origin.as_bytes() == b"http://localhost:5173"
             })
-            .allowed_methods(vec!["GET", "POST", "PATCH", "DELETE", "OPTIONS"])
-            .allowed_headers(vec!["Authorization", "Content-Type"])
+            .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"])
+            .allowed_headers(vec!["Authorization", "Content-Type", "X-Drop-Password"])
             .allowed_header(header::ACCEPT)
             .allowed_header(header::ORIGIN)
             .max_age(3600);
@@ -56,12 +71,30 @@ async fn main() -> std::io::Result<()> {
                             "register": "POST /api/auth/register",
                             "login": "POST /api/auth/login",
                             "me": "POST /api/auth/me",
+                            "profile": "GET|PATCH|DELETE /api/users/me",
+                            "oidc_login": "GET /api/auth/oidc/{provider}/login",
                             "files_list": "GET /api/files",
-                            "files_upload": "POST /api/files/upload"
+                            "files_upload": "POST /api/files/upload",
+                            "files_archive": "POST /api/files/archive",
+                            "api_keys": "GET|POST /api/api-keys",
+                            "orgs": "GET|POST /api/orgs",
+                            "document_requests": "GET|POST /api/requests",
+                            "folders": "GET|POST /api/folders",
+                            "file_drops": "GET|POST /api/drops",
+                            "webhooks": "GET|POST /api/webhooks",
+                            "events": "GET /api/events/stream",
+                            "notifications": "GET /api/notifications",
+                            "comments": "GET|POST /api/files/{id}/comments",
+                            "search": "GET /api/search?q=",
+                            "file_text": "GET /api/files/{id}/text",
+                            "file_metadata": "GET /api/files/{id}/metadata",
+                            "file_view": "GET /api/files/{id}/view",
+                            "jwks": "GET /.well-known/jwks.json"
                         }
                     }))
                 }),
             )
+            .configure(routes::well_known::configure)
             .service(web::scope("/api").configure(routes::configure))
     })
     .bind((host.as_str(), port))?
@@ -1,46 +1,238 @@
+use std::marker::PhantomData;
+
 use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
-use futures::future::{ready, Ready};
+use bson::{doc, oid::ObjectId};
+use chrono::{DateTime, SubsecRound, Utc};
+use futures::future::LocalBoxFuture;
 
-use crate::{config::AppConfig, errors::ApiError, utils::jwt};
+use crate::{
+    db::{bson_time, AppState},
+    errors::ApiError,
+    models::{
+        api_key::{ApiKeyDoc, ApiScope},
+        role::{Permission, Role},
+        user::User,
+    },
+    utils::{api_key, jwt},
+};
 
 #[derive(Debug, Clone)]
 pub struct AuthUser {
     pub user_id: String,
     pub email: String,
-    pub role: String,
+    pub role: Role,
+    // None = sesión JWT (sin restricciones); Some = autenticado con API key
+    pub scopes: Option<Vec<ApiScope>>,
+    // Id del admin si el token es de suplantación
+    pub impersonator: Option<String>,
+    pub active_org_id: Option<String>,
+}
+
+impl AuthUser {
+    pub fn require_scope(&self, scope: ApiScope) -> Result<(), ApiError> {
+        match &self.scopes {
+            Some(scopes) if !scopes.contains(&scope) => Err(ApiError::Forbidden(format!(
+                "API key lacks scope '{}'",
+                scope.as_str()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn require_permission(&self, permission: Permission) -> Result<(), ApiError> {
+        if !self.role.can(permission) {
+            return Err(ApiError::Forbidden("Insufficient permissions".into()));
+        }
+        Ok(())
+    }
+
+    // Para acciones que no deben poder hacerse con una API key (p.ej. crear más llaves)
+    pub fn require_session(&self) -> Result<(), ApiError> {
+        if self.scopes.is_some() {
+            return Err(ApiError::Forbidden("Not allowed with an API key".into()));
+        }
+        if self.impersonator.is_some() {
+            return Err(ApiError::Forbidden(
+                "Not allowed while impersonating".into(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl FromRequest for AuthUser {
     type Error = ApiError;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        let cfg = req
-            .app_data::<web::Data<AppConfig>>()
+        let state = req
+            .app_data::<web::Data<AppState>>()
             .map(|d| d.get_ref().clone());
 
-        let Some(cfg) = cfg else {
-            return ready(Err(ApiError::Internal));
-        };
-
         let auth = req
             .headers()
             .get("Authorization")
             .and_then(|h| h.to_str().ok())
-            .unwrap_or("");
+            .unwrap_or("")
+            .to_string();
 
-        if !auth.starts_with("Bearer ") {
-            return ready(Err(ApiError::Unauthorized("Missing Bearer token".into())));
-        }
+        Box::pin(async move {
+            let Some(state) = state else {
+                return Err(ApiError::Internal);
+            };
+            authenticate(&state, &auth).await
+        })
+    }
+}
 
-        let token = auth.trim_start_matches("Bearer ").trim();
-        match jwt::verify_jwt(token, &cfg.jwt_secret) {
-            Ok(claims) => ready(Ok(AuthUser {
-                user_id: claims.sub,
-                email: claims.email,
-                role: claims.role,
-            })),
-            Err(_) => ready(Err(ApiError::Unauthorized("Invalid token".into()))),
-        }
+// Valida el header Authorization; también lo usan las conexiones largas para revalidarse
+pub(crate) async fn authenticate(state: &AppState, auth: &str) -> Result<AuthUser, ApiError> {
+    if let Some(key) = auth.strip_prefix("ApiKey ") {
+        return from_api_key(state, key.trim()).await;
+    }
+
+    if !auth.starts_with("Bearer ") {
+        return Err(ApiError::Unauthorized("Missing Bearer token".into()));
+    }
+
+    let token = auth.trim_start_matches("Bearer ").trim();
+    let invalid = || ApiError::Unauthorized("Invalid token".into());
+    let claims = jwt::verify_jwt(&state.jwt, token).map_err(|_| invalid())?;
+
+    // Se consulta el usuario para respetar bloqueos y cambios de rol al momento
+    let user = load_active_user(state, &claims.sub).await?;
+
+    let issued_at = DateTime::from_timestamp(claims.iat as i64, 0).ok_or_else(invalid)?;
+    if user
+        .tokens_valid_after
+        .is_some_and(|after| issued_at < after.trunc_subsecs(0))
+    {
+        return Err(ApiError::Unauthorized("Session revoked".into()));
+    }
+
+    Ok(AuthUser {
+        user_id: claims.sub,
+        email: user.email,
+        role: user.role,
+        scopes: None,
+        impersonator: claims.act,
+        active_org_id: user.active_org_id,
+    })
+}
+
+async fn from_api_key(state: &AppState, key: &str) -> Result<AuthUser, ApiError> {
+    let invalid = || ApiError::Unauthorized("Invalid API key".into());
+
+    let keys = state.db.collection::<ApiKeyDoc>("api_keys");
+    let now = Utc::now();
+
+    let found = keys
+        .find_one(
+            doc! { "key_hash": api_key::hash_key(key), "revoked_at": null },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo find_one error (api key auth): {:?}", e);
+            ApiError::Internal
+        })?
+        .ok_or_else(invalid)?;
+
+    if found.expires_at.is_some_and(|exp| exp <= now) {
+        return Err(ApiError::Unauthorized("API key expired".into()));
+    }
+
+    let user = load_active_user(state, &found.user_id).await?;
+
+    // Registro de último uso (si falla no bloquea la petición)
+    if let Err(e) = keys
+        .update_one(
+            doc! { "_id": found.id },
+            doc! { "$set": { "last_used_at": bson_time(now) } },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_one error (api key last_used_at): {:?}", e);
+    }
+
+    Ok(AuthUser {
+        user_id: user.id.to_hex(),
+        email: user.email,
+        role: user.role,
+        scopes: Some(found.scopes),
+        impersonator: None,
+        active_org_id: user.active_org_id,
+    })
+}
+
+async fn load_active_user(state: &AppState, user_id: &str) -> Result<User, ApiError> {
+    let invalid = || ApiError::Unauthorized("Invalid token".into());
+    let id = ObjectId::parse_str(user_id).map_err(|_| invalid())?;
+
+    let user = state
+        .db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo find_one error (auth user): {:?}", e);
+            ApiError::Internal
+        })?
+        .ok_or_else(invalid)?;
+
+    if user.disabled {
+        return Err(ApiError::Forbidden("Account disabled".into()));
+    }
+    Ok(user)
+}
+
+// Guard de ruta: `RequireRole<Admin>` solo deja pasar al rol indicado (y a admin)
+pub trait RoleMarker {
+    const ROLE: Role;
+}
+
+pub struct Admin;
+pub struct Colaborador;
+
+impl RoleMarker for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+impl RoleMarker for Colaborador {
+    const ROLE: Role = Role::Colaborador;
+}
+
+pub struct RequireRole<R: RoleMarker> {
+    pub user: AuthUser,
+    _role: PhantomData<R>,
+}
+
+impl<R: RoleMarker + 'static> FromRequest for RequireRole<R> {
+    type Error = ApiError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let auth = AuthUser::from_request(req, payload);
+
+        Box::pin(async move {
+            let user = auth.await?;
+
+            if user.role != R::ROLE && user.role != Role::Admin {
+                return Err(ApiError::Forbidden(format!(
+                    "Requires role '{}'",
+                    R::ROLE.as_str()
+                )));
+            }
+            // Con API key, las rutas de admin además requieren el scope "admin"
+            if R::ROLE == Role::Admin {
+                user.require_scope(ApiScope::Admin)?;
+            }
+
+            Ok(RequireRole {
+                user,
+                _role: PhantomData,
+            })
+        })
     }
 }
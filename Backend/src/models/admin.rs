@@ -0,0 +1,75 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{role::Role, user::User};
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUsersQuery {
+    pub q: Option<String>, // busca en nombre y email
+    pub role: Option<Role>,
+    pub disabled: Option<bool>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUserOut {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub role: Role,
+    pub disabled: bool,
+    pub password_reset_required: bool,
+    pub has_password: bool,
+    pub identities: Vec<String>, // proveedores OIDC vinculados
+    pub storage_bytes: i64,
+    pub file_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AdminUserOut {
+    pub fn new(u: User, storage_bytes: i64, file_count: i64) -> Self {
+        Self {
+            id: u.id.to_hex(),
+            name: u.name,
+            email: u.email,
+            role: u.role,
+            disabled: u.disabled,
+            password_reset_required: u.password_reset.as_ref().is_some_and(|r| r.forced),
+            has_password: u.password_hash.is_some(),
+            identities: u.identities.into_iter().map(|i| i.provider).collect(),
+            storage_bytes,
+            file_count,
+            created_at: u.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImpersonateDto {
+    pub reason: Option<String>,
+}
+
+// Registro de cada token de suplantación emitido
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImpersonationDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub admin_id: String,
+    pub target_id: String,
+    pub jti: String,
+    pub reason: Option<String>,
+    pub ip: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,87 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    #[serde(rename = "files:read")]
+    FilesRead,
+    #[serde(rename = "files:write")]
+    FilesWrite,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::FilesRead => "files:read",
+            ApiScope::FilesWrite => "files:write",
+            ApiScope::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKeyDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub user_id: String,
+    pub name: String,
+    pub prefix: String,   // primeros caracteres, para que el usuario la reconozca
+    pub key_hash: String, // sha256 hex de la llave completa (nunca se guarda en claro)
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKeyDto {
+    #[validate(length(min = 1, max = 100, message = "name required"))]
+    pub name: String,
+
+    #[serde(default)]
+    pub scopes: Vec<ApiScope>,
+
+    // Sin valor = no expira
+    #[validate(range(min = 1, max = 3650, message = "expires_in_days must be 1..3650"))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyOut {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKeyDoc> for ApiKeyOut {
+    fn from(k: ApiKeyDoc) -> Self {
+        Self {
+            id: k.id.to_hex(),
+            name: k.name,
+            prefix: k.prefix,
+            scopes: k.scopes,
+            expires_at: k.expires_at,
+            last_used_at: k.last_used_at,
+            revoked_at: k.revoked_at,
+            created_at: k.created_at,
+        }
+    }
+}
+
+// Solo se regresa una vez, al crearla
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    pub api_key: ApiKeyOut,
+}
@@ -0,0 +1,132 @@
+use bson::{oid::ObjectId, Document};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    #[serde(rename = "auth.login")]
+    LoginSuccess,
+    #[serde(rename = "auth.login_failed")]
+    LoginFailure,
+    #[serde(rename = "auth.register")]
+    Register,
+    #[serde(rename = "file.upload")]
+    FileUpload,
+    #[serde(rename = "file.download")]
+    FileDownload,
+    #[serde(rename = "file.rename")]
+    FileRename,
+    #[serde(rename = "file.share")]
+    FileShare, // visibilidad -> public
+    #[serde(rename = "file.unshare")]
+    FileUnshare, // visibilidad -> private
+    #[serde(rename = "file.delete")]
+    FileDelete,
+    #[serde(rename = "admin.update_role")]
+    AdminUpdateRole,
+    #[serde(rename = "admin.disable_user")]
+    AdminDisableUser,
+    #[serde(rename = "admin.enable_user")]
+    AdminEnableUser,
+    #[serde(rename = "admin.force_password_reset")]
+    AdminForcePasswordReset,
+    #[serde(rename = "admin.impersonate")]
+    AdminImpersonate,
+    #[serde(rename = "admin.unlock_user")]
+    AdminUnlockUser,
+    #[serde(rename = "admin.delete_user")]
+    AdminDeleteUser,
+    #[serde(rename = "account.change_email")]
+    AccountChangeEmail,
+    #[serde(rename = "account.change_password")]
+    AccountChangePassword,
+    #[serde(rename = "account.delete")]
+    AccountDelete,
+    #[serde(rename = "api_key.create")]
+    ApiKeyCreate,
+    #[serde(rename = "api_key.revoke")]
+    ApiKeyRevoke,
+    #[serde(rename = "org.update")]
+    OrgUpdate, // nombre o cuota
+    #[serde(rename = "org.invite")]
+    OrgInvite,
+    #[serde(rename = "org.revoke_invitation")]
+    OrgRevokeInvitation,
+    #[serde(rename = "org.join")]
+    OrgJoin, // invitación aceptada
+    #[serde(rename = "org.update_member")]
+    OrgUpdateMember,
+    #[serde(rename = "org.remove_member")]
+    OrgRemoveMember,
+    #[serde(rename = "webhook.create")]
+    WebhookCreate,
+    #[serde(rename = "webhook.update")]
+    WebhookUpdate,
+    #[serde(rename = "webhook.delete")]
+    WebhookDelete,
+}
+
+// Colección append-only: nunca se actualiza ni se borra desde la API
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEventDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub action: AuditAction,
+    pub actor_id: Option<String>, // None = anónimo (enlaces públicos, login fallido)
+    pub actor_email: Option<String>,
+    pub impersonator_id: Option<String>,
+    pub target_type: Option<String>, // "user" | "file" | "api_key" | "org" | "webhook"
+    pub target_id: Option<String>,
+    pub details: Option<Document>,
+    pub ip: String,
+    pub user_agent: Option<String>,
+    // Fecha BSON real para poder filtrar por rango
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventOut {
+    pub id: String,
+    pub action: AuditAction,
+    pub actor_id: Option<String>,
+    pub actor_email: Option<String>,
+    pub impersonator_id: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub details: Option<Document>,
+    pub ip: String,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AuditEventDoc> for AuditEventOut {
+    fn from(e: AuditEventDoc) -> Self {
+        Self {
+            id: e.id.to_hex(),
+            action: e.action,
+            actor_id: e.actor_id,
+            actor_email: e.actor_email,
+            impersonator_id: e.impersonator_id,
+            target_type: e.target_type,
+            target_id: e.target_id,
+            details: e.details,
+            ip: e.ip,
+            user_agent: e.user_agent,
+            created_at: e.created_at,
+        }
+    }
+}
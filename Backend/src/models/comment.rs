@@ -0,0 +1,85 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::models::file::ActivityActor;
+
+// Ubicación opcional del comentario: página (PDF) y/o región en coordenadas
+// relativas 0..1 respecto al tamaño de la página o imagen
+#[derive(Debug, Serialize, Deserialize, Clone, Validate)]
+#[validate(schema(function = "validate_region"))]
+pub struct CommentAnchor {
+    #[validate(range(min = 1, message = "page must be >= 1"))]
+    pub page: Option<u32>,
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+}
+
+fn validate_region(a: &CommentAnchor) -> Result<(), ValidationError> {
+    let unit = |v: f64| (0.0..=1.0).contains(&v);
+    match (a.x, a.y, a.width, a.height) {
+        (None, None, None, None) => Ok(()),
+        (Some(x), Some(y), Some(w), Some(h))
+            if unit(x) && unit(y) && unit(w) && unit(h) && x + w <= 1.0 && y + h <= 1.0 =>
+        {
+            Ok(())
+        }
+        // La región va completa y dentro de la página, o no va
+        _ => Err(ValidationError::new(
+            "region needs x, y, width and height within 0..1",
+        )),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommentDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub file_id: String,
+    pub author_id: String,
+    pub parent_id: Option<String>, // None = inicia un hilo
+    pub body: String,
+    #[serde(default)]
+    pub mentions: Vec<String>, // user ids mencionados con @email
+    pub anchor: Option<CommentAnchor>,
+
+    // Borrado con respuestas: se conserva el hueco para no romper el hilo
+    #[serde(default)]
+    pub deleted: bool,
+
+    pub edited_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCommentDto {
+    #[validate(length(min = 1, max = 5000, message = "body must be 1-5000 chars"))]
+    pub body: String,
+    pub parent_id: Option<String>,
+    #[validate(nested)]
+    pub anchor: Option<CommentAnchor>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateCommentDto {
+    #[validate(length(min = 1, max = 5000, message = "body must be 1-5000 chars"))]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommentOut {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub author: Option<ActivityActor>, // None = comentario borrado
+    pub body: Option<String>,
+    pub mentions: Vec<String>,
+    pub anchor: Option<CommentAnchor>,
+    pub deleted: bool,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub replies: Vec<CommentOut>,
+}
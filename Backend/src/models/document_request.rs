@@ -0,0 +1,258 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestStatus {
+    Pending,
+    Submitted,
+    Approved,
+    Rejected,
+    // Nunca se guarda: se calcula al leer cuando vence due_date sin aprobación
+    Overdue,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmissionStatus {
+    Submitted,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Submission {
+    pub id: String,
+    pub file_id: String,
+    pub file_name: String,
+    pub mime: String,
+    pub size: i64,
+    pub status: SubmissionStatus,
+    pub comment: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentRequestDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub collaborator_id: String, // colaborador que pide los documentos
+    pub client_id: String,       // cliente que los entrega
+    pub title: String,
+    pub description: Option<String>,
+    // Extensiones ("pdf") o tipos MIME ("image/png", "image/*"); vacío = cualquiera
+    pub required_types: Vec<String>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub status: RequestStatus,
+    pub submissions: Vec<Submission>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DocumentRequestDoc {
+    pub fn effective_status(&self, now: DateTime<Utc>) -> RequestStatus {
+        let open = matches!(
+            self.status,
+            RequestStatus::Pending | RequestStatus::Rejected
+        );
+        if open && self.due_date.is_some_and(|d| d < now) {
+            return RequestStatus::Overdue;
+        }
+        self.status
+    }
+
+    // Estado según las entregas: con una aprobada queda aprobada; si no, espera revisión
+    // mientras quede alguna sin revisar
+    pub fn status_from_submissions(&self) -> RequestStatus {
+        let has = |status| self.submissions.iter().any(|s| s.status == status);
+        if has(SubmissionStatus::Approved) {
+            RequestStatus::Approved
+        } else if has(SubmissionStatus::Submitted) {
+            RequestStatus::Submitted
+        } else if has(SubmissionStatus::Rejected) {
+            RequestStatus::Rejected
+        } else {
+            RequestStatus::Pending
+        }
+    }
+
+    pub fn accepts(&self, filename: &str, mime: &str) -> bool {
+        if self.required_types.is_empty() {
+            return true;
+        }
+
+        let ext = filename
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_lowercase())
+            .unwrap_or_default();
+        let mime = mime.to_lowercase();
+
+        self.required_types.iter().any(|t| {
+            let t = t.trim().trim_start_matches('.').to_lowercase();
+            match t.strip_suffix("/*") {
+                Some(major) => mime.starts_with(&format!("{}/", major)),
+                None if t.contains('/') => mime == t,
+                None => ext == t,
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateDocumentRequestDto {
+    pub client_id: Option<String>,
+    #[validate(email(message = "invalid email"))]
+    pub client_email: Option<String>,
+
+    #[validate(length(min = 2, max = 200, message = "title must be 2..200 chars"))]
+    pub title: String,
+
+    #[validate(length(max = 2000, message = "description must be at most 2000 chars"))]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    #[validate(length(max = 20, message = "at most 20 required types"))]
+    pub required_types: Vec<String>,
+
+    pub due_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentRequestsQuery {
+    pub status: Option<RequestStatus>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReviewSubmissionDto {
+    pub approve: bool,
+
+    #[validate(length(max = 2000, message = "comment must be at most 2000 chars"))]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocumentRequestOut {
+    pub id: String,
+    pub collaborator_id: String,
+    pub client_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub required_types: Vec<String>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub status: RequestStatus,
+    pub submissions: Vec<Submission>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<DocumentRequestDoc> for DocumentRequestOut {
+    fn from(r: DocumentRequestDoc) -> Self {
+        Self {
+            id: r.id.to_hex(),
+            status: r.effective_status(Utc::now()),
+            collaborator_id: r.collaborator_id,
+            client_id: r.client_id,
+            title: r.title,
+            description: r.description,
+            required_types: r.required_types,
+            due_date: r.due_date,
+            submissions: r.submissions,
+            created_at: r.created_at,
+            updated_at: r.updated_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn request(required_types: &[&str]) -> DocumentRequestDoc {
+        let now = Utc::now();
+        DocumentRequestDoc {
+            id: ObjectId::new(),
+            collaborator_id: "c".into(),
+            client_id: "k".into(),
+            title: "Contrato".into(),
+            description: None,
+            required_types: required_types.iter().map(|t| t.to_string()).collect(),
+            due_date: None,
+            status: RequestStatus::Pending,
+            submissions: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn submission(status: SubmissionStatus) -> Submission {
+        Submission {
+            id: ObjectId::new().to_hex(),
+            file_id: ObjectId::new().to_hex(),
+            file_name: "a.pdf".into(),
+            mime: "application/pdf".into(),
+            size: 1,
+            status,
+            comment: None,
+            submitted_at: Utc::now(),
+            reviewed_at: None,
+        }
+    }
+
+    #[test]
+    fn accepts_extensions_and_mime_types() {
+        assert!(request(&[]).accepts("x.exe", "application/octet-stream"));
+
+        let r = request(&[".PDF", "image/*", "text/csv"]);
+        assert!(r.accepts("Contrato.pdf", "application/octet-stream"));
+        assert!(r.accepts("foto", "image/jpeg"));
+        assert!(r.accepts("datos.txt", "text/csv"));
+        assert!(!r.accepts("datos.txt", "text/plain"));
+        assert!(!r.accepts("pdf", "application/octet-stream"));
+        assert!(!r.accepts("x.png", "imagenes/png"));
+    }
+
+    #[test]
+    fn overdue_only_while_open() {
+        let now = Utc::now();
+        let mut r = request(&[]);
+        assert_eq!(r.effective_status(now), RequestStatus::Pending);
+
+        r.due_date = Some(now - Duration::days(1));
+        assert_eq!(r.effective_status(now), RequestStatus::Overdue);
+        r.status = RequestStatus::Rejected;
+        assert_eq!(r.effective_status(now), RequestStatus::Overdue);
+        r.status = RequestStatus::Submitted;
+        assert_eq!(r.effective_status(now), RequestStatus::Submitted);
+        r.status = RequestStatus::Approved;
+        assert_eq!(r.effective_status(now), RequestStatus::Approved);
+
+        r.status = RequestStatus::Pending;
+        r.due_date = Some(now + Duration::days(1));
+        assert_eq!(r.effective_status(now), RequestStatus::Pending);
+    }
+
+    #[test]
+    fn status_follows_submissions() {
+        let mut r = request(&[]);
+        assert_eq!(r.status_from_submissions(), RequestStatus::Pending);
+
+        r.submissions = vec![
+            submission(SubmissionStatus::Submitted),
+            submission(SubmissionStatus::Submitted),
+        ];
+        assert_eq!(r.status_from_submissions(), RequestStatus::Submitted);
+
+        // Aprobar una y rechazar la otra deja la solicitud aprobada
+        r.submissions[0].status = SubmissionStatus::Approved;
+        r.submissions[1].status = SubmissionStatus::Rejected;
+        assert_eq!(r.status_from_submissions(), RequestStatus::Approved);
+
+        r.submissions[0].status = SubmissionStatus::Rejected;
+        assert_eq!(r.status_from_submissions(), RequestStatus::Rejected);
+    }
+}
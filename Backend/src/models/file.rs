@@ -1,30 +1,69 @@
-use bson::oid::ObjectId;
+use bson::{oid::ObjectId, Document};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::{audit::AuditAction, folder::FolderOut};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileDoc {
     #[serde(rename = "_id")]
     pub id: ObjectId,
 
-    pub owner_id: String, // AuthUser.user_id (hex)
+    pub owner_id: String, // AuthUser.user_id (hex) de quien lo subió
+    // Si existe, el archivo pertenece a la organización y no al usuario
+    #[serde(default)]
+    pub org_id: Option<String>,
+    #[serde(default)]
+    pub folder_id: Option<String>, // None = raíz
     pub original_name: String,
     pub stored_name: String,
     pub mime: String,
     pub size: i64,
     pub visibility: String, // "private" | "public"
+    #[serde(default)]
+    pub download_count: i64,
+    #[serde(default)]
+    pub last_downloaded_at: Option<DateTime<Utc>>,
+    // Metadatos leídos al subir una foto; se conservan aunque se borren del archivo
+    #[serde(default)]
+    pub image_meta: Option<ImageMetadata>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ImageMetadata {
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens_model: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub taken_at: Option<String>, // hora local de la cámara, con zona solo si la trae
+    pub orientation: Option<u16>,
+    pub has_location: bool, // las coordenadas nunca se guardan
+    pub stripped: bool,     // EXIF/XMP/IPTC ya no están en el archivo
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageMetadataOut {
+    pub file_id: String,
+    #[serde(flatten)]
+    pub meta: ImageMetadata,
+}
+
 #[derive(Debug, Serialize)]
 pub struct FileOut {
     pub id: String,
     pub owner_id: String,
+    pub org_id: Option<String>,
+    pub folder_id: Option<String>,
     pub original_name: String,
     pub mime: String,
     pub size: i64,
     pub visibility: String,
+    pub download_count: i64,
+    pub last_downloaded_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -34,17 +73,73 @@ impl From<FileDoc> for FileOut {
         Self {
             id: f.id.to_hex(),
             owner_id: f.owner_id,
+            org_id: f.org_id,
+            folder_id: f.folder_id,
             original_name: f.original_name,
             mime: f.mime,
             size: f.size,
             visibility: f.visibility,
+            download_count: f.download_count,
+            last_downloaded_at: f.last_downloaded_at,
             created_at: f.created_at,
             updated_at: f.updated_at,
         }
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UploadQuery {
+    pub folder_id: Option<String>,
+    pub extract: Option<bool>, // descomprimir ZIP/TAR en una carpeta nueva
+    // Quitar EXIF/XMP/IPTC de las fotos; None = lo que diga la preferencia del usuario
+    pub strip_metadata: Option<bool>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateVisibilityDto {
     pub visibility: String, // "private" | "public"
 }
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RenameFileDto {
+    #[validate(length(min = 1, max = 255, message = "name must be 1..255 chars"))]
+    pub name: String,
+}
+
+// Descarga en ZIP: lista de archivos o una carpeta completa (con subcarpetas)
+#[derive(Debug, Deserialize, Validate)]
+pub struct ArchiveDto {
+    #[validate(length(min = 1, max = 1000, message = "file_ids must have 1..1000 items"))]
+    pub file_ids: Option<Vec<String>>,
+    pub folder_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityActor {
+    pub id: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+// Entrada del timeline de un archivo; actor None = acceso anónimo (enlace público)
+#[derive(Debug, Serialize)]
+pub struct FileActivityOut {
+    pub action: AuditAction,
+    pub actor: Option<ActivityActor>,
+    pub details: Option<Document>,
+    pub at: DateTime<Utc>,
+}
+
+// Entrada de un archivo comprimido que no se extrajo
+#[derive(Debug, Serialize, Clone)]
+pub struct SkippedEntry {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExtractedArchiveOut {
+    pub folder: FolderOut,
+    pub files: Vec<FileOut>,
+    pub skipped: Vec<SkippedEntry>,
+}
@@ -0,0 +1,104 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+// Enlace anónimo de solo subida hacia una carpeta del dueño
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileDropDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub owner_id: String,
+    #[serde(default)]
+    pub org_id: Option<String>,
+    pub folder_id: Option<String>, // None = raíz del espacio
+    pub label: String,
+    pub token_hash: String, // sha256 del token del enlace
+    pub password_hash: Option<String>,
+    pub max_files: Option<i64>,
+    pub max_file_bytes: Option<i64>,
+    pub files_received: i64,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FileDropDoc {
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none()
+            && self.expires_at > now
+            && self.max_files.is_none_or(|max| self.files_received < max)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateDropDto {
+    #[validate(length(min = 1, max = 100, message = "label must be 1..100 chars"))]
+    pub label: String,
+
+    pub folder_id: Option<String>,
+
+    #[validate(range(min = 1, max = 720, message = "expires_in_hours must be 1..720"))]
+    pub expires_in_hours: Option<i64>,
+
+    #[validate(range(min = 1, max = 1000, message = "max_files must be 1..1000"))]
+    pub max_files: Option<i64>,
+
+    #[validate(range(min = 1, message = "max_file_bytes must be > 0"))]
+    pub max_file_bytes: Option<i64>,
+
+    #[validate(length(min = 4, max = 128, message = "password must be 4..128 chars"))]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DropOut {
+    pub id: String,
+    pub label: String,
+    pub org_id: Option<String>,
+    pub folder_id: Option<String>,
+    pub password_protected: bool,
+    pub max_files: Option<i64>,
+    pub max_file_bytes: Option<i64>,
+    pub files_received: i64,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<FileDropDoc> for DropOut {
+    fn from(d: FileDropDoc) -> Self {
+        Self {
+            id: d.id.to_hex(),
+            label: d.label,
+            org_id: d.org_id,
+            folder_id: d.folder_id,
+            password_protected: d.password_hash.is_some(),
+            max_files: d.max_files,
+            max_file_bytes: d.max_file_bytes,
+            files_received: d.files_received,
+            expires_at: d.expires_at,
+            revoked_at: d.revoked_at,
+            created_at: d.created_at,
+        }
+    }
+}
+
+// Respuesta al crear: el enlace solo se muestra esta vez
+#[derive(Debug, Serialize)]
+pub struct CreatedDrop {
+    pub url: String,
+    pub token: String,
+    pub drop: DropOut,
+}
+
+// Lo único que ve quien sube: nada del contenido de la carpeta
+#[derive(Debug, Serialize)]
+pub struct PublicDropInfo {
+    pub label: String,
+    pub password_required: bool,
+    pub remaining_files: Option<i64>,
+    pub max_file_bytes: Option<i64>,
+    pub expires_at: DateTime<Utc>,
+}
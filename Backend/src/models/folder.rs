@@ -0,0 +1,57 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FolderDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub owner_id: String,
+    // Igual que en FileDoc: si existe, la carpeta es de la organización
+    #[serde(default)]
+    pub org_id: Option<String>,
+    pub parent_id: Option<String>, // None = raíz
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateFolderDto {
+    #[validate(length(min = 1, max = 200, message = "name must be 1..200 chars"))]
+    pub name: String,
+
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FolderQuery {
+    pub folder_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FolderOut {
+    pub id: String,
+    pub owner_id: String,
+    pub org_id: Option<String>,
+    pub parent_id: Option<String>,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<FolderDoc> for FolderOut {
+    fn from(f: FolderDoc) -> Self {
+        Self {
+            id: f.id.to_hex(),
+            owner_id: f.owner_id,
+            org_id: f.org_id,
+            parent_id: f.parent_id,
+            name: f.name,
+            created_at: f.created_at,
+            updated_at: f.updated_at,
+        }
+    }
+}
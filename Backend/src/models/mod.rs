@@ -1,2 +1,16 @@
-pub mod user;
+pub mod admin;
+pub mod api_key;
+pub mod audit;
+pub mod comment;
+pub mod document_request;
 pub mod file;
+pub mod file_drop;
+pub mod folder;
+pub mod notification;
+pub mod oidc;
+pub mod organization;
+pub mod preview;
+pub mod role;
+pub mod search;
+pub mod user;
+pub mod webhook;
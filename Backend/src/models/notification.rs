@@ -0,0 +1,99 @@
+use bson::{oid::ObjectId, Document};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    FileReceived,     // llegó un archivo por un enlace de subida
+    RequestCreated,   // un colaborador te pidió documentos
+    RequestSubmitted, // el cliente entregó un archivo
+    RequestReviewed,  // tu entrega fue aprobada o rechazada
+    Comment,          // comentario en tu archivo o respuesta a tu comentario
+    Mention,          // te mencionaron en un comentario
+    QuotaWarning,     // la organización se está quedando sin espacio
+    FilesTransferred, // alguien que borró su cuenta te dejó sus archivos
+}
+
+// Preferencias de correo; las notificaciones dentro de la app siempre se guardan
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct NotificationPrefs {
+    pub email_enabled: bool,
+    // Tipos que no se mandan por correo aunque email_enabled sea true
+    #[serde(default)]
+    pub email_muted: Vec<NotificationKind>,
+}
+
+impl Default for NotificationPrefs {
+    fn default() -> Self {
+        Self {
+            email_enabled: true,
+            email_muted: Vec::new(),
+        }
+    }
+}
+
+impl NotificationPrefs {
+    pub fn wants_email(&self, kind: NotificationKind) -> bool {
+        self.email_enabled && !self.email_muted.contains(&kind)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateNotificationPrefsDto {
+    pub email_enabled: Option<bool>,
+    #[validate(length(max = 20, message = "too many kinds"))]
+    pub email_muted: Option<Vec<NotificationKind>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub user_id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub link: Option<String>, // ruta de la API relacionada
+    pub data: Option<Document>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationsQuery {
+    pub unread: Option<bool>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationOut {
+    pub id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub link: Option<String>,
+    pub data: Option<Document>,
+    pub read: bool,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<NotificationDoc> for NotificationOut {
+    fn from(n: NotificationDoc) -> Self {
+        Self {
+            id: n.id.to_hex(),
+            kind: n.kind,
+            title: n.title,
+            body: n.body,
+            link: n.link,
+            data: n.data,
+            read: n.read_at.is_some(),
+            read_at: n.read_at,
+            created_at: n.created_at,
+        }
+    }
+}
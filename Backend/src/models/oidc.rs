@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Estado temporal entre /login y /callback (authorization code + PKCE)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OidcLoginState {
+    #[serde(rename = "_id")]
+    pub state: String,
+
+    pub provider: String,
+    pub code_verifier: String,
+    pub nonce: String,
+    pub created_at: DateTime<Utc>,
+    // Fecha BSON real: el índice TTL ignora fechas guardadas como string
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
@@ -0,0 +1,161 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl OrgRole {
+    // Owner y admin administran miembros y cualquier archivo de la organización
+    pub fn can_manage(&self) -> bool {
+        matches!(self, OrgRole::Owner | OrgRole::Admin)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrgMember {
+    pub user_id: String,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrganizationDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub name: String,
+    pub members: Vec<OrgMember>,
+    pub quota_bytes: Option<i64>, // None = sin límite
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OrganizationDoc {
+    pub fn role_of(&self, user_id: &str) -> Option<OrgRole> {
+        self.members
+            .iter()
+            .find(|m| m.user_id == user_id)
+            .map(|m| m.role)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvitationDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub org_id: String,
+    pub email: String,
+    pub role: OrgRole,
+    pub token_hash: String,
+    pub invited_by: String,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateOrgDto {
+    #[validate(length(min = 2, max = 100, message = "name must be 2..100 chars"))]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateOrgDto {
+    #[validate(length(min = 2, max = 100, message = "name must be 2..100 chars"))]
+    pub name: Option<String>,
+
+    // Solo un admin de la plataforma puede cambiar la cuota
+    #[validate(range(min = 0, message = "quota_bytes must be >= 0"))]
+    pub quota_bytes: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct InviteDto {
+    #[validate(email(message = "invalid email"))]
+    pub email: String,
+
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptInvitationDto {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberDto {
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwitchOrgDto {
+    pub org_id: Option<String>, // None = espacio personal
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgMemberOut {
+    pub user_id: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgOut {
+    pub id: String,
+    pub name: String,
+    pub my_role: Option<OrgRole>,
+    pub quota_bytes: Option<i64>,
+    pub used_bytes: i64,
+    pub member_count: usize,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OrgOut {
+    pub fn new(o: &OrganizationDoc, user_id: &str, used_bytes: i64) -> Self {
+        Self {
+            id: o.id.to_hex(),
+            name: o.name.clone(),
+            my_role: o.role_of(user_id),
+            quota_bytes: o.quota_bytes,
+            used_bytes,
+            member_count: o.members.len(),
+            created_at: o.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvitationOut {
+    pub id: String,
+    pub org_id: String,
+    pub email: String,
+    pub role: OrgRole,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<InvitationDoc> for InvitationOut {
+    fn from(i: InvitationDoc) -> Self {
+        Self {
+            id: i.id.to_hex(),
+            org_id: i.org_id,
+            email: i.email,
+            role: i.role,
+            expires_at: i.expires_at,
+            accepted_at: i.accepted_at,
+            created_at: i.created_at,
+        }
+    }
+}
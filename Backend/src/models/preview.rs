@@ -0,0 +1,35 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+// PDF derivado de un documento de oficina; mismo _id que el FileDoc
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreviewDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub source_stored_name: String,
+    pub pdf_stored_name: Option<String>, // listo cuando status = ready
+    pub status: PreviewStatus,
+    pub attempts: u32,
+    // Fecha BSON real: el worker consulta por rango
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub next_attempt_at: DateTime<Utc>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Respuesta de /view mientras el PDF todavía se genera
+#[derive(Debug, Serialize)]
+pub struct PreviewPendingOut {
+    pub status: PreviewStatus,
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Cliente,
+    Colaborador,
+    Admin,
+}
+
+// El acceso a archivos no depende del rol: lo deciden el dueño, la organización y los
+// scopes de la API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ManageUsers,
+    AssignRoles,
+    CreateOrganizations,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Cliente => "cliente",
+            Role::Colaborador => "colaborador",
+            Role::Admin => "admin",
+        }
+    }
+
+    // Matriz de permisos por rol
+    pub fn permissions(&self) -> &'static [Permission] {
+        use Permission::*;
+        match self {
+            Role::Cliente => &[],
+            Role::Colaborador => &[CreateOrganizations],
+            Role::Admin => &[ManageUsers, AssignRoles, CreateOrganizations],
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_matrix() {
+        assert!(!Role::Cliente.can(Permission::CreateOrganizations));
+        assert!(Role::Colaborador.can(Permission::CreateOrganizations));
+        assert!(!Role::Colaborador.can(Permission::ManageUsers));
+        assert!(Role::Admin.can(Permission::ManageUsers));
+        assert!(Role::Admin.can(Permission::AssignRoles));
+    }
+}
@@ -0,0 +1,82 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::file::FileOut;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextStatus {
+    Pending,     // en cola para extraer
+    Indexed,     // hay texto buscable
+    Empty,       // se pudo leer pero no tiene texto
+    Unsupported, // formato sin extractor
+    Skipped,     // demasiado grande para extraer
+    Failed,      // se agotaron los reintentos
+}
+
+// Texto extraído de un archivo; mismo _id que el FileDoc. Dueño, organización y nombre van
+// copiados para que la búsqueda filtre por acceso en el mismo $match del índice de texto.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileTextDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub owner_id: String,
+    pub org_id: Option<String>,
+    pub name: String,
+    pub mime: String,
+    pub stored_name: String,
+    pub size: i64,
+    pub text: Option<String>,
+    // Se pasó por OCR; si hay texto, salió de ahí y no del propio archivo
+    #[serde(default)]
+    pub ocr: bool,
+    pub status: TextStatus,
+    pub attempts: u32,
+    // Fecha BSON real: el worker consulta por rango
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub next_attempt_at: DateTime<Utc>,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SearchQuery {
+    #[validate(length(min = 1, max = 200, message = "q must be 1-200 chars"))]
+    pub q: String,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub file: FileOut,
+    pub score: f64,
+    // Fragmento HTML escapado con los términos dentro de <mark>
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileTextOut {
+    pub file_id: String,
+    pub status: TextStatus,
+    pub ocr: bool,
+    pub text: Option<String>,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<FileTextDoc> for FileTextOut {
+    fn from(t: FileTextDoc) -> Self {
+        Self {
+            file_id: t.id.to_hex(),
+            status: t.status,
+            ocr: t.ocr,
+            text: t.text,
+            error: t.error,
+            updated_at: t.updated_at,
+        }
+    }
+}
@@ -3,6 +3,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
+use crate::models::{notification::NotificationPrefs, role::Role};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct User {
     #[serde(rename = "_id")]
@@ -10,14 +12,78 @@ pub struct User {
 
     pub name: String,
     pub email: String,
-    pub role: String, // "cliente" | "colaborador"
+    pub role: Role,
 
     // ✅ IMPORTANTE: NO uses skip_serializing aquí, si no Mongo NO lo guarda.
     pub password_hash: Option<String>,
 
+    // Cuentas externas (OIDC) vinculadas a este usuario
+    #[serde(default)]
+    pub identities: Vec<ExternalIdentity>,
+
+    #[serde(default)]
+    pub disabled: bool,
+
+    // Reset pendiente (forzado por un admin o solicitado)
+    #[serde(default)]
+    pub password_reset: Option<PasswordReset>,
+
+    // Los JWT emitidos antes de esta fecha dejan de ser válidos
+    #[serde(default)]
+    pub tokens_valid_after: Option<DateTime<Utc>>,
+
+    // Cambio de email esperando confirmación desde el nuevo correo
+    #[serde(default)]
+    pub pending_email: Option<PendingEmail>,
+
+    // Clave de los PNG del avatar en storage (avatar_<key>_<size>.png)
+    #[serde(default)]
+    pub avatar_key: Option<String>,
+
+    // Organización activa para listar/subir archivos; None = espacio personal
+    #[serde(default)]
+    pub active_org_id: Option<String>,
+
+    #[serde(default)]
+    pub notification_prefs: NotificationPrefs,
+
+    #[serde(default)]
+    pub image_metadata_policy: MetadataPolicy,
+
     pub created_at: DateTime<Utc>,
 }
 
+// Cuándo se quitan EXIF/XMP/IPTC (GPS, cámara...) de las fotos del usuario
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataPolicy {
+    #[default]
+    Keep,
+    StripOnUpload,
+    StripOnShare, // al hacerlas públicas por primera vez
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PasswordReset {
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub forced: bool, // true = no puede iniciar sesión hasta cambiarla
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExternalIdentity {
+    pub provider: String, // nombre configurado en OIDC_PROVIDERS
+    pub subject: String,  // claim "sub" del IdP
+    pub linked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingEmail {
+    pub email: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct RegisterDto {
     #[validate(length(min = 2, message = "name too short"))]
@@ -28,9 +94,6 @@ pub struct RegisterDto {
 
     #[validate(length(min = 6, message = "password too short"))]
     pub password: String,
-
-    #[validate(length(min = 3, message = "role required"))]
-    pub role: String,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -42,6 +105,60 @@ pub struct LoginDto {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UnlockQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PasswordResetDto {
+    #[validate(length(min = 1, message = "token required"))]
+    pub token: String,
+
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfileDto {
+    #[validate(length(min = 2, message = "name too short"))]
+    pub name: Option<String>,
+
+    #[validate(email(message = "invalid email"))]
+    pub email: Option<String>,
+
+    pub image_metadata_policy: Option<MetadataPolicy>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailDto {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordDto {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountDto {
+    // Obligatoria si la cuenta tiene contraseña
+    pub password: Option<String>,
+    // Email de otro miembro de alguna de tus organizaciones que recibe los archivos; si no,
+    // se borran
+    pub transfer_files_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AvatarQuery {
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleDto {
+    pub role: Role,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
@@ -53,7 +170,12 @@ pub struct PublicUser {
     pub id: String,
     pub name: String,
     pub email: String,
-    pub role: String,
+    pub role: Role,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_email: Option<String>,
+    pub avatar_url: Option<String>,
+    pub active_org_id: Option<String>,
+    pub image_metadata_policy: MetadataPolicy,
     pub created_at: DateTime<Utc>,
 }
 
@@ -64,6 +186,13 @@ impl From<User> for PublicUser {
             name: u.name,
             email: u.email,
             role: u.role,
+            pending_email: u.pending_email.map(|p| p.email),
+            // ?v= cambia con cada avatar nuevo para romper la caché del navegador
+            avatar_url: u
+                .avatar_key
+                .map(|k| format!("/api/users/{}/avatar?v={}", u.id.to_hex(), k)),
+            active_org_id: u.active_org_id,
+            image_metadata_policy: u.image_metadata_policy,
             created_at: u.created_at,
         }
     }
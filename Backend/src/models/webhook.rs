@@ -0,0 +1,156 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    #[serde(rename = "file.uploaded")]
+    FileUploaded,
+    #[serde(rename = "file.deleted")]
+    FileDeleted,
+    #[serde(rename = "file.shared")]
+    FileShared,
+    #[serde(rename = "user.registered")]
+    UserRegistered,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::FileUploaded => "file.uploaded",
+            WebhookEvent::FileDeleted => "file.deleted",
+            WebhookEvent::FileShared => "file.shared",
+            WebhookEvent::UserRegistered => "user.registered",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub owner_id: String,
+    // Webhooks de admin reciben los eventos de toda la plataforma
+    pub platform: bool,
+    pub url: String,
+    pub secret: String, // se necesita en claro para firmar (HMAC)
+    pub events: Vec<WebhookEvent>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeliveryDoc {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub webhook_id: String,
+    pub event: WebhookEvent,
+    // Cuerpo exacto que se firma y se envía; se reutiliza tal cual en reintentos
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    // Fecha BSON real: el worker consulta por rango
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWebhookDto {
+    #[validate(url(message = "invalid url"))]
+    pub url: String,
+
+    #[validate(length(min = 1, message = "at least one event is required"))]
+    pub events: Vec<WebhookEvent>,
+
+    // Si no se manda se genera uno
+    #[validate(length(min = 16, max = 256, message = "secret must be 16..256 chars"))]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateWebhookDto {
+    #[validate(url(message = "invalid url"))]
+    pub url: Option<String>,
+
+    #[validate(length(min = 1, message = "at least one event is required"))]
+    pub events: Option<Vec<WebhookEvent>>,
+
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookOut {
+    pub id: String,
+    pub url: String,
+    pub platform: bool,
+    pub events: Vec<WebhookEvent>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<WebhookDoc> for WebhookOut {
+    fn from(w: WebhookDoc) -> Self {
+        Self {
+            id: w.id.to_hex(),
+            url: w.url,
+            platform: w.platform,
+            events: w.events,
+            active: w.active,
+            created_at: w.created_at,
+            updated_at: w.updated_at,
+        }
+    }
+}
+
+// Respuesta al crear: el secreto solo se muestra esta vez
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    pub secret: String,
+    pub webhook: WebhookOut,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryOut {
+    pub id: String,
+    pub event: WebhookEvent,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_status_code: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl From<DeliveryDoc> for DeliveryOut {
+    fn from(d: DeliveryDoc) -> Self {
+        Self {
+            id: d.id.to_hex(),
+            event: d.event,
+            next_attempt_at: (d.status == DeliveryStatus::Pending).then_some(d.next_attempt_at),
+            status: d.status,
+            attempts: d.attempts,
+            last_status_code: d.last_status_code,
+            last_error: d.last_error,
+            created_at: d.created_at,
+            delivered_at: d.delivered_at,
+        }
+    }
+}
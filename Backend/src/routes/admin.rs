@@ -0,0 +1,524 @@
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use mongodb::options::FindOptions;
+
+use crate::{
+    config::AppConfig,
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::{Admin, RequireRole},
+    models::{
+        admin::{AdminUserOut, AdminUsersQuery, ImpersonateDto, ImpersonationDoc, Page},
+        audit::{AuditAction, AuditEventOut, AuditQuery},
+        role::{Permission, Role},
+        user::{PublicUser, UpdateRoleDto, User},
+    },
+    routes::{
+        api_keys::api_keys_collection,
+        auth::{client_ip, start_password_reset, users_collection},
+        drops,
+        files::{delete_all_files_of, storage_usage},
+        orgs,
+        users::remove_avatar_files,
+    },
+    utils::{
+        audit::{self, audit_collection, AuditEntry},
+        jwt, notify, webhooks,
+    },
+};
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_users)
+        .service(get_user)
+        .service(update_role)
+        .service(disable_user)
+        .service(enable_user)
+        .service(force_password_reset)
+        .service(impersonate_user)
+        .service(unlock_user)
+        .service(delete_user)
+        .service(list_audit);
+}
+
+fn parse_user_id(raw: String) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(raw).map_err(|_| ApiError::BadRequest("Invalid user id".into()))
+}
+
+async fn find_user(state: &AppState, id: ObjectId) -> Result<User, ApiError> {
+    users_collection(state)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("User not found".into()))
+}
+
+fn regex_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[get("/users")]
+async fn list_users(
+    admin: RequireRole<Admin>,
+    state: web::Data<AppState>,
+    query: web::Query<AdminUsersQuery>,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::ManageUsers)?;
+    let query = query.into_inner();
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let mut filter = Document::new();
+    if let Some(q) = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        let pattern = regex_escape(q);
+        filter.insert(
+            "$or",
+            vec![
+                doc! { "name": { "$regex": &pattern, "$options": "i" } },
+                doc! { "email": { "$regex": &pattern, "$options": "i" } },
+            ],
+        );
+    }
+    if let Some(role) = query.role {
+        filter.insert("role", role.as_str());
+    }
+    match query.disabled {
+        Some(true) => {
+            filter.insert("disabled", true);
+        }
+        Some(false) => {
+            filter.insert("disabled", doc! { "$ne": true });
+        }
+        None => {}
+    }
+
+    let col = users_collection(&state);
+    let total = col
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let options = FindOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .skip((page - 1) * per_page)
+        .limit(per_page as i64)
+        .build();
+
+    let mut cursor = col
+        .find(filter, options)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    let mut users: Vec<User> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        users.push(item.map_err(|_| ApiError::Internal)?);
+    }
+
+    let ids: Vec<String> = users.iter().map(|u| u.id.to_hex()).collect();
+    let usage = storage_usage(&state, &ids).await?;
+
+    let items = users
+        .into_iter()
+        .map(|u| {
+            let (bytes, count) = usage.get(&u.id.to_hex()).copied().unwrap_or((0, 0));
+            AdminUserOut::new(u, bytes, count)
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(Page {
+        items,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+#[get("/users/{id}")]
+async fn get_user(
+    admin: RequireRole<Admin>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::ManageUsers)?;
+    let id = parse_user_id(path.into_inner())?;
+    let user = find_user(&state, id).await?;
+
+    let usage = storage_usage(&state, &[id.to_hex()]).await?;
+    let (bytes, count) = usage.get(&id.to_hex()).copied().unwrap_or((0, 0));
+
+    Ok(HttpResponse::Ok().json(AdminUserOut::new(user, bytes, count)))
+}
+
+#[patch("/users/{id}/role")]
+async fn update_role(
+    req: HttpRequest,
+    admin: RequireRole<Admin>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<UpdateRoleDto>,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::AssignRoles)?;
+    let id = parse_user_id(path.into_inner())?;
+
+    // Evita que el último admin se quite el rol a sí mismo por accidente
+    if id.to_hex() == admin.user.user_id && body.role != admin.user.role {
+        return Err(ApiError::BadRequest(
+            "Admins cannot change their own role".into(),
+        ));
+    }
+
+    let col = users_collection(&state);
+    let role = bson::to_bson(&body.role).map_err(|_| ApiError::Internal)?;
+    col.update_one(doc! { "_id": id }, doc! { "$set": { "role": role } }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    if body.role != Role::Admin {
+        webhooks::revoke_platform_of(&state, &id.to_hex()).await;
+    }
+
+    let user = find_user(&state, id).await?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::AdminUpdateRole)
+            .by(&admin.user)
+            .target("user", user.id.to_hex())
+            .details(doc! { "role": user.role.as_str() }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(PublicUser::from(user)))
+}
+
+async fn set_disabled(
+    req: &HttpRequest,
+    admin: &RequireRole<Admin>,
+    state: &AppState,
+    raw_id: String,
+    disabled: bool,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::ManageUsers)?;
+    let id = parse_user_id(raw_id)?;
+
+    if disabled && id.to_hex() == admin.user.user_id {
+        return Err(ApiError::BadRequest(
+            "Admins cannot disable themselves".into(),
+        ));
+    }
+
+    let res = users_collection(state)
+        .update_one(
+            doc! { "_id": id },
+            doc! { "$set": { "disabled": disabled } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    if res.matched_count == 0 {
+        return Err(ApiError::NotFound("User not found".into()));
+    }
+
+    let action = if disabled {
+        AuditAction::AdminDisableUser
+    } else {
+        AuditAction::AdminEnableUser
+    };
+    audit::record(
+        state,
+        req,
+        AuditEntry::new(action)
+            .by(&admin.user)
+            .target("user", id.to_hex()),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true, "disabled": disabled })))
+}
+
+#[post("/users/{id}/disable")]
+async fn disable_user(
+    req: HttpRequest,
+    admin: RequireRole<Admin>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    set_disabled(&req, &admin, &state, path.into_inner(), true).await
+}
+
+#[post("/users/{id}/enable")]
+async fn enable_user(
+    req: HttpRequest,
+    admin: RequireRole<Admin>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    set_disabled(&req, &admin, &state, path.into_inner(), false).await
+}
+
+#[post("/users/{id}/force-password-reset")]
+async fn force_password_reset(
+    req: HttpRequest,
+    admin: RequireRole<Admin>,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::ManageUsers)?;
+    let id = parse_user_id(path.into_inner())?;
+    let user = find_user(&state, id).await?;
+
+    start_password_reset(&cfg, &state, &user, true).await?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::AdminForcePasswordReset)
+            .by(&admin.user)
+            .target("user", user.id.to_hex()),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+#[post("/users/{id}/impersonate")]
+async fn impersonate_user(
+    req: HttpRequest,
+    admin: RequireRole<Admin>,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: Option<web::Json<ImpersonateDto>>,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::ManageUsers)?;
+    admin.user.require_session()?;
+
+    let id = parse_user_id(path.into_inner())?;
+    let target = find_user(&state, id).await?;
+
+    if target.role == Role::Admin {
+        return Err(ApiError::Forbidden("Cannot impersonate an admin".into()));
+    }
+    if target.disabled {
+        return Err(ApiError::BadRequest("User is disabled".into()));
+    }
+
+    let (token, claims) = jwt::sign_impersonation_jwt(
+        &state.jwt,
+        &target.id.to_hex(),
+        &target.email,
+        target.role,
+        &admin.user.user_id,
+        cfg.impersonation_minutes,
+    )
+    .map_err(ApiError::BadRequest)?;
+
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    let record = ImpersonationDoc {
+        id: ObjectId::new(),
+        admin_id: admin.user.user_id.clone(),
+        target_id: target.id.to_hex(),
+        jti: claims.jti,
+        reason: body.and_then(|b| b.into_inner().reason),
+        ip: client_ip(&req),
+        created_at: Utc::now(),
+        expires_at,
+    };
+
+    // Sin registro no se entrega el token
+    state
+        .db
+        .collection::<ImpersonationDoc>("impersonations")
+        .insert_one(&record, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (impersonation): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::AdminImpersonate)
+            .by(&admin.user)
+            .target("user", target.id.to_hex())
+            .details(doc! { "jti": &record.jti, "reason": &record.reason }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "token": token,
+        "expires_at": expires_at,
+        "user": PublicUser::from(target),
+    })))
+}
+
+#[post("/users/{id}/unlock")]
+async fn unlock_user(
+    req: HttpRequest,
+    admin: RequireRole<Admin>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::ManageUsers)?;
+    let id = parse_user_id(path.into_inner())?;
+    let target = find_user(&state, id).await?;
+
+    state.login_guard.unlock_email(&target.email).await?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::AdminUnlockUser)
+            .by(&admin.user)
+            .target("user", target.id.to_hex()),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+#[delete("/users/{id}")]
+async fn delete_user(
+    req: HttpRequest,
+    admin: RequireRole<Admin>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::ManageUsers)?;
+    let id = parse_user_id(path.into_inner())?;
+
+    if id.to_hex() == admin.user.user_id {
+        return Err(ApiError::BadRequest(
+            "Admins cannot delete themselves".into(),
+        ));
+    }
+    let user = find_user(&state, id).await?;
+    let user_id = user.id.to_hex();
+
+    let files_deleted = delete_all_files_of(&state, &user_id).await?;
+
+    orgs::remove_user_from_all(&state, &user_id).await?;
+    drops::revoke_all_of(&state, &user_id, None).await?;
+    webhooks::remove_all_of(&state, &user_id).await;
+    notify::remove_all_of(&state, &user_id).await;
+
+    api_keys_collection(&state)
+        .update_many(
+            doc! { "user_id": &user_id, "revoked_at": null },
+            doc! { "$set": { "revoked_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    users_collection(&state)
+        .delete_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    if let Some(key) = user.avatar_key.as_deref() {
+        remove_avatar_files(key);
+    }
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::AdminDeleteUser)
+            .by(&admin.user)
+            .target("user", user_id.clone())
+            .details(doc! { "email": &user.email, "files_deleted": files_deleted as i64 }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "ok": true,
+        "files_deleted": files_deleted
+    })))
+}
+
+#[get("/audit")]
+async fn list_audit(
+    admin: RequireRole<Admin>,
+    state: web::Data<AppState>,
+    query: web::Query<AuditQuery>,
+) -> Result<HttpResponse, ApiError> {
+    admin.user.require_permission(Permission::ManageUsers)?;
+    let query = query.into_inner();
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let mut filter = Document::new();
+    for (key, value) in [
+        ("actor_id", query.actor_id),
+        ("action", query.action),
+        ("target_type", query.target_type),
+        ("target_id", query.target_id),
+    ] {
+        if let Some(v) = value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+        {
+            filter.insert(key, v);
+        }
+    }
+
+    let mut range = Document::new();
+    if let Some(from) = query.from {
+        range.insert("$gte", bson::DateTime::from_chrono(from));
+    }
+    if let Some(to) = query.to {
+        range.insert("$lt", bson::DateTime::from_chrono(to));
+    }
+    if !range.is_empty() {
+        filter.insert("created_at", range);
+    }
+
+    let col = audit_collection(&state);
+    let total = col
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let options = FindOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .skip((page - 1) * per_page)
+        .limit(per_page as i64)
+        .build();
+
+    let mut cursor = col
+        .find(filter, options)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    let mut items: Vec<AuditEventOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        items.push(item.map_err(|_| ApiError::Internal)?.into());
+    }
+
+    Ok(HttpResponse::Ok().json(Page {
+        items,
+        total,
+        page,
+        per_page,
+    }))
+}
@@ -0,0 +1,181 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use validator::Validate;
+
+use crate::{
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        api_key::{ApiKeyDoc, ApiKeyOut, ApiScope, CreateApiKeyDto, CreatedApiKey},
+        audit::AuditAction,
+        role::Role,
+    },
+    utils::{
+        api_key,
+        audit::{self, AuditEntry},
+    },
+};
+
+pub(crate) fn api_keys_collection(state: &AppState) -> mongodb::Collection<ApiKeyDoc> {
+    state.db.collection::<ApiKeyDoc>("api_keys")
+}
+
+async fn ensure_key_hash_unique_index(state: &AppState) {
+    let options = IndexOptions::builder()
+        .unique(true)
+        .name(Some("unique_key_hash".to_string()))
+        .build();
+
+    let model = IndexModel::builder()
+        .keys(doc! { "key_hash": 1 })
+        .options(options)
+        .build();
+
+    if let Err(e) = api_keys_collection(state).create_index(model, None).await {
+        eprintln!("Mongo create_index error (unique_key_hash): {:?}", e);
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_key)
+        .service(list_keys)
+        .service(revoke_key);
+}
+
+#[post("")]
+async fn create_key(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<CreateApiKeyDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+    ensure_key_hash_unique_index(&state).await;
+
+    let mut dto = body.into_inner();
+    dto.name = dto.name.trim().to_string();
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    if dto.scopes.is_empty() {
+        return Err(ApiError::BadRequest(
+            "at least one scope is required".into(),
+        ));
+    }
+    if dto.scopes.contains(&ApiScope::Admin) && user.role != Role::Admin {
+        return Err(ApiError::Forbidden(
+            "Only admins can create admin keys".into(),
+        ));
+    }
+    let mut scopes = Vec::with_capacity(dto.scopes.len());
+    for scope in dto.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+
+    let (key, prefix) = api_key::generate_key();
+    let now = Utc::now();
+
+    let doc = ApiKeyDoc {
+        id: ObjectId::new(),
+        user_id: user.user_id.clone(),
+        name: dto.name,
+        prefix,
+        key_hash: api_key::hash_key(&key),
+        scopes,
+        expires_at: dto.expires_in_days.map(|d| now + Duration::days(d)),
+        last_used_at: None,
+        revoked_at: None,
+        created_at: now,
+    };
+
+    api_keys_collection(&state)
+        .insert_one(&doc, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (create api key): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    let scopes: Vec<&str> = doc.scopes.iter().map(|s| s.as_str()).collect();
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::ApiKeyCreate)
+            .by(&user)
+            .target("api_key", doc.id.to_hex())
+            .details(doc! { "name": &doc.name, "prefix": &doc.prefix, "scopes": scopes }),
+    )
+    .await;
+
+    Ok(HttpResponse::Created().json(CreatedApiKey {
+        key,
+        api_key: doc.into(),
+    }))
+}
+
+#[get("")]
+async fn list_keys(user: AuthUser, state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let options = FindOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .build();
+    let mut cursor = api_keys_collection(&state)
+        .find(doc! { "user_id": &user.user_id }, options)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out: Vec<ApiKeyOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        let k = item.map_err(|_| ApiError::Internal)?;
+        out.push(ApiKeyOut::from(k));
+    }
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+#[delete("/{id}")]
+async fn revoke_key(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| ApiError::BadRequest("Invalid key id".into()))?;
+
+    let res = api_keys_collection(&state)
+        .update_one(
+            doc! { "_id": id, "user_id": &user.user_id, "revoked_at": null },
+            doc! { "$set": { "revoked_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    if res.matched_count == 0 {
+        return Err(ApiError::NotFound("API key not found".into()));
+    }
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::ApiKeyRevoke)
+            .by(&user)
+            .target("api_key", id.to_hex()),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
@@ -0,0 +1,464 @@
+use actix_multipart::Field;
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures::StreamExt;
+use sanitize_filename::sanitize;
+use std::collections::{HashMap, HashSet};
+use validator::Validate;
+
+use crate::{
+    config::AppConfig,
+    db::AppState,
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        api_key::ApiScope,
+        audit::AuditAction,
+        file::{ArchiveDto, ExtractedArchiveOut, FileDoc, FileOut, SkippedEntry},
+        folder::FolderDoc,
+        webhook::WebhookEvent,
+    },
+    routes::{
+        files::{
+            field_meta, file_audience, file_payload, files_collection, find_accessible,
+            parse_file_id, record_downloads, warn_if_near_quota, write_field, UploadLimit,
+            UploadTarget,
+        },
+        folders::{find_accessible_folder, folders_collection},
+    },
+    utils::{
+        audit::{self, AuditEntry},
+        disposition,
+        events::LiveEventKind,
+        extract::{self, ArchiveKind, ExtractLimits},
+        image_meta, search_index, storage, webhooks,
+        zip::{self, ZipEntry},
+    },
+};
+
+// Tope de archivos por ZIP al bajar una carpeta completa
+const MAX_ARCHIVE_FILES: usize = 10_000;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(download_archive);
+}
+
+// Nombres únicos por carpeta (sin distinguir mayúsculas): "a.pdf", "a (1).pdf"...
+#[derive(Default)]
+struct NameTable {
+    taken: HashSet<String>,
+}
+
+impl NameTable {
+    fn unique(&mut self, dir: &str, name: &str) -> String {
+        let (stem, ext) = match name.rfind('.') {
+            Some(i) if i > 0 => name.split_at(i),
+            _ => (name, ""),
+        };
+
+        let mut candidate = name.to_string();
+        let mut n = 1;
+        while !self
+            .taken
+            .insert(format!("{}/{}", dir, candidate).to_lowercase())
+        {
+            candidate = format!("{} ({}){}", stem, n, ext);
+            n += 1;
+        }
+        candidate
+    }
+
+    // Ruta completa dentro del ZIP
+    fn claim(&mut self, dir: &str, name: &str) -> String {
+        let name = self.unique(dir, name);
+        if dir.is_empty() {
+            name
+        } else {
+            format!("{}/{}", dir, name)
+        }
+    }
+}
+
+fn file_entry(path: String, file: &FileDoc) -> ZipEntry {
+    ZipEntry::File {
+        path,
+        stored_name: file.stored_name.clone(),
+        modified: file.updated_at,
+    }
+}
+
+// Un archivo que ya no está en disco se omite en vez de cortar el ZIP a la mitad
+fn on_disk(file: &FileDoc) -> bool {
+    let ok = std::path::Path::new(&storage::path_for(&file.stored_name)).exists();
+    if !ok {
+        eprintln!(
+            "Archive: file {} missing on disk, skipped",
+            file.id.to_hex()
+        );
+    }
+    ok
+}
+
+async fn collect_files(state: &AppState, filter: Document) -> Result<Vec<FileDoc>, ApiError> {
+    let mut cursor = files_collection(state)
+        .find(filter, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out = Vec::new();
+    while let Some(item) = cursor.next().await {
+        out.push(item.map_err(|_| ApiError::Internal)?);
+        if out.len() > MAX_ARCHIVE_FILES {
+            return Err(ApiError::BadRequest(format!(
+                "Too many files for one archive (max {})",
+                MAX_ARCHIVE_FILES
+            )));
+        }
+    }
+    Ok(out)
+}
+
+// La carpeta, sus subcarpetas y sus archivos, con la ruta relativa de cada uno
+async fn folder_entries(
+    state: &AppState,
+    root: &FolderDoc,
+) -> Result<(Vec<ZipEntry>, Vec<FileDoc>), ApiError> {
+    // Mismo espacio que la carpeta raíz: personal del dueño o de la organización
+    let space = match root.org_id.as_deref() {
+        Some(org_id) => doc! { "org_id": org_id },
+        None => doc! { "org_id": null, "owner_id": &root.owner_id },
+    };
+
+    let mut cursor = folders_collection(state)
+        .find(space.clone(), None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    let mut by_parent: HashMap<String, Vec<FolderDoc>> = HashMap::new();
+    while let Some(item) = cursor.next().await {
+        let f = item.map_err(|_| ApiError::Internal)?;
+        if let Some(parent) = f.parent_id.clone() {
+            by_parent.entry(parent).or_default().push(f);
+        }
+    }
+
+    let mut names = NameTable::default();
+    let mut entries = Vec::new();
+    let mut paths: HashMap<String, String> = HashMap::new();
+
+    // Recorrido desde la raíz; cada subcarpeta queda bajo la ruta de su padre
+    let root_path = names.claim("", &root.name);
+    let mut queue = vec![(root.clone(), root_path)];
+    while let Some((folder, path)) = queue.pop() {
+        let id = folder.id.to_hex();
+        entries.push(ZipEntry::Dir {
+            path: path.clone(),
+            modified: folder.updated_at,
+        });
+        for child in by_parent.remove(&id).unwrap_or_default() {
+            let child_path = names.claim(&path, &child.name);
+            queue.push((child, child_path));
+        }
+        paths.insert(id, path);
+    }
+
+    let mut filter = space;
+    filter.insert(
+        "folder_id",
+        doc! { "$in": paths.keys().cloned().collect::<Vec<_>>() },
+    );
+    let mut files = collect_files(state, filter).await?;
+    files.retain(on_disk);
+    files.sort_by(|a, b| a.original_name.cmp(&b.original_name));
+
+    for file in &files {
+        let dir = file
+            .folder_id
+            .as_ref()
+            .and_then(|id| paths.get(id))
+            .cloned()
+            .unwrap_or_default();
+        let path = names.claim(&dir, &file.original_name);
+        entries.push(file_entry(path, file));
+    }
+
+    Ok((entries, files))
+}
+
+// ZIP generado al vuelo con varios archivos o una carpeta; mismas reglas de acceso que la
+// descarga individual y cada archivo cuenta como descargado
+#[post("/archive")]
+async fn download_archive(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<ArchiveDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+    body.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let dto = body.into_inner();
+    let (archive_name, entries, files) = match (dto.file_ids, dto.folder_id) {
+        (Some(ids), None) => {
+            let mut seen = HashSet::new();
+            let mut names = NameTable::default();
+            let mut entries = Vec::new();
+            let mut files = Vec::new();
+
+            for raw in ids {
+                let id = parse_file_id(raw)?;
+                if !seen.insert(id) {
+                    continue;
+                }
+                let (file, _) = find_accessible(&state, &user, id).await?;
+                if !on_disk(&file) {
+                    continue;
+                }
+                entries.push(file_entry(names.claim("", &file.original_name), &file));
+                files.push(file);
+            }
+            ("files.zip".to_string(), entries, files)
+        }
+        (None, Some(folder_id)) => {
+            let (root, _) = find_accessible_folder(&state, &user, &folder_id).await?;
+            let (entries, files) = folder_entries(&state, &root).await?;
+            (format!("{}.zip", root.name), entries, files)
+        }
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Send either file_ids or folder_id".into(),
+            ))
+        }
+    };
+
+    record_downloads(&state, &req, &files, Some(&user), "archive").await;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Content-Type", "application/zip"))
+        .insert_header(disposition::attachment(&archive_name))
+        .streaming(zip::stream(entries)))
+}
+
+// Sube un ZIP/TAR(.gz) y lo descomprime en una carpeta nueva dentro del destino: un FileDoc
+// por entrada, la estructura de carpetas se respeta y lo que no se extrae se reporta
+pub(crate) async fn extract_upload(
+    state: &AppState,
+    cfg: &AppConfig,
+    req: &HttpRequest,
+    user: &AuthUser,
+    mut field: Field,
+    target: UploadTarget,
+    limit: Option<UploadLimit>,
+) -> Result<HttpResponse, ApiError> {
+    let (filename, _) = field_meta(&field);
+    let kind = ArchiveKind::from_name(&filename).ok_or_else(|| {
+        ApiError::BadRequest("Only .zip, .tar, .tar.gz and .tgz can be extracted".into())
+    })?;
+
+    // El comprimido solo vive en disco mientras se extrae
+    let tmp_name = format!("tmp_{}_{}", ObjectId::new().to_hex(), filename);
+    let archive_size = write_field(&mut field, &tmp_name, limit.as_ref()).await?;
+
+    let limits = ExtractLimits::new(
+        &cfg.archive_extract,
+        archive_size as u64,
+        limit.map(|l| (l.max_bytes as u64, l.exceeded)),
+    );
+    let tmp = tmp_name.clone();
+    let result = web::block(move || extract::extract(&tmp, kind, &limits)).await;
+    storage::remove(&tmp_name);
+    let mut extracted = result.map_err(|_| ApiError::Internal)??;
+
+    // Fotos: se leen sus metadatos y se limpian igual que en una subida suelta; la que no se
+    // pudo limpiar no entra
+    let strip = image_meta::strip_on_upload(state, &user.user_id, target.strip_metadata).await;
+    let mut kept = Vec::with_capacity(extracted.files.len());
+    let mut metas = Vec::with_capacity(extracted.files.len());
+    for mut e in std::mem::take(&mut extracted.files) {
+        let mime = mime_guess::from_path(&e.name)
+            .first_or_octet_stream()
+            .to_string();
+        match image_meta::on_upload(
+            state,
+            &e.stored_name,
+            &mime,
+            &e.name,
+            &user.user_id,
+            Some(strip),
+        )
+        .await
+        {
+            Ok(meta) => {
+                let meta = meta.map(|(meta, size)| {
+                    e.size = size;
+                    meta
+                });
+                kept.push(e);
+                metas.push(meta);
+            }
+            Err(_) => {
+                storage::remove(&e.stored_name);
+                let mut path = e.dir.clone();
+                path.push(e.name);
+                extracted.skipped.push(SkippedEntry {
+                    name: path.join("/"),
+                    reason: "could not remove image metadata".to_string(),
+                });
+            }
+        }
+    }
+    extracted.files = kept;
+
+    let now = Utc::now();
+    let folder = |name: String, parent_id: Option<String>| FolderDoc {
+        id: ObjectId::new(),
+        owner_id: user.user_id.clone(),
+        org_id: target.org_id.clone(),
+        parent_id,
+        name,
+        created_at: now,
+        updated_at: now,
+    };
+
+    let root = folder(
+        sanitize(kind.folder_name(&filename)),
+        target.folder_id.clone(),
+    );
+    let mut folders = vec![root.clone()];
+    let mut folder_ids: HashMap<Vec<String>, String> = HashMap::new();
+    for dir in &extracted.dirs {
+        let parent = folder_ids
+            .get(&dir[..dir.len() - 1])
+            .cloned()
+            .unwrap_or_else(|| root.id.to_hex());
+        let f = folder(dir[dir.len() - 1].clone(), Some(parent));
+        folder_ids.insert(dir.clone(), f.id.to_hex());
+        folders.push(f);
+    }
+
+    let mut names = NameTable::default();
+    let files: Vec<FileDoc> = extracted
+        .files
+        .iter()
+        .zip(metas)
+        .map(|(e, image_meta)| {
+            let folder_id = folder_ids
+                .get(&e.dir)
+                .cloned()
+                .unwrap_or_else(|| root.id.to_hex());
+            FileDoc {
+                id: ObjectId::new(),
+                owner_id: user.user_id.clone(),
+                org_id: target.org_id.clone(),
+                original_name: names.unique(&folder_id, &e.name),
+                folder_id: Some(folder_id),
+                stored_name: e.stored_name.clone(),
+                mime: mime_guess::from_path(&e.name)
+                    .first_or_octet_stream()
+                    .to_string(),
+                size: e.size,
+                visibility: "private".to_string(),
+                download_count: 0,
+                last_downloaded_at: None,
+                image_meta,
+                created_at: now,
+                updated_at: now,
+            }
+        })
+        .collect();
+
+    let inserted = async {
+        folders_collection(state)
+            .insert_many(&folders, None)
+            .await?;
+        if !files.is_empty() {
+            files_collection(state).insert_many(&files, None).await?;
+        }
+        Ok::<_, mongodb::error::Error>(())
+    }
+    .await;
+    if let Err(e) = inserted {
+        eprintln!("Mongo insert_many error (extract_upload): {:?}", e);
+        for f in &extracted.files {
+            storage::remove(&f.stored_name);
+        }
+        let ids: Vec<ObjectId> = folders.iter().map(|f| f.id).collect();
+        let _ = folders_collection(state)
+            .delete_many(doc! { "_id": { "$in": ids } }, None)
+            .await;
+        return Err(ApiError::Internal);
+    }
+    search_index::enqueue(state, &files).await;
+
+    // Todos los archivos van al mismo destino: comparten audiencia
+    let audience = match files.first() {
+        Some(first) => file_audience(state, first).await,
+        None => Vec::new(),
+    };
+    let entries = files
+        .iter()
+        .map(|file| {
+            AuditEntry::new(AuditAction::FileUpload)
+                .by(user)
+                .target("file", file.id.to_hex())
+                .details(doc! {
+                    "name": &file.original_name,
+                    "size": file.size,
+                    "org_id": &file.org_id,
+                    "archive": &filename,
+                })
+        })
+        .collect();
+    audit::record_many(state, req, entries).await;
+    webhooks::dispatch_many(
+        state,
+        WebhookEvent::FileUploaded,
+        &audience,
+        files.iter().map(file_payload).collect(),
+    )
+    .await;
+    if !files.is_empty() {
+        state.events.publish(
+            audience,
+            LiveEventKind::FileUploaded,
+            serde_json::json!({ "folder_id": root.id.to_hex(), "files": files.len() }),
+        );
+    }
+    warn_if_near_quota(state, target.org_id.as_deref(), extracted.bytes as i64).await;
+
+    Ok(HttpResponse::Created().json(ExtractedArchiveOut {
+        folder: root.into(),
+        files: files.into_iter().map(FileOut::from).collect(),
+        skipped: extracted.skipped,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_repeated_names_per_folder_ignoring_case() {
+        let mut names = NameTable::default();
+        assert_eq!(names.unique("", "a.pdf"), "a.pdf");
+        assert_eq!(names.unique("", "A.PDF"), "A (1).PDF");
+        assert_eq!(names.unique("", "a.pdf"), "a (2).pdf");
+        // Otra carpeta tiene su propio espacio de nombres
+        assert_eq!(names.unique("docs", "a.pdf"), "a.pdf");
+        assert_eq!(names.claim("docs", "a.pdf"), "docs/a (1).pdf");
+    }
+
+    #[test]
+    fn keeps_dotfiles_and_names_without_extension_whole() {
+        let mut names = NameTable::default();
+        assert_eq!(names.unique("", ".env"), ".env");
+        assert_eq!(names.unique("", ".env"), ".env (1)");
+        assert_eq!(names.unique("", "README"), "README");
+        assert_eq!(names.unique("", "README"), "README (1)");
+        // Un nombre que ya es la versión numerada de otro no se pisa
+        assert_eq!(names.unique("", "b (1).txt"), "b (1).txt");
+        assert_eq!(names.unique("", "b.txt"), "b.txt");
+        assert_eq!(names.unique("", "b.txt"), "b (2).txt");
+    }
+}
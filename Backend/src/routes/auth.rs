@@ -1,19 +1,37 @@
-use actix_web::{post, web, HttpResponse};
+use std::net::IpAddr;
+
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use bson::{doc, oid::ObjectId};
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use mongodb::{options::IndexOptions, IndexModel};
 use validator::Validate;
 
 use crate::{
     config::AppConfig,
-    db::AppState,
+    db::{bson_time, AppState},
     errors::ApiError,
     middleware::auth::AuthUser,
-    models::user::{AuthResponse, LoginDto, RegisterDto, User},
-    utils::{jwt, password},
+    models::{
+        audit::AuditAction,
+        role::Role,
+        user::{
+            AuthResponse, LoginDto, PasswordReset, PasswordResetDto, PublicUser, RegisterDto,
+            UnlockQuery, User,
+        },
+        webhook::WebhookEvent,
+    },
+    utils::{
+        api_key,
+        audit::{self, AuditEntry},
+        jwt,
+        random::random_token,
+        webhooks,
+    },
 };
 
-fn users_collection(state: &AppState) -> mongodb::Collection<User> {
+const PASSWORD_RESET_TTL_HOURS: i64 = 24;
+
+pub(crate) fn users_collection(state: &AppState) -> mongodb::Collection<User> {
     state.db.collection::<User>("users")
 }
 
@@ -36,13 +54,37 @@ async fn ensure_email_unique_index(state: &AppState) {
     }
 }
 
+// Con BOOTSTRAP_ADMIN_EMAIL se promueve esa cuenta a admin al arrancar,
+// así existe al menos un admin que pueda asignar roles
+pub async fn bootstrap_admin(state: &AppState, email: &str) {
+    let res = users_collection(state)
+        .update_one(
+            doc! { "email": email },
+            doc! { "$set": { "role": Role::Admin.as_str() } },
+            None,
+        )
+        .await;
+
+    match res {
+        Ok(r) if r.matched_count == 0 => {
+            eprintln!("BOOTSTRAP_ADMIN_EMAIL: no user with email {}", email)
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Mongo update_one error (bootstrap_admin): {:?}", e),
+    }
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(register).service(login).service(me);
+    cfg.service(register)
+        .service(login)
+        .service(unlock)
+        .service(password_reset)
+        .service(me);
 }
 
 #[post("/register")]
 async fn register(
-    cfg: web::Data<AppConfig>,
+    req: HttpRequest,
     state: web::Data<AppState>,
     body: web::Json<RegisterDto>,
 ) -> Result<HttpResponse, ApiError> {
@@ -53,17 +95,10 @@ async fn register(
     // Normalizar
     dto.email = dto.email.trim().to_lowercase();
     dto.name = dto.name.trim().to_string();
-    dto.role = dto.role.trim().to_string();
 
     dto.validate()
         .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
 
-    if dto.role != "cliente" && dto.role != "colaborador" {
-        return Err(ApiError::BadRequest(
-            "role must be 'cliente' or 'colaborador'".into(),
-        ));
-    }
-
     let col: mongodb::Collection<User> = users_collection(&state);
 
     // Revisar si existe
@@ -79,33 +114,59 @@ async fn register(
         return Err(ApiError::BadRequest("Email already registered".into()));
     }
 
-    let hash = password::hash_password(&dto.password).map_err(ApiError::BadRequest)?;
+    state
+        .passwords
+        .validate_policy(&dto.password)
+        .map_err(ApiError::BadRequest)?;
+
+    let hash = state.passwords.hash(&dto.password).await.map_err(|e| {
+        eprintln!("Password hash error (register): {}", e);
+        ApiError::Internal
+    })?;
 
     let user = User {
         id: ObjectId::new(),
         name: dto.name,
         email: dto.email,
-        role: dto.role,
+        // El rol lo asigna un admin; todo registro empieza como cliente
+        role: Role::Cliente,
         // ✅ ahora es Option
         password_hash: Some(hash),
+        identities: Vec::new(),
+        disabled: false,
+        password_reset: None,
+        tokens_valid_after: None,
+        pending_email: None,
+        avatar_key: None,
+        active_org_id: None,
+        notification_prefs: Default::default(),
+        image_metadata_policy: Default::default(),
         created_at: Utc::now(),
     };
 
-    col.insert_one(&user, None)
-        .await
-        .map_err(|e| {
-            eprintln!("Mongo insert_one error (register): {:?}", e);
-            ApiError::Internal
-        })?;
+    col.insert_one(&user, None).await.map_err(|e| {
+        eprintln!("Mongo insert_one error (register): {:?}", e);
+        ApiError::Internal
+    })?;
 
-    let token = jwt::sign_jwt(
-        &user.id.to_hex(),
-        &user.email,
-        &user.role,
-        &cfg.jwt_secret,
-        cfg.jwt_exp_minutes,
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::Register)
+            .by_account(Some(user.id.to_hex()), &user.email)
+            .target("user", user.id.to_hex()),
+    )
+    .await;
+    webhooks::dispatch(
+        &state,
+        WebhookEvent::UserRegistered,
+        &[],
+        serde_json::to_value(PublicUser::from(user.clone())).unwrap_or_default(),
     )
-    .map_err(ApiError::BadRequest)?;
+    .await;
+
+    let token = jwt::sign_jwt(&state.jwt, &user.id.to_hex(), &user.email, user.role)
+        .map_err(ApiError::BadRequest)?;
 
     Ok(HttpResponse::Created().json(AuthResponse {
         token,
@@ -115,6 +176,7 @@ async fn register(
 
 #[post("/login")]
 async fn login(
+    req: HttpRequest,
     cfg: web::Data<AppConfig>,
     state: web::Data<AppState>,
     body: web::Json<LoginDto>,
@@ -125,6 +187,9 @@ async fn login(
     dto.validate()
         .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
 
+    let ip = client_ip(&req);
+    state.login_guard.attempt(&ip, &dto.email).await?;
+
     let col: mongodb::Collection<User> = users_collection(&state);
 
     let user = col
@@ -133,30 +198,95 @@ async fn login(
         .map_err(|e| {
             eprintln!("Mongo find_one error (login): {:?}", e);
             ApiError::Internal
-        })?
-        .ok_or_else(|| ApiError::Unauthorized("Invalid credentials".into()))?;
+        })?;
 
-    // ✅ si por alguna razón el usuario no tiene password_hash, no truena
-    let hash = user
-        .password_hash
-        .as_deref()
-        .ok_or_else(|| ApiError::Unauthorized("User has no password set".into()))?;
+    let Some(user) = user else {
+        // Email inexistente también cuenta, para no revelar qué cuentas existen
+        state.login_guard.record_failure(&ip, &dto.email).await?;
+        audit::record(
+            &state,
+            &req,
+            AuditEntry::new(AuditAction::LoginFailure)
+                .by_account(None, &dto.email)
+                .details(doc! { "reason": "unknown_email" }),
+        )
+        .await;
+        return Err(ApiError::Unauthorized("Invalid credentials".into()));
+    };
 
-    let ok = password::verify_password(&dto.password, hash)
-        .map_err(ApiError::BadRequest)?;
+    // Una cuenta sin contraseña (solo OIDC) falla igual que una contraseña incorrecta:
+    // cuenta para el bloqueo y no revela cómo se creó la cuenta
+    let check = match user.password_hash.as_deref() {
+        Some(hash) => Some(
+            state
+                .passwords
+                .verify(&dto.password, hash)
+                .await
+                .map_err(ApiError::BadRequest)?,
+        ),
+        None => None,
+    };
 
-    if !ok {
+    let Some(check) = check.filter(|c| c.ok) else {
+        let reason = if user.password_hash.is_some() {
+            "bad_password"
+        } else {
+            "no_password"
+        };
+        let locked = state.login_guard.record_failure(&ip, &dto.email).await?;
+        audit::record(
+            &state,
+            &req,
+            AuditEntry::new(AuditAction::LoginFailure)
+                .by_account(Some(user.id.to_hex()), &user.email)
+                .details(doc! { "reason": reason, "locked": locked.is_some() }),
+        )
+        .await;
+        if let Some(unlock_token) = locked {
+            send_unlock_email(&cfg, &state, &user, &unlock_token).await;
+        }
         return Err(ApiError::Unauthorized("Invalid credentials".into()));
+    };
+
+    state.login_guard.record_success(&dto.email).await?;
+
+    if user.disabled {
+        return Err(ApiError::Forbidden("Account disabled".into()));
+    }
+    if user.password_reset.as_ref().is_some_and(|r| r.forced) {
+        return Err(ApiError::Forbidden("Password reset required".into()));
+    }
+
+    // Migración transparente: bcrypt (o argon2 con parámetros viejos) -> argon2id actual
+    if check.needs_rehash {
+        match state.passwords.hash(&dto.password).await {
+            Ok(new_hash) => {
+                if let Err(e) = col
+                    .update_one(
+                        doc! { "_id": user.id },
+                        doc! { "$set": { "password_hash": new_hash } },
+                        None,
+                    )
+                    .await
+                {
+                    eprintln!("Mongo update_one error (rehash): {:?}", e);
+                }
+            }
+            Err(e) => eprintln!("Password rehash error: {}", e),
+        }
     }
 
-    let token = jwt::sign_jwt(
-        &user.id.to_hex(),
-        &user.email,
-        &user.role,
-        &cfg.jwt_secret,
-        cfg.jwt_exp_minutes,
+    let token = jwt::sign_jwt(&state.jwt, &user.id.to_hex(), &user.email, user.role)
+        .map_err(ApiError::BadRequest)?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::LoginSuccess)
+            .by_account(Some(user.id.to_hex()), &user.email)
+            .details(doc! { "method": "password" }),
     )
-    .map_err(ApiError::BadRequest)?;
+    .await;
 
     Ok(HttpResponse::Ok().json(AuthResponse {
         token,
@@ -164,6 +294,181 @@ async fn login(
     }))
 }
 
+// IP del cliente para los límites por IP. X-Forwarded-For lo escribe cualquiera: solo se lee
+// cuando la conexión viene de un proxy de confianza, y de derecha a izquierda se toma el primer
+// salto que no es otro proxy de confianza.
+pub(crate) fn client_ip(req: &HttpRequest) -> String {
+    let Some(peer) = req.peer_addr().map(|a| a.ip()) else {
+        return "unknown".to_string();
+    };
+    let trusted = req
+        .app_data::<web::Data<AppConfig>>()
+        .map(|cfg| cfg.trusted_proxies.as_slice())
+        .unwrap_or_default();
+    if !trusted.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let forwarded = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    for hop in forwarded.into_iter().rev() {
+        match hop.parse::<IpAddr>() {
+            Ok(ip) if trusted.contains(&ip) => continue,
+            Ok(ip) => return ip.to_string(),
+            Err(_) => break,
+        }
+    }
+    peer.to_string()
+}
+
+async fn send_unlock_email(cfg: &AppConfig, state: &AppState, user: &User, token: &str) {
+    let link = format!("{}/api/auth/unlock?token={}", cfg.public_url, token);
+    let body = format!(
+        "Hola {},\n\nTu cuenta fue bloqueada temporalmente por varios intentos fallidos de inicio de sesión.\n\
+         Si fuiste tú, puedes desbloquearla aquí:\n{}\n\nSi no fuiste tú, te recomendamos cambiar tu contraseña.",
+        user.name, link
+    );
+
+    if let Err(e) = state
+        .mailer
+        .send(&user.email, "Tu cuenta fue bloqueada temporalmente", &body)
+        .await
+    {
+        eprintln!("Mail error (unlock): {}", e);
+    }
+}
+
+// Genera un token de reset, lo guarda hasheado y lo manda por correo.
+// Con `forced` la cuenta no puede iniciar sesión y se cierran sus sesiones.
+pub(crate) async fn start_password_reset(
+    cfg: &AppConfig,
+    state: &AppState,
+    user: &User,
+    forced: bool,
+) -> Result<(), ApiError> {
+    let token = random_token(32);
+    let now = Utc::now();
+    let reset = PasswordReset {
+        token_hash: api_key::hash_key(&token),
+        expires_at: now + Duration::hours(PASSWORD_RESET_TTL_HOURS),
+        forced,
+    };
+
+    let mut set = doc! {
+        "password_reset": bson::to_bson(&reset).map_err(|_| ApiError::Internal)?,
+    };
+    if forced {
+        set.insert("tokens_valid_after", bson_time(now));
+    }
+
+    users_collection(state)
+        .update_one(doc! { "_id": user.id }, doc! { "$set": set }, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo update_one error (password reset): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    let body = format!(
+        "Hola {},\n\nSe solicitó un cambio de contraseña para tu cuenta.\n\
+         Usa este código en POST {}/api/auth/password-reset (válido {} horas):\n\n{}\n",
+        user.name, cfg.public_url, PASSWORD_RESET_TTL_HOURS, token
+    );
+    if let Err(e) = state
+        .mailer
+        .send(&user.email, "Cambio de contraseña", &body)
+        .await
+    {
+        eprintln!("Mail error (password reset): {}", e);
+    }
+
+    Ok(())
+}
+
+#[post("/password-reset")]
+async fn password_reset(
+    state: web::Data<AppState>,
+    body: web::Json<PasswordResetDto>,
+) -> Result<HttpResponse, ApiError> {
+    let dto = body.into_inner();
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    state
+        .passwords
+        .validate_policy(&dto.password)
+        .map_err(ApiError::BadRequest)?;
+
+    let col = users_collection(&state);
+    let invalid = || ApiError::BadRequest("Invalid or expired reset token".into());
+
+    let user = col
+        .find_one(
+            doc! { "password_reset.token_hash": api_key::hash_key(dto.token.trim()) },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo find_one error (password reset): {:?}", e);
+            ApiError::Internal
+        })?
+        .ok_or_else(invalid)?;
+
+    if user
+        .password_reset
+        .as_ref()
+        .is_none_or(|r| r.expires_at <= Utc::now())
+    {
+        return Err(invalid());
+    }
+
+    let hash = state.passwords.hash(&dto.password).await.map_err(|e| {
+        eprintln!("Password hash error (reset): {}", e);
+        ApiError::Internal
+    })?;
+
+    col.update_one(
+        doc! { "_id": user.id },
+        doc! {
+            "$set": { "password_hash": hash, "tokens_valid_after": bson_time(Utc::now()) },
+            "$unset": { "password_reset": "" },
+        },
+        None,
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Mongo update_one error (password reset): {:?}", e);
+        ApiError::Internal
+    })?;
+
+    state.login_guard.unlock_email(&user.email).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+#[get("/unlock")]
+async fn unlock(
+    state: web::Data<AppState>,
+    query: web::Query<UnlockQuery>,
+) -> Result<HttpResponse, ApiError> {
+    if !state
+        .login_guard
+        .unlock_with_token(query.token.trim())
+        .await?
+    {
+        return Err(ApiError::BadRequest(
+            "Invalid or expired unlock token".into(),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
 #[post("/me")]
 async fn me(user: AuthUser) -> Result<HttpResponse, ApiError> {
     Ok(HttpResponse::Ok().json(serde_json::json!({
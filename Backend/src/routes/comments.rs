@@ -0,0 +1,472 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use bson::{doc, oid::ObjectId};
+use chrono::Utc;
+use futures::StreamExt;
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    IndexModel,
+};
+use std::collections::{HashMap, HashSet};
+use validator::Validate;
+
+use crate::{
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        api_key::ApiScope,
+        comment::{CommentDoc, CommentOut, CreateCommentDto, UpdateCommentDto},
+        file::{ActivityActor, FileDoc},
+        notification::NotificationKind,
+        user::User,
+    },
+    routes::{
+        auth::users_collection,
+        files::{file_audience, find_accessible, parse_file_id},
+    },
+    utils::{
+        events::LiveEventKind,
+        notify::{self, Notice},
+    },
+};
+
+const COMMENTS_LIMIT: i64 = 1000;
+const MAX_MENTIONS: usize = 20;
+const PREVIEW_CHARS: usize = 200;
+
+pub(crate) fn comments_collection(state: &AppState) -> mongodb::Collection<CommentDoc> {
+    state.db.collection::<CommentDoc>("file_comments")
+}
+
+pub async fn ensure_indexes(state: &AppState) {
+    let options = IndexOptions::builder()
+        .name(Some("comments_file".to_string()))
+        .build();
+    let model = IndexModel::builder()
+        .keys(doc! { "file_id": 1, "created_at": 1 })
+        .options(options)
+        .build();
+
+    if let Err(e) = comments_collection(state).create_index(model, None).await {
+        eprintln!("Mongo create_index error (comments_file): {:?}", e);
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_comments)
+        .service(create_comment)
+        .service(update_comment)
+        .service(delete_comment);
+}
+
+// Al borrar archivos se van sus comentarios
+pub(crate) async fn remove_for_files(
+    state: &AppState,
+    file_ids: Vec<String>,
+) -> Result<(), ApiError> {
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+    comments_collection(state)
+        .delete_many(doc! { "file_id": { "$in": file_ids } }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    Ok(())
+}
+
+// Emails escritos como `@ana@ejemplo.com` en el cuerpo
+fn mentioned_emails(body: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for word in body.split_whitespace() {
+        let Some(handle) = word.strip_prefix('@') else {
+            continue;
+        };
+        let handle = handle
+            .trim_end_matches(|c: char| ",.;:!?)]}\"'".contains(c))
+            .to_lowercase();
+        if handle.contains('@') && !out.contains(&handle) {
+            out.push(handle);
+        }
+        if out.len() == MAX_MENTIONS {
+            break;
+        }
+    }
+    out
+}
+
+// Solo cuentan las menciones a usuarios que ven el archivo
+async fn resolve_mentions(
+    state: &AppState,
+    file: &FileDoc,
+    body: &str,
+) -> Result<Vec<String>, ApiError> {
+    let emails = mentioned_emails(body);
+    if emails.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let audience = file_audience(state, file).await;
+    let mut cursor = users_collection(state)
+        .find(doc! { "email": { "$in": emails } }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out = Vec::new();
+    while let Some(item) = cursor.next().await {
+        let u = item.map_err(|_| ApiError::Internal)?;
+        let id = u.id.to_hex();
+        if audience.contains(&id) {
+            out.push(id);
+        }
+    }
+    Ok(out)
+}
+
+fn preview(body: &str) -> String {
+    let mut out: String = body.chars().take(PREVIEW_CHARS).collect();
+    if body.chars().count() > PREVIEW_CHARS {
+        out.push('…');
+    }
+    out
+}
+
+fn parse_comment_id(raw: &str) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(raw).map_err(|_| ApiError::BadRequest("Invalid comment id".into()))
+}
+
+async fn find_comment(
+    state: &AppState,
+    file: &FileDoc,
+    raw_id: &str,
+) -> Result<CommentDoc, ApiError> {
+    let id = parse_comment_id(raw_id)?;
+    comments_collection(state)
+        .find_one(doc! { "_id": id, "file_id": file.id.to_hex() }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("Comment not found".into()))
+}
+
+async fn authors(state: &AppState, ids: HashSet<&str>) -> Result<HashMap<String, User>, ApiError> {
+    let ids: Vec<ObjectId> = ids
+        .into_iter()
+        .filter_map(|id| ObjectId::parse_str(id).ok())
+        .collect();
+    let mut cursor = users_collection(state)
+        .find(doc! { "_id": { "$in": ids } }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out = HashMap::new();
+    while let Some(item) = cursor.next().await {
+        let u = item.map_err(|_| ApiError::Internal)?;
+        out.insert(u.id.to_hex(), u);
+    }
+    Ok(out)
+}
+
+fn to_out(c: CommentDoc, authors: &HashMap<String, User>) -> CommentOut {
+    let author = (!c.deleted).then(|| {
+        let u = authors.get(&c.author_id);
+        ActivityActor {
+            name: u.map(|u| u.name.clone()),
+            email: u.map(|u| u.email.clone()),
+            id: c.author_id.clone(),
+        }
+    });
+
+    CommentOut {
+        id: c.id.to_hex(),
+        parent_id: c.parent_id,
+        author,
+        body: (!c.deleted).then_some(c.body),
+        mentions: c.mentions,
+        anchor: c.anchor,
+        deleted: c.deleted,
+        edited_at: c.edited_at,
+        created_at: c.created_at,
+        replies: Vec::new(),
+    }
+}
+
+// Arma los hilos a partir de la lista plana (ordenada por fecha)
+fn build_threads(comments: Vec<CommentDoc>, authors: &HashMap<String, User>) -> Vec<CommentOut> {
+    let ids: HashSet<String> = comments.iter().map(|c| c.id.to_hex()).collect();
+    let mut children: HashMap<String, Vec<CommentOut>> = HashMap::new();
+
+    for c in comments {
+        // Una respuesta cuyo padre ya no existe sube a la raíz
+        let key = c
+            .parent_id
+            .clone()
+            .filter(|p| ids.contains(p))
+            .unwrap_or_default();
+        children.entry(key).or_default().push(to_out(c, authors));
+    }
+
+    fn attach(node: &mut CommentOut, children: &mut HashMap<String, Vec<CommentOut>>) {
+        if let Some(mut replies) = children.remove(&node.id) {
+            for r in replies.iter_mut() {
+                attach(r, children);
+            }
+            node.replies = replies;
+        }
+    }
+
+    let mut roots = children.remove("").unwrap_or_default();
+    for root in roots.iter_mut() {
+        attach(root, &mut children);
+    }
+    roots
+}
+
+#[get("/{id}/comments")]
+async fn list_comments(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let id = parse_file_id(path.into_inner())?;
+    let (file, _) = find_accessible(&state, &user, id).await?;
+
+    let options = FindOptions::builder()
+        .sort(doc! { "created_at": 1 })
+        .limit(COMMENTS_LIMIT)
+        .build();
+
+    let mut cursor = comments_collection(&state)
+        .find(doc! { "file_id": file.id.to_hex() }, options)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut comments = Vec::new();
+    while let Some(item) = cursor.next().await {
+        comments.push(item.map_err(|_| ApiError::Internal)?);
+    }
+
+    let authors = authors(
+        &state,
+        comments.iter().map(|c| c.author_id.as_str()).collect(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(build_threads(comments, &authors)))
+}
+
+#[post("/{id}/comments")]
+async fn create_comment(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<CreateCommentDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let mut dto = body.into_inner();
+    dto.body = dto.body.trim().to_string();
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let id = parse_file_id(path.into_inner())?;
+    let (file, _) = find_accessible(&state, &user, id).await?;
+
+    let parent = match dto.parent_id.as_deref() {
+        Some(raw) => Some(find_comment(&state, &file, raw).await?).filter(|p| !p.deleted),
+        None => None,
+    };
+    if dto.parent_id.is_some() && parent.is_none() {
+        return Err(ApiError::NotFound("Comment not found".into()));
+    }
+    let mentions = resolve_mentions(&state, &file, &dto.body).await?;
+
+    let now = Utc::now();
+    let comment = CommentDoc {
+        id: ObjectId::new(),
+        file_id: file.id.to_hex(),
+        author_id: user.user_id.clone(),
+        parent_id: parent.as_ref().map(|p| p.id.to_hex()),
+        body: dto.body,
+        mentions,
+        anchor: dto.anchor,
+        deleted: false,
+        edited_at: None,
+        created_at: now,
+    };
+
+    comments_collection(&state)
+        .insert_one(&comment, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (create_comment): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    let authors = authors(&state, HashSet::from([user.user_id.as_str()])).await?;
+    let out = to_out(comment.clone(), &authors);
+
+    state.events.publish(
+        file_audience(&state, &file).await,
+        LiveEventKind::CommentCreated,
+        serde_json::json!({ "file_id": file.id.to_hex(), "comment": &out }),
+    );
+
+    // Dueño del archivo y autor del comentario respondido, sin repetir a los mencionados
+    let mut involved: Vec<String> = vec![file.owner_id.clone()];
+    if let Some(parent) = parent {
+        involved.push(parent.author_id);
+    }
+    involved.retain(|id| *id != user.user_id && !comment.mentions.contains(id));
+    involved.dedup();
+
+    notify_mentions(&state, &user, &file, &comment, comment.mentions.clone()).await;
+    notify::send(
+        &state,
+        involved,
+        Notice::new(
+            NotificationKind::Comment,
+            format!("Nuevo comentario en \"{}\"", file.original_name),
+            format!(
+                "{} comentó en \"{}\":\n\n{}",
+                user.email,
+                file.original_name,
+                preview(&comment.body)
+            ),
+        )
+        .link(format!("/api/files/{}/comments", file.id.to_hex()))
+        .data(doc! { "file_id": file.id.to_hex(), "comment_id": comment.id.to_hex() }),
+    )
+    .await;
+
+    Ok(HttpResponse::Created().json(out))
+}
+
+async fn notify_mentions(
+    state: &AppState,
+    user: &AuthUser,
+    file: &FileDoc,
+    comment: &CommentDoc,
+    mentioned: Vec<String>,
+) {
+    let recipients: Vec<String> = mentioned
+        .into_iter()
+        .filter(|id| *id != user.user_id)
+        .collect();
+    notify::send(
+        state,
+        recipients,
+        Notice::new(
+            NotificationKind::Mention,
+            format!("Te mencionaron en \"{}\"", file.original_name),
+            format!(
+                "{} te mencionó en \"{}\":\n\n{}",
+                user.email,
+                file.original_name,
+                preview(&comment.body)
+            ),
+        )
+        .link(format!("/api/files/{}/comments", file.id.to_hex()))
+        .data(doc! { "file_id": file.id.to_hex(), "comment_id": comment.id.to_hex() }),
+    )
+    .await;
+}
+
+#[patch("/{id}/comments/{comment_id}")]
+async fn update_comment(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: web::Json<UpdateCommentDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let mut dto = body.into_inner();
+    dto.body = dto.body.trim().to_string();
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let (file_id, comment_id) = path.into_inner();
+    let (file, _) = find_accessible(&state, &user, parse_file_id(file_id)?).await?;
+    let comment = find_comment(&state, &file, &comment_id).await?;
+    if comment.deleted {
+        return Err(ApiError::NotFound("Comment not found".into()));
+    }
+    if comment.author_id != user.user_id {
+        return Err(ApiError::Forbidden(
+            "Only the author can edit this comment".into(),
+        ));
+    }
+
+    let mentions = resolve_mentions(&state, &file, &dto.body).await?;
+    let added: Vec<String> = mentions
+        .iter()
+        .filter(|id| !comment.mentions.contains(id))
+        .cloned()
+        .collect();
+    let now = Utc::now();
+
+    comments_collection(&state)
+        .update_one(
+            doc! { "_id": comment.id },
+            doc! { "$set": { "body": &dto.body, "mentions": &mentions, "edited_at": bson_time(now) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let comment = CommentDoc {
+        body: dto.body,
+        mentions,
+        edited_at: Some(now),
+        ..comment
+    };
+    // Solo se avisa a quien no estaba mencionado antes
+    notify_mentions(&state, &user, &file, &comment, added).await;
+
+    let authors = authors(&state, HashSet::from([user.user_id.as_str()])).await?;
+    Ok(HttpResponse::Ok().json(to_out(comment, &authors)))
+}
+
+#[delete("/{id}/comments/{comment_id}")]
+async fn delete_comment(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let (file_id, comment_id) = path.into_inner();
+    let (file, _) = find_accessible(&state, &user, parse_file_id(file_id)?).await?;
+    let comment = find_comment(&state, &file, &comment_id).await?;
+    if comment.deleted {
+        return Err(ApiError::NotFound("Comment not found".into()));
+    }
+    if comment.author_id != user.user_id {
+        return Err(ApiError::Forbidden(
+            "Only the author can delete this comment".into(),
+        ));
+    }
+
+    let col = comments_collection(&state);
+    let has_replies = col
+        .count_documents(doc! { "parent_id": comment.id.to_hex() }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        > 0;
+
+    if has_replies {
+        col.update_one(
+            doc! { "_id": comment.id },
+            doc! { "$set": { "deleted": true, "body": "", "mentions": [], "anchor": null } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    } else {
+        col.delete_one(doc! { "_id": comment.id }, None)
+            .await
+            .map_err(|_| ApiError::Internal)?;
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
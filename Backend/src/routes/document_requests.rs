@@ -0,0 +1,478 @@
+use actix_multipart::Multipart;
+use actix_web::{get, patch, post, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId};
+use chrono::Utc;
+use futures::StreamExt;
+use validator::Validate;
+
+use crate::{
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::{AuthUser, Colaborador, RequireRole},
+    models::{
+        api_key::ApiScope,
+        audit::AuditAction,
+        document_request::{
+            CreateDocumentRequestDto, DocumentRequestDoc, DocumentRequestOut,
+            DocumentRequestsQuery, RequestStatus, ReviewSubmissionDto, Submission,
+            SubmissionStatus,
+        },
+        notification::NotificationKind,
+        role::Role,
+        webhook::WebhookEvent,
+    },
+    routes::{
+        auth::users_collection,
+        files::{
+            attachment, field_meta, file_payload, files_collection, record_download, save_upload,
+            UploadTarget,
+        },
+    },
+    utils::{
+        audit::{self, AuditEntry},
+        events::LiveEventKind,
+        notify::{self, Notice},
+        search_index, storage, webhooks,
+    },
+};
+
+fn requests_collection(state: &AppState) -> mongodb::Collection<DocumentRequestDoc> {
+    state
+        .db
+        .collection::<DocumentRequestDoc>("document_requests")
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_request)
+        .service(list_requests)
+        .service(get_request)
+        .service(submit)
+        .service(review_submission)
+        .service(download_submission);
+}
+
+fn parse_request_id(raw: &str) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(raw).map_err(|_| ApiError::BadRequest("Invalid request id".into()))
+}
+
+// Solo el colaborador que la creó y el cliente destinatario ven la solicitud
+async fn find_for_participant(
+    state: &AppState,
+    user: &AuthUser,
+    raw_id: &str,
+) -> Result<DocumentRequestDoc, ApiError> {
+    let id = parse_request_id(raw_id)?;
+
+    requests_collection(state)
+        .find_one(
+            doc! {
+                "_id": id,
+                "$or": [ { "collaborator_id": &user.user_id }, { "client_id": &user.user_id } ],
+            },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("Request not found".into()))
+}
+
+#[post("")]
+async fn create_request(
+    guard: RequireRole<Colaborador>,
+    state: web::Data<AppState>,
+    body: web::Json<CreateDocumentRequestDto>,
+) -> Result<HttpResponse, ApiError> {
+    let user = guard.user;
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let mut dto = body.into_inner();
+    dto.title = dto.title.trim().to_string();
+    dto.client_email = dto.client_email.map(|e| e.trim().to_lowercase());
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let filter = match (dto.client_id.as_deref(), dto.client_email.as_deref()) {
+        (Some(id), _) => {
+            let id = ObjectId::parse_str(id)
+                .map_err(|_| ApiError::BadRequest("Invalid client id".into()))?;
+            doc! { "_id": id }
+        }
+        (None, Some(email)) => doc! { "email": email },
+        (None, None) => {
+            return Err(ApiError::BadRequest(
+                "client_id or client_email is required".into(),
+            ))
+        }
+    };
+
+    let client = users_collection(&state)
+        .find_one(filter, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("Client not found".into()))?;
+
+    if client.role != Role::Cliente || client.disabled {
+        return Err(ApiError::BadRequest(
+            "Requests can only be addressed to active clientes".into(),
+        ));
+    }
+
+    let now = Utc::now();
+    if dto.due_date.is_some_and(|d| d <= now) {
+        return Err(ApiError::BadRequest(
+            "due_date must be in the future".into(),
+        ));
+    }
+
+    let request = DocumentRequestDoc {
+        id: ObjectId::new(),
+        collaborator_id: user.user_id.clone(),
+        client_id: client.id.to_hex(),
+        title: dto.title,
+        description: dto
+            .description
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty()),
+        required_types: dto
+            .required_types
+            .into_iter()
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        due_date: dto.due_date,
+        status: RequestStatus::Pending,
+        submissions: Vec::new(),
+        created_at: now,
+        updated_at: now,
+    };
+
+    requests_collection(&state)
+        .insert_one(&request, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (create_request): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    notify::send(
+        &state,
+        vec![request.client_id.clone()],
+        Notice::new(
+            NotificationKind::RequestCreated,
+            format!("Nueva solicitud: {}", request.title),
+            format!("Te pidieron documentos: \"{}\".", request.title),
+        )
+        .link(format!("/api/requests/{}", request.id.to_hex()))
+        .data(doc! { "request_id": request.id.to_hex() }),
+    )
+    .await;
+
+    Ok(HttpResponse::Created().json(DocumentRequestOut::from(request)))
+}
+
+// Colaboradores ven las que crearon, clientes las que recibieron
+#[get("")]
+async fn list_requests(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    query: web::Query<DocumentRequestsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let mut cursor = requests_collection(&state)
+        .find(
+            doc! { "$or": [ { "collaborator_id": &user.user_id }, { "client_id": &user.user_id } ] },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out: Vec<DocumentRequestOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        let r = DocumentRequestOut::from(item.map_err(|_| ApiError::Internal)?);
+        // overdue se calcula al leer, por eso el filtro se aplica aquí
+        if query.status.is_none_or(|s| s == r.status) {
+            out.push(r);
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+#[get("/{id}")]
+async fn get_request(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let request = find_for_participant(&state, &user, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(DocumentRequestOut::from(request)))
+}
+
+// El cliente sube un archivo (multipart, 1 por request) que queda en su espacio personal
+#[post("/{id}/submissions")]
+async fn submit(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let request = find_for_participant(&state, &user, &path.into_inner()).await?;
+    if request.client_id != user.user_id {
+        return Err(ApiError::Forbidden(
+            "Only the addressed client can submit files".into(),
+        ));
+    }
+    if request.status == RequestStatus::Approved {
+        return Err(ApiError::BadRequest("Request is already approved".into()));
+    }
+
+    let Some(item) = payload.next().await else {
+        return Err(ApiError::BadRequest("No file uploaded".into()));
+    };
+    let field = item.map_err(|_| ApiError::BadRequest("Invalid multipart".into()))?;
+
+    let (filename, mime) = field_meta(&field);
+    if !request.accepts(&filename, &mime) {
+        return Err(ApiError::BadRequest(format!(
+            "File type not accepted; expected one of: {}",
+            request.required_types.join(", ")
+        )));
+    }
+
+    let target = UploadTarget {
+        owner_id: user.user_id.clone(),
+        org_id: None,
+        folder_id: None,
+        strip_metadata: None,
+    };
+    let file = save_upload(&state, field, target, None).await?;
+
+    let now = Utc::now();
+    let submission = Submission {
+        id: ObjectId::new().to_hex(),
+        file_id: file.id.to_hex(),
+        file_name: file.original_name.clone(),
+        mime: file.mime.clone(),
+        size: file.size,
+        status: SubmissionStatus::Submitted,
+        comment: None,
+        submitted_at: now,
+        reviewed_at: None,
+    };
+    let entry = bson::to_bson(&submission).map_err(|_| ApiError::Internal)?;
+    let status = bson::to_bson(&RequestStatus::Submitted).map_err(|_| ApiError::Internal)?;
+
+    // La solicitud pudo aprobarse mientras subía el archivo
+    let res = requests_collection(&state)
+        .update_one(
+            doc! { "_id": request.id, "status": { "$ne": "approved" } },
+            doc! {
+                "$push": { "submissions": entry },
+                "$set": { "status": status, "updated_at": bson_time(now) },
+            },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    if res.matched_count == 0 {
+        storage::remove(&file.stored_name);
+        let _ = files_collection(&state)
+            .delete_one(doc! { "_id": file.id }, None)
+            .await;
+        search_index::remove_for_files(&state, vec![file.id]).await;
+        return Err(ApiError::BadRequest("Request is already approved".into()));
+    }
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::FileUpload)
+            .by(&user)
+            .target("file", file.id.to_hex())
+            .details(doc! { "request_id": request.id.to_hex(), "name": &file.original_name, "size": file.size }),
+    )
+    .await;
+    // El archivo es del cliente, pero quien lo pidió también escucha la entrega
+    webhooks::dispatch(
+        &state,
+        WebhookEvent::FileUploaded,
+        &[file.owner_id.clone(), request.collaborator_id.clone()],
+        file_payload(&file),
+    )
+    .await;
+    state.events.publish(
+        vec![request.collaborator_id.clone()],
+        LiveEventKind::FileSharedWithYou,
+        serde_json::json!({ "request_id": request.id.to_hex(), "file": file_payload(&file) }),
+    );
+
+    notify::send(
+        &state,
+        vec![request.collaborator_id.clone()],
+        Notice::new(
+            NotificationKind::RequestSubmitted,
+            format!("Entrega recibida: {}", request.title),
+            format!(
+                "{} subió \"{}\" a la solicitud \"{}\".",
+                user.email, submission.file_name, request.title
+            ),
+        )
+        .link(format!("/api/requests/{}", request.id.to_hex()))
+        .data(doc! { "request_id": request.id.to_hex(), "submission_id": &submission.id }),
+    )
+    .await;
+
+    Ok(HttpResponse::Created().json(submission))
+}
+
+#[patch("/{id}/submissions/{submission_id}")]
+async fn review_submission(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: web::Json<ReviewSubmissionDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+    body.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let (request_id, submission_id) = path.into_inner();
+    let request = find_for_participant(&state, &user, &request_id).await?;
+    if request.collaborator_id != user.user_id {
+        return Err(ApiError::Forbidden(
+            "Only the requesting colaborador can review".into(),
+        ));
+    }
+
+    let submission = request
+        .submissions
+        .iter()
+        .find(|s| s.id == submission_id)
+        .ok_or_else(|| ApiError::NotFound("Submission not found".into()))?;
+    if submission.status != SubmissionStatus::Submitted {
+        return Err(ApiError::BadRequest(
+            "Submission was already reviewed".into(),
+        ));
+    }
+
+    let submission_status = if body.approve {
+        SubmissionStatus::Approved
+    } else {
+        SubmissionStatus::Rejected
+    };
+
+    let comment = body
+        .comment
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    let now = Utc::now();
+
+    // Solo si sigue sin revisar: de dos revisiones simultáneas gana una
+    let col = requests_collection(&state);
+    let res = col
+        .update_one(
+            doc! {
+                "_id": request.id,
+                "submissions": { "$elemMatch": { "id": &submission_id, "status": "submitted" } },
+            },
+            doc! { "$set": {
+                "submissions.$.status": bson::to_bson(&submission_status).map_err(|_| ApiError::Internal)?,
+                "submissions.$.comment": comment,
+                "submissions.$.reviewed_at": bson_time(now),
+                "updated_at": bson_time(now),
+            } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    if res.modified_count == 0 {
+        return Err(ApiError::BadRequest(
+            "Submission was already reviewed".into(),
+        ));
+    }
+
+    // El estado de la solicitud sale de todas sus entregas; una aprobación no se pisa
+    let mut request = find_for_participant(&state, &user, &request_id).await?;
+    let status = request.status_from_submissions();
+    let mut filter = doc! { "_id": request.id };
+    if status != RequestStatus::Approved {
+        filter.insert("status", doc! { "$ne": "approved" });
+    }
+    let res = col
+        .update_one(
+            filter,
+            doc! { "$set": { "status": bson::to_bson(&status).map_err(|_| ApiError::Internal)? } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    if res.matched_count > 0 {
+        request.status = status;
+    }
+    let kind = if body.approve {
+        LiveEventKind::RequestApproved
+    } else {
+        LiveEventKind::RequestRejected
+    };
+    state.events.publish(
+        vec![request.client_id.clone()],
+        kind,
+        serde_json::json!({ "request_id": request.id.to_hex(), "submission_id": &submission_id, "comment": comment }),
+    );
+
+    let verdict = if body.approve {
+        "aprobada"
+    } else {
+        "rechazada"
+    };
+    let mut text = format!("Tu entrega para \"{}\" fue {}.", request.title, verdict);
+    if let Some(comment) = comment {
+        text.push_str(&format!("\n\nComentario: {}", comment));
+    }
+    notify::send(
+        &state,
+        vec![request.client_id.clone()],
+        Notice::new(NotificationKind::RequestReviewed, format!("Entrega {}: {}", verdict, request.title), text)
+            .link(format!("/api/requests/{}", request.id.to_hex()))
+            .data(doc! { "request_id": request.id.to_hex(), "submission_id": &submission_id, "approved": body.approve }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(DocumentRequestOut::from(request)))
+}
+
+#[get("/{id}/submissions/{submission_id}/download")]
+async fn download_submission(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let (request_id, submission_id) = path.into_inner();
+    let request = find_for_participant(&state, &user, &request_id).await?;
+
+    let submission = request
+        .submissions
+        .iter()
+        .find(|s| s.id == submission_id)
+        .ok_or_else(|| ApiError::NotFound("Submission not found".into()))?;
+
+    let file_id = ObjectId::parse_str(&submission.file_id).map_err(|_| ApiError::Internal)?;
+    let file = files_collection(&state)
+        .find_one(doc! { "_id": file_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("File not found".into()))?;
+
+    record_download(&state, &req, &file, Some(&user), "request").await;
+    attachment(file)
+}
@@ -0,0 +1,409 @@
+use actix_multipart::Multipart;
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use validator::Validate;
+
+use crate::{
+    config::AppConfig,
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        api_key::ApiScope,
+        audit::AuditAction,
+        file::FileDoc,
+        file_drop::{CreateDropDto, CreatedDrop, DropOut, FileDropDoc, PublicDropInfo},
+        notification::NotificationKind,
+        webhook::WebhookEvent,
+    },
+    routes::{
+        auth::{client_ip, users_collection},
+        files::{
+            file_audience, file_payload, org_quota_limit, save_upload, warn_if_near_quota,
+            UploadLimit, UploadTarget,
+        },
+        folders::{find_accessible_folder, folders_collection},
+        orgs,
+    },
+    utils::{
+        api_key,
+        audit::{self, AuditEntry},
+        events::LiveEventKind,
+        notify::{self, Notice},
+        random::random_token,
+        webhooks,
+    },
+};
+
+const DEFAULT_EXPIRY_HOURS: i64 = 7 * 24;
+const PASSWORD_HEADER: &str = "X-Drop-Password";
+
+fn drops_collection(state: &AppState) -> mongodb::Collection<FileDropDoc> {
+    state.db.collection::<FileDropDoc>("file_drops")
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    // /public/* antes que /{id}
+    cfg.service(public_info)
+        .service(public_upload)
+        .service(create_drop)
+        .service(list_drops)
+        .service(revoke_drop);
+}
+
+#[post("")]
+async fn create_drop(
+    user: AuthUser,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    body: web::Json<CreateDropDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let mut dto = body.into_inner();
+    dto.label = dto.label.trim().to_string();
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    // El enlace deposita en la carpeta indicada o en la raíz del espacio activo
+    let (org_id, folder_id) = match dto.folder_id.as_deref() {
+        Some(raw) => {
+            let (folder, _) = find_accessible_folder(&state, &user, raw).await?;
+            (folder.org_id.clone(), Some(folder.id.to_hex()))
+        }
+        None => (
+            orgs::active_org(&state, &user)
+                .await?
+                .map(|(o, _)| o.id.to_hex()),
+            None,
+        ),
+    };
+
+    let password_hash = match dto.password.as_deref() {
+        Some(p) => Some(state.passwords.hash(p).await.map_err(|e| {
+            eprintln!("Password hash error (create_drop): {}", e);
+            ApiError::Internal
+        })?),
+        None => None,
+    };
+
+    let token = random_token(32);
+    let now = Utc::now();
+    let drop = FileDropDoc {
+        id: ObjectId::new(),
+        owner_id: user.user_id.clone(),
+        org_id,
+        folder_id,
+        label: dto.label,
+        token_hash: api_key::hash_key(&token),
+        password_hash,
+        max_files: dto.max_files,
+        max_file_bytes: dto.max_file_bytes,
+        files_received: 0,
+        expires_at: now + Duration::hours(dto.expires_in_hours.unwrap_or(DEFAULT_EXPIRY_HOURS)),
+        revoked_at: None,
+        created_at: now,
+    };
+
+    drops_collection(&state)
+        .insert_one(&drop, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (create_drop): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    Ok(HttpResponse::Created().json(CreatedDrop {
+        url: format!("{}/api/drops/public/{}", cfg.public_url, token),
+        token,
+        drop: drop.into(),
+    }))
+}
+
+#[get("")]
+async fn list_drops(user: AuthUser, state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let mut cursor = drops_collection(&state)
+        .find(doc! { "owner_id": &user.user_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out: Vec<DropOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        out.push(item.map_err(|_| ApiError::Internal)?.into());
+    }
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+#[delete("/{id}")]
+async fn revoke_drop(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| ApiError::BadRequest("Invalid drop id".into()))?;
+
+    let res = drops_collection(&state)
+        .update_one(
+            doc! { "_id": id, "owner_id": &user.user_id, "revoked_at": null },
+            doc! { "$set": { "revoked_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    if res.matched_count == 0 {
+        return Err(ApiError::NotFound("Drop link not found".into()));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+// Enlaces vencidos, revocados o llenos se ven igual que los inexistentes
+async fn find_open_drop(state: &AppState, token: &str) -> Result<FileDropDoc, ApiError> {
+    let drop = drops_collection(state)
+        .find_one(doc! { "token_hash": api_key::hash_key(token) }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .filter(|d| d.is_open(Utc::now()))
+        .ok_or_else(|| ApiError::NotFound("Drop link not found or expired".into()))?;
+    if !owner_still_allowed(state, &drop).await? {
+        return Err(ApiError::NotFound("Drop link not found or expired".into()));
+    }
+    Ok(drop)
+}
+
+// El dueño tiene que seguir existiendo y, si el enlace es de una organización, seguir en ella
+async fn owner_still_allowed(state: &AppState, drop: &FileDropDoc) -> Result<bool, ApiError> {
+    let Ok(owner_id) = ObjectId::parse_str(&drop.owner_id) else {
+        return Ok(false);
+    };
+    let exists = users_collection(state)
+        .count_documents(doc! { "_id": owner_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        > 0;
+    if !exists {
+        return Ok(false);
+    }
+
+    match drop.org_id.as_deref() {
+        None => Ok(true),
+        Some(org_id) => match orgs::membership(state, &drop.owner_id, org_id).await {
+            Ok(_) => Ok(true),
+            Err(ApiError::NotFound(_)) | Err(ApiError::BadRequest(_)) => Ok(false),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+// Al borrar la cuenta o salir de una organización sus enlaces dejan de recibir archivos
+pub(crate) async fn revoke_all_of(
+    state: &AppState,
+    owner_id: &str,
+    org_id: Option<&str>,
+) -> Result<(), ApiError> {
+    let mut filter = doc! { "owner_id": owner_id, "revoked_at": null };
+    if let Some(org_id) = org_id {
+        filter.insert("org_id", org_id);
+    }
+    drops_collection(state)
+        .update_many(
+            filter,
+            doc! { "$set": { "revoked_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo update_many error (drops revoke_all_of): {:?}", e);
+            ApiError::Internal
+        })?;
+    Ok(())
+}
+
+#[get("/public/{token}")]
+async fn public_info(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let drop = find_open_drop(&state, &path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(PublicDropInfo {
+        label: drop.label,
+        password_required: drop.password_hash.is_some(),
+        remaining_files: drop.max_files.map(|max| max - drop.files_received),
+        max_file_bytes: drop.max_file_bytes,
+        expires_at: drop.expires_at,
+    }))
+}
+
+fn file_too_large() -> ApiError {
+    ApiError::BadRequest("File exceeds the maximum size allowed by this link".into())
+}
+
+// Sin autenticación: sube un archivo (multipart, 1 por request) a la carpeta del enlace
+#[post("/public/{token}/upload")]
+async fn public_upload(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, ApiError> {
+    let drop = find_open_drop(&state, &path.into_inner()).await?;
+    let drop_key = format!("drop:{}", drop.id.to_hex());
+    let ip = client_ip(&req);
+
+    if let Some(hash) = drop.password_hash.as_deref() {
+        // Los intentos fallidos cuentan en el mismo guard que el login
+        state.login_guard.attempt(&ip, &drop_key).await?;
+
+        let password = req
+            .headers()
+            .get(PASSWORD_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        let ok = state
+            .passwords
+            .verify(password, hash)
+            .await
+            .map(|v| v.ok)
+            .unwrap_or(false);
+        if !ok {
+            state.login_guard.record_failure(&ip, &drop_key).await?;
+            return Err(ApiError::Unauthorized("Invalid drop password".into()));
+        }
+        state.login_guard.release(&ip, &drop_key).await?;
+    }
+
+    // Reserva un lugar antes de escribir para respetar max_files con subidas en paralelo
+    let reserved = drops_collection(&state)
+        .update_one(
+            doc! {
+                "_id": drop.id,
+                "revoked_at": null,
+                "$or": [
+                    { "max_files": null },
+                    { "$expr": { "$lt": ["$files_received", "$max_files"] } },
+                ],
+            },
+            doc! { "$inc": { "files_received": 1 } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    if reserved.modified_count == 0 {
+        return Err(ApiError::NotFound("Drop link not found or expired".into()));
+    }
+
+    let result = receive(&state, &drop, &mut payload).await;
+    if result.is_err() {
+        drops_collection(&state)
+            .update_one(
+                doc! { "_id": drop.id },
+                doc! { "$inc": { "files_received": -1 } },
+                None,
+            )
+            .await
+            .map_err(|_| ApiError::Internal)?;
+    }
+    let file = result?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::FileUpload)
+            .target("file", file.id.to_hex())
+            .details(doc! { "drop_id": drop.id.to_hex(), "name": &file.original_name, "size": file.size }),
+    )
+    .await;
+    let audience = file_audience(&state, &file).await;
+    webhooks::dispatch(
+        &state,
+        WebhookEvent::FileUploaded,
+        &audience,
+        file_payload(&file),
+    )
+    .await;
+    state
+        .events
+        .publish(audience, LiveEventKind::FileUploaded, file_payload(&file));
+    notify::send(
+        &state,
+        vec![drop.owner_id.clone()],
+        Notice::new(
+            NotificationKind::FileReceived,
+            format!("Nuevo archivo en \"{}\"", drop.label),
+            format!(
+                "Recibiste un archivo por tu enlace de subida \"{}\":\n\n{} ({} bytes)",
+                drop.label, file.original_name, file.size
+            ),
+        )
+        .link(format!("/api/files/{}", file.id.to_hex()))
+        .data(doc! { "drop_id": drop.id.to_hex(), "file_id": file.id.to_hex() }),
+    )
+    .await;
+    warn_if_near_quota(&state, file.org_id.as_deref(), file.size).await;
+
+    // Solo se confirma la recepción; quien sube no obtiene acceso al archivo
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "ok": true,
+        "name": file.original_name,
+        "size": file.size,
+    })))
+}
+
+// La carpeta tiene que seguir en el espacio del enlace: la organización, o el espacio
+// personal del dueño
+fn folder_filter(id: ObjectId, drop: &FileDropDoc) -> Document {
+    match drop.org_id.as_deref() {
+        Some(org_id) => doc! { "_id": id, "org_id": org_id },
+        None => doc! { "_id": id, "owner_id": &drop.owner_id, "org_id": null },
+    }
+}
+
+async fn receive(
+    state: &AppState,
+    drop: &FileDropDoc,
+    payload: &mut Multipart,
+) -> Result<FileDoc, ApiError> {
+    let Some(item) = payload.next().await else {
+        return Err(ApiError::BadRequest("No file uploaded".into()));
+    };
+    let field = item.map_err(|_| ApiError::BadRequest("Invalid multipart".into()))?;
+
+    // El tope más estricto entre el del enlace y la cuota de la organización
+    let quota = org_quota_limit(state, drop.org_id.as_deref()).await?;
+    let limit = match (drop.max_file_bytes, quota) {
+        (Some(max), Some(q)) if q.max_bytes < max => Some(q),
+        (Some(max), _) => Some(UploadLimit {
+            max_bytes: max,
+            exceeded: file_too_large,
+        }),
+        (None, q) => q,
+    };
+
+    // Si la carpeta ya no existe o cambió de espacio, el archivo cae en la raíz
+    let folder_id = match drop.folder_id.as_deref().map(ObjectId::parse_str) {
+        Some(Ok(id)) => folders_collection(state)
+            .find_one(folder_filter(id, drop), None)
+            .await
+            .map_err(|_| ApiError::Internal)?
+            .map(|f| f.id.to_hex()),
+        _ => None,
+    };
+
+    let target = UploadTarget {
+        owner_id: drop.owner_id.clone(),
+        org_id: drop.org_id.clone(),
+        folder_id,
+        strip_metadata: None,
+    };
+    save_upload(state, field, target, limit).await
+}
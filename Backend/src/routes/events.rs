@@ -0,0 +1,97 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use futures::stream;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::{
+    db::AppState,
+    errors::ApiError,
+    middleware::auth::{self, AuthUser},
+    models::api_key::ApiScope,
+    utils::events::LiveEvent,
+};
+
+// Comentario SSE periódico para que proxies no corten la conexión; en cada uno se revalida
+// la credencial (cuenta deshabilitada, sesiones revocadas, token o API key vencidos)
+const KEEP_ALIVE_SECS: u64 = 25;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(stream_events);
+}
+
+// Server-Sent Events con los eventos del usuario autenticado
+#[get("/stream")]
+async fn stream_events(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let credential = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let period = std::time::Duration::from_secs(KEEP_ALIVE_SECS);
+    let conn = Connection {
+        rx: state.events.subscribe(),
+        ticker: tokio::time::interval_at(tokio::time::Instant::now() + period, period),
+        user_id: user.user_id,
+        credential,
+        state: state.get_ref().clone(),
+    };
+
+    let hello = format!(
+        "event: ready\ndata: {{\"user_id\":\"{}\"}}\n\n",
+        conn.user_id
+    );
+    let first = stream::once(async move { Ok::<_, actix_web::Error>(web::Bytes::from(hello)) });
+
+    let events = stream::unfold(conn, |mut conn| async move {
+        loop {
+            tokio::select! {
+                msg = conn.rx.recv() => match msg {
+                    Ok(event) if event.is_for(&conn.user_id) => {
+                        let chunk = web::Bytes::from(event.to_sse());
+                        return Some((Ok(chunk), conn));
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = conn.ticker.tick() => {
+                    if !conn.still_valid().await {
+                        return None;
+                    }
+                    let chunk = web::Bytes::from_static(b": keep-alive\n\n");
+                    return Some((Ok(chunk), conn));
+                }
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Content-Type", "text/event-stream"))
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(futures::StreamExt::chain(first, events)))
+}
+
+struct Connection {
+    rx: Receiver<LiveEvent>,
+    ticker: tokio::time::Interval,
+    user_id: String,
+    credential: String,
+    state: AppState,
+}
+
+impl Connection {
+    // Un error de la base no corta la conexión; una credencial rechazada sí
+    async fn still_valid(&self) -> bool {
+        match auth::authenticate(&self.state, &self.credential).await {
+            Ok(user) => user.user_id == self.user_id,
+            Err(ApiError::Internal) => true,
+            Err(_) => false,
+        }
+    }
+}
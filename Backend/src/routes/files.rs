@@ -1,99 +1,452 @@
-use actix_multipart::Multipart;
-use actix_web::{delete, get, patch, post, web, HttpResponse};
+use actix_multipart::{Field, Multipart};
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
 use bson::{doc, oid::ObjectId};
 use chrono::Utc;
 use futures::StreamExt;
+use mongodb::options::FindOptions;
 use sanitize_filename::sanitize;
-use std::{fs, io::Write, path::Path};
+use std::{collections::HashMap, io::Write};
+use validator::Validate;
 
 use crate::{
-    db::AppState,
+    config::AppConfig,
+    db::{bson_time, AppState},
     errors::ApiError,
     middleware::auth::AuthUser,
-    models::file::{FileDoc, FileOut, UpdateVisibilityDto},
+    models::{
+        api_key::ApiScope,
+        audit::{AuditAction, AuditEventDoc, AuditEventOut},
+        file::{
+            ActivityActor, FileActivityOut, FileDoc, FileOut, ImageMetadataOut, RenameFileDto,
+            UpdateVisibilityDto, UploadQuery,
+        },
+        folder::FolderQuery,
+        notification::NotificationKind,
+        search::FileTextOut,
+        webhook::WebhookEvent,
+    },
+    routes::{
+        archive,
+        auth::users_collection,
+        comments,
+        folders::{find_accessible_folder, folders_collection},
+        orgs,
+    },
+    utils::{
+        audit::{self, audit_collection, AuditEntry},
+        disposition,
+        events::LiveEventKind,
+        image_meta,
+        notify::{self, Notice},
+        office, search_index, storage, webhooks,
+    },
 };
 
-const UPLOAD_DIR: &str = "uploads";
+const HISTORY_LIMIT: i64 = 200;
+// Porcentaje de la cuota de la organización a partir del cual se avisa a sus administradores
+const QUOTA_WARNING_PERCENT: i64 = 90;
 
-fn files_collection(state: &AppState) -> mongodb::Collection<FileDoc> {
+pub(crate) fn files_collection(state: &AppState) -> mongodb::Collection<FileDoc> {
     state.db.collection::<FileDoc>("files")
 }
 
-fn ensure_upload_dir() -> Result<(), ApiError> {
-    if !Path::new(UPLOAD_DIR).exists() {
-        fs::create_dir_all(UPLOAD_DIR).map_err(|_| ApiError::Internal)?;
+// (bytes, archivos) del espacio personal por owner_id
+pub(crate) async fn storage_usage(
+    state: &AppState,
+    owner_ids: &[String],
+) -> Result<HashMap<String, (i64, i64)>, ApiError> {
+    let pipeline = vec![
+        doc! { "$match": { "owner_id": { "$in": owner_ids }, "org_id": null } },
+        doc! { "$group": { "_id": "$owner_id", "bytes": { "$sum": "$size" }, "count": { "$sum": 1 } } },
+    ];
+
+    let mut cursor = files_collection(state)
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo aggregate error (storage_usage): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    let mut out = HashMap::new();
+    while let Some(item) = cursor.next().await {
+        let d = item.map_err(|_| ApiError::Internal)?;
+        let owner = d.get_str("_id").unwrap_or_default().to_string();
+        let bytes = d
+            .get_i64("bytes")
+            .or_else(|_| d.get_i32("bytes").map(i64::from))
+            .unwrap_or(0);
+        let count = d.get_i32("count").map(i64::from).unwrap_or(0);
+        out.insert(owner, (bytes, count));
     }
-    Ok(())
+
+    Ok(out)
 }
 
-#[post("/upload")]
-pub async fn upload_file(
-    user: AuthUser,
-    state: web::Data<AppState>,
-    mut payload: Multipart,
-) -> Result<HttpResponse, ApiError> {
-    ensure_upload_dir()?;
+// Borra los archivos personales (disco + Mongo) de un usuario; los de organizaciones se quedan
+pub(crate) async fn delete_all_files_of(state: &AppState, owner_id: &str) -> Result<u64, ApiError> {
+    let col = files_collection(state);
 
-    let mut saved: Option<FileDoc> = None;
+    let mut cursor = col
+        .find(doc! { "owner_id": owner_id, "org_id": null }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
 
-    while let Some(item) = payload.next().await {
-        let mut field = item.map_err(|_| ApiError::BadRequest("Invalid multipart".into()))?;
+    let mut file_ids = Vec::new();
+    while let Some(item) = cursor.next().await {
+        let f = item.map_err(|_| ApiError::Internal)?;
+        storage::remove(&f.stored_name);
+        file_ids.push(f.id);
+    }
+    comments::remove_for_files(state, file_ids.iter().map(|id| id.to_hex()).collect()).await?;
+    search_index::remove_for_files(state, file_ids.clone()).await;
+    office::remove_for_files(state, file_ids).await;
 
-        // ✅ En tu versión: content_disposition() regresa referencia, no Option
-        let cd = field.content_disposition();
+    let res = col
+        .delete_many(doc! { "owner_id": owner_id, "org_id": null }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
 
-        let filename = cd
-            .get_filename()
-            .map(|f| sanitize(f))
-            .unwrap_or_else(|| "file.bin".to_string());
+    folders_collection(state)
+        .delete_many(doc! { "owner_id": owner_id, "org_id": null }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    Ok(res.deleted_count)
+}
+
+// Archivo visible para el usuario: propio (personal) o de una organización donde es miembro.
+// El bool indica si además puede modificarlo (dueño, o admin de la organización).
+pub(crate) async fn find_accessible(
+    state: &AppState,
+    user: &AuthUser,
+    id: ObjectId,
+) -> Result<(FileDoc, bool), ApiError> {
+    let not_found = || ApiError::NotFound("File not found".into());
+
+    let file = files_collection(state)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(not_found)?;
+
+    match file.org_id.as_deref() {
+        None if file.owner_id == user.user_id => Ok((file, true)),
+        None => Err(not_found()),
+        Some(org_id) => {
+            let (_, role) = orgs::membership(state, &user.user_id, org_id)
+                .await
+                .map_err(|e| match e {
+                    ApiError::Internal => ApiError::Internal,
+                    _ => not_found(),
+                })?;
+            let can_modify = file.owner_id == user.user_id || role.can_manage();
+            Ok((file, can_modify))
+        }
+    }
+}
 
-        let stored_name = format!("{}_{}", ObjectId::new().to_hex(), filename);
-        let filepath = format!("{}/{}", UPLOAD_DIR, stored_name);
+pub(crate) fn parse_file_id(raw: String) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(raw).map_err(|_| ApiError::BadRequest("Invalid file id".into()))
+}
 
-        // ✅ En tu versión: content_type() es Option<&Mime>
-        let mime = field
-            .content_type()
-            .map(|m| m.to_string())
-            .unwrap_or_else(|| "application/octet-stream".to_string());
+// Respuesta de descarga con el contenido del archivo
+pub(crate) fn attachment(file: FileDoc) -> Result<HttpResponse, ApiError> {
+    let bytes = storage::read(&file.stored_name)?;
 
-        let mut f = fs::File::create(&filepath).map_err(|_| ApiError::Internal)?;
-        let mut size: i64 = 0;
+    Ok(HttpResponse::Ok()
+        .insert_header(("Content-Type", file.mime))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(disposition::attachment(&file.original_name))
+        .body(bytes))
+}
 
-        while let Some(chunk) = field.next().await {
-            let data = chunk.map_err(|_| ApiError::Internal)?;
-            size += data.len() as i64;
-            f.write_all(&data).map_err(|_| ApiError::Internal)?;
+// Quién ve el archivo: su dueño y, si es de una organización, todos sus miembros
+pub(crate) async fn file_audience(state: &AppState, file: &FileDoc) -> Vec<String> {
+    let mut out = vec![file.owner_id.clone()];
+    if let Some(org_id) = file.org_id.as_deref() {
+        if let Ok(org) = orgs::find_org(state, org_id).await {
+            out.extend(
+                org.members
+                    .into_iter()
+                    .map(|m| m.user_id)
+                    .filter(|id| *id != file.owner_id),
+            );
         }
+    }
+    out
+}
+
+// `data` de los eventos de webhook sobre archivos
+pub(crate) fn file_payload(file: &FileDoc) -> serde_json::Value {
+    serde_json::to_value(FileOut::from(file.clone())).unwrap_or_default()
+}
 
-        let now = Utc::now();
-        let doc = FileDoc {
-            id: ObjectId::new(),
+fn quota_exceeded() -> ApiError {
+    ApiError::Forbidden("Organization storage quota exceeded".into())
+}
+
+// Espacio libre de la organización como límite de subida (None = personal o sin cuota)
+pub(crate) async fn org_quota_limit(
+    state: &AppState,
+    org_id: Option<&str>,
+) -> Result<Option<UploadLimit>, ApiError> {
+    let Some(org_id) = org_id else {
+        return Ok(None);
+    };
+    let org = orgs::find_org(state, org_id).await?;
+    let Some(quota) = org.quota_bytes else {
+        return Ok(None);
+    };
+
+    let used = orgs::org_usage(state, org_id).await?;
+    if used >= quota {
+        return Err(quota_exceeded());
+    }
+
+    Ok(Some(UploadLimit {
+        max_bytes: quota - used,
+        exceeded: quota_exceeded,
+    }))
+}
+
+// Avisa a los administradores de la organización cuando una subida cruza el umbral de cuota
+pub(crate) async fn warn_if_near_quota(state: &AppState, org_id: Option<&str>, added: i64) {
+    let Some(org_id) = org_id else {
+        return;
+    };
+    let Ok(org) = orgs::find_org(state, org_id).await else {
+        return;
+    };
+    let Some(quota) = org.quota_bytes.filter(|q| *q > 0) else {
+        return;
+    };
+    let Ok(used) = orgs::org_usage(state, org_id).await else {
+        return;
+    };
+
+    let threshold = quota / 100 * QUOTA_WARNING_PERCENT;
+    if used < threshold || used - added >= threshold {
+        return;
+    }
+
+    let managers: Vec<String> = org
+        .members
+        .iter()
+        .filter(|m| m.role.can_manage())
+        .map(|m| m.user_id.clone())
+        .collect();
+    notify::send(
+        state,
+        managers,
+        Notice::new(
+            NotificationKind::QuotaWarning,
+            format!("\"{}\" está por llenar su espacio", org.name),
+            format!(
+                "La organización \"{}\" usa {} de {} bytes ({}%).",
+                org.name,
+                used,
+                quota,
+                used * 100 / quota
+            ),
+        )
+        .link(format!("/api/orgs/{}", org_id))
+        .data(doc! { "org_id": org_id, "used": used, "quota": quota }),
+    )
+    .await;
+}
+
+// Nombre (ya saneado) y tipo MIME declarados en la parte multipart
+pub(crate) fn field_meta(field: &Field) -> (String, String) {
+    // ✅ En tu versión: content_disposition() regresa referencia, no Option
+    let cd = field.content_disposition();
+
+    let filename = cd
+        .get_filename()
+        .map(sanitize)
+        .unwrap_or_else(|| "file.bin".to_string());
+
+    // ✅ En tu versión: content_type() es Option<&Mime>
+    let mime = field
+        .content_type()
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+
+    (filename, mime)
+}
+
+// Tope de bytes para una subida y el error que se devuelve al rebasarlo
+pub(crate) struct UploadLimit {
+    pub max_bytes: i64,
+    pub exceeded: fn() -> ApiError,
+}
+
+// Dónde queda el archivo subido
+pub(crate) struct UploadTarget {
+    pub owner_id: String,
+    pub org_id: Option<String>,
+    pub folder_id: Option<String>,
+    pub strip_metadata: Option<bool>, // None = preferencia del dueño
+}
+
+// Escribe la parte a disco en streaming; si se rebasa el límite se borra lo escrito
+pub(crate) async fn write_field(
+    field: &mut Field,
+    stored_name: &str,
+    limit: Option<&UploadLimit>,
+) -> Result<i64, ApiError> {
+    let mut f = storage::create(stored_name)?;
+    let mut size: i64 = 0;
+
+    while let Some(chunk) = field.next().await {
+        let data = chunk.map_err(|_| ApiError::Internal)?;
+        size += data.len() as i64;
+        if let Some(limit) = limit {
+            if size > limit.max_bytes {
+                drop(f);
+                storage::remove(stored_name);
+                return Err((limit.exceeded)());
+            }
+        }
+        f.write_all(&data).map_err(|_| ApiError::Internal)?;
+    }
+
+    Ok(size)
+}
+
+// Guarda la parte y registra su FileDoc
+pub(crate) async fn save_upload(
+    state: &AppState,
+    mut field: Field,
+    target: UploadTarget,
+    limit: Option<UploadLimit>,
+) -> Result<FileDoc, ApiError> {
+    let (filename, mime) = field_meta(&field);
+    let stored_name = format!("{}_{}", ObjectId::new().to_hex(), filename);
+    let size = write_field(&mut field, &stored_name, limit.as_ref()).await?;
+
+    // Si no se pudo limpiar una foto que debía limpiarse, no se guarda
+    let (image_meta, size) = match image_meta::on_upload(
+        state,
+        &stored_name,
+        &mime,
+        &filename,
+        &target.owner_id,
+        target.strip_metadata,
+    )
+    .await
+    {
+        Ok(Some((meta, size))) => (Some(meta), size),
+        Ok(None) => (None, size),
+        Err(e) => {
+            storage::remove(&stored_name);
+            return Err(e);
+        }
+    };
+
+    let now = Utc::now();
+    let doc = FileDoc {
+        id: ObjectId::new(),
+        owner_id: target.owner_id,
+        org_id: target.org_id,
+        folder_id: target.folder_id,
+        original_name: filename,
+        stored_name,
+        mime,
+        size,
+        visibility: "private".to_string(),
+        download_count: 0,
+        last_downloaded_at: None,
+        image_meta,
+        created_at: now,
+        updated_at: now,
+    };
+
+    files_collection(state)
+        .insert_one(&doc, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert file error: {:?}", e);
+            ApiError::Internal
+        })?;
+    search_index::enqueue(state, std::slice::from_ref(&doc)).await;
+
+    Ok(doc)
+}
+
+#[post("/upload")]
+pub async fn upload_file(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    cfg: web::Data<AppConfig>,
+    query: web::Query<UploadQuery>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    // En una carpeta el archivo hereda su espacio; si no, va al espacio activo
+    let folder_id = match query.folder_id.as_deref() {
+        Some(raw) => Some(find_accessible_folder(&state, &user, raw).await?.0),
+        None => None,
+    };
+    let org_id = match &folder_id {
+        Some(folder) => folder.org_id.clone(),
+        None => orgs::active_org(&state, &user)
+            .await?
+            .map(|(org, _)| org.id.to_hex()),
+    };
+    let limit = org_quota_limit(&state, org_id.as_deref()).await?;
+
+    let mut saved: Option<FileDoc> = None;
+
+    if let Some(item) = payload.next().await {
+        let field = item.map_err(|_| ApiError::BadRequest("Invalid multipart".into()))?;
+
+        let target = UploadTarget {
             owner_id: user.user_id.clone(),
-            original_name: filename,
-            stored_name,
-            mime,
-            size,
-            visibility: "private".to_string(),
-            created_at: now,
-            updated_at: now,
+            org_id,
+            folder_id: folder_id.map(|f| f.id.to_hex()),
+            strip_metadata: query.strip_metadata,
         };
 
-        let col = files_collection(&state);
-        col.insert_one(&doc, None)
-            .await
-            .map_err(|e| {
-                eprintln!("Mongo insert file error: {:?}", e);
-                ApiError::Internal
-            })?;
+        // ZIP/TAR que se descomprime en una carpeta nueva en vez de guardarse tal cual
+        if query.extract == Some(true) {
+            return archive::extract_upload(&state, &cfg, &req, &user, field, target, limit).await;
+        }
 
-        saved = Some(doc);
-        break; // solo 1 archivo por request
+        // solo 1 archivo por request
+        saved = Some(save_upload(&state, field, target, limit).await?);
     }
 
     let Some(saved) = saved else {
         return Err(ApiError::BadRequest("No file uploaded".into()));
     };
 
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::FileUpload)
+            .by(&user)
+            .target("file", saved.id.to_hex())
+            .details(
+                doc! { "name": &saved.original_name, "size": saved.size, "org_id": &saved.org_id },
+            ),
+    )
+    .await;
+    let audience = file_audience(&state, &saved).await;
+    webhooks::dispatch(
+        &state,
+        WebhookEvent::FileUploaded,
+        &audience,
+        file_payload(&saved),
+    )
+    .await;
+    state
+        .events
+        .publish(audience, LiveEventKind::FileUploaded, file_payload(&saved));
+    warn_if_near_quota(&state, saved.org_id.as_deref(), saved.size).await;
+
     Ok(HttpResponse::Created().json(FileOut::from(saved)))
 }
 
@@ -101,11 +454,26 @@ pub async fn upload_file(
 pub async fn list_files(
     user: AuthUser,
     state: web::Data<AppState>,
+    query: web::Query<FolderQuery>,
 ) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    // Sin folder_id se listan todos los archivos del espacio activo
+    let filter = match query.folder_id.as_deref() {
+        Some(raw) => {
+            let (folder, _) = find_accessible_folder(&state, &user, raw).await?;
+            doc! { "folder_id": folder.id.to_hex() }
+        }
+        None => match orgs::active_org(&state, &user).await? {
+            Some((org, _)) => doc! { "org_id": org.id.to_hex() },
+            None => doc! { "owner_id": &user.user_id, "org_id": null },
+        },
+    };
+
     let col = files_collection(&state);
 
     let mut cursor = col
-        .find(doc! { "owner_id": &user.user_id }, None)
+        .find(filter, None)
         .await
         .map_err(|_| ApiError::Internal)?;
 
@@ -120,60 +488,213 @@ pub async fn list_files(
 
 #[get("/{id}/download")]
 pub async fn download_file(
+    req: HttpRequest,
     user: AuthUser,
     state: web::Data<AppState>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, ApiError> {
-    let id = ObjectId::parse_str(path.into_inner())
-        .map_err(|_| ApiError::BadRequest("Invalid file id".into()))?;
+    user.require_scope(ApiScope::FilesRead)?;
 
-    let col = files_collection(&state);
-    let file = col
-        .find_one(doc! { "_id": id, "owner_id": &user.user_id }, None)
+    let id = parse_file_id(path.into_inner())?;
+    let (file, _) = find_accessible(&state, &user, id).await?;
+
+    record_download(&state, &req, &file, Some(&user), "app").await;
+    attachment(file)
+}
+
+// Descarga sin sesión de un archivo marcado como público
+#[get("/{id}/public")]
+pub async fn download_public(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = parse_file_id(path.into_inner())?;
+
+    let file = files_collection(&state)
+        .find_one(doc! { "_id": id, "visibility": "public" }, None)
         .await
         .map_err(|_| ApiError::Internal)?
         .ok_or_else(|| ApiError::NotFound("File not found".into()))?;
 
-    let filepath = format!("{}/{}", UPLOAD_DIR, file.stored_name);
-    let bytes =
-        fs::read(&filepath).map_err(|_| ApiError::NotFound("File missing on disk".into()))?;
+    record_download(&state, &req, &file, None, "public").await;
+    attachment(file)
+}
 
-    Ok(HttpResponse::Ok()
-        .insert_header(("Content-Type", file.mime))
-        .insert_header((
-            "Content-Disposition",
-            format!("attachment; filename=\"{}\"", file.original_name),
-        ))
-        .body(bytes))
+// Contador + evento de acceso; un fallo aquí no impide la descarga
+pub(crate) async fn record_download(
+    state: &AppState,
+    req: &HttpRequest,
+    file: &FileDoc,
+    user: Option<&AuthUser>,
+    via: &str,
+) {
+    record_downloads(state, req, std::slice::from_ref(file), user, via).await;
+}
+
+// Varias descargas de una vez (p. ej. un ZIP): un solo update y un solo insert de auditoría
+pub(crate) async fn record_downloads(
+    state: &AppState,
+    req: &HttpRequest,
+    files: &[FileDoc],
+    user: Option<&AuthUser>,
+    via: &str,
+) {
+    if files.is_empty() {
+        return;
+    }
+
+    let ids: Vec<ObjectId> = files.iter().map(|f| f.id).collect();
+    if let Err(e) = files_collection(state)
+        .update_many(
+            doc! { "_id": { "$in": ids } },
+            doc! {
+                "$inc": { "download_count": 1 },
+                "$set": { "last_downloaded_at": bson_time(Utc::now()) },
+            },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_many error (record_downloads): {:?}", e);
+    }
+
+    let entries = files
+        .iter()
+        .map(|file| {
+            let entry = AuditEntry::new(AuditAction::FileDownload)
+                .target("file", file.id.to_hex())
+                .details(doc! { "via": via });
+            match user {
+                Some(user) => entry.by(user),
+                None => entry,
+            }
+        })
+        .collect();
+    audit::record_many(state, req, entries).await;
+}
+
+#[patch("/{id}/name")]
+pub async fn rename_file(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<RenameFileDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let id = parse_file_id(path.into_inner())?;
+    let mut dto = body.into_inner();
+    dto.name = sanitize(dto.name.trim());
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let (file, can_modify) = find_accessible(&state, &user, id).await?;
+    if !can_modify {
+        return Err(ApiError::Forbidden(
+            "Only the uploader or an organization admin can change this file".into(),
+        ));
+    }
+
+    // Solo cambia el nombre visible; el archivo en disco conserva su stored_name
+    files_collection(&state)
+        .update_one(
+            doc! { "_id": id },
+            doc! { "$set": { "original_name": &dto.name, "updated_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    search_index::rename(&state, id, &dto.name).await;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::FileRename)
+            .by(&user)
+            .target("file", file.id.to_hex())
+            .details(doc! { "from": &file.original_name, "to": &dto.name }),
+    )
+    .await;
+
+    let (file, _) = find_accessible(&state, &user, id).await?;
+    Ok(HttpResponse::Ok().json(FileOut::from(file)))
 }
 
 #[patch("/{id}/visibility")]
 pub async fn update_visibility(
+    req: HttpRequest,
     user: AuthUser,
     state: web::Data<AppState>,
     path: web::Path<String>,
     body: web::Json<UpdateVisibilityDto>,
 ) -> Result<HttpResponse, ApiError> {
-    let id = ObjectId::parse_str(path.into_inner())
-        .map_err(|_| ApiError::BadRequest("Invalid file id".into()))?;
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let id = parse_file_id(path.into_inner())?;
 
     let visibility = body.visibility.trim().to_lowercase();
     if visibility != "public" && visibility != "private" {
-        return Err(ApiError::BadRequest("visibility must be public|private".into()));
+        return Err(ApiError::BadRequest(
+            "visibility must be public|private".into(),
+        ));
+    }
+
+    let (file, can_modify) = find_accessible(&state, &user, id).await?;
+    if !can_modify {
+        return Err(ApiError::Forbidden(
+            "Only the uploader or an organization admin can change this file".into(),
+        ));
+    }
+
+    let mut set = doc! { "visibility": &visibility, "updated_at": bson_time(Utc::now()) };
+    // Si no se pudieron quitar los metadatos, la foto no se publica
+    if visibility == "public" && file.visibility != "public" {
+        if let Some((meta, size)) = image_meta::on_share(&state, &file).await? {
+            set.insert(
+                "image_meta",
+                bson::to_bson(&meta).map_err(|_| ApiError::Internal)?,
+            );
+            set.insert("size", size);
+        }
     }
 
     let col = files_collection(&state);
-    let res = col
-        .update_one(
-            doc! { "_id": id, "owner_id": &user.user_id },
-            doc! { "$set": { "visibility": &visibility, "updated_at": Utc::now() } },
-            None,
-        )
+    col.update_one(doc! { "_id": id }, doc! { "$set": set }, None)
         .await
         .map_err(|_| ApiError::Internal)?;
 
-    if res.matched_count == 0 {
-        return Err(ApiError::NotFound("File not found".into()));
+    if file.visibility != visibility {
+        let action = if visibility == "public" {
+            AuditAction::FileShare
+        } else {
+            AuditAction::FileUnshare
+        };
+        audit::record(
+            &state,
+            &req,
+            AuditEntry::new(action)
+                .by(&user)
+                .target("file", file.id.to_hex())
+                .details(doc! { "from": &file.visibility, "to": &visibility }),
+        )
+        .await;
+        let audience = file_audience(&state, &file).await;
+        if visibility == "public" {
+            webhooks::dispatch(
+                &state,
+                WebhookEvent::FileShared,
+                &audience,
+                file_payload(&file),
+            )
+            .await;
+        }
+        state.events.publish(
+            audience,
+            LiveEventKind::FileVisibilityChanged,
+            serde_json::json!({ "id": file.id.to_hex(), "visibility": &visibility }),
+        );
     }
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -184,26 +705,205 @@ pub async fn update_visibility(
 
 #[delete("/{id}")]
 pub async fn delete_file(
+    req: HttpRequest,
     user: AuthUser,
     state: web::Data<AppState>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, ApiError> {
-    let id = ObjectId::parse_str(path.into_inner())
-        .map_err(|_| ApiError::BadRequest("Invalid file id".into()))?;
+    user.require_scope(ApiScope::FilesWrite)?;
 
-    let col = files_collection(&state);
-    let file = col
-        .find_one(doc! { "_id": id, "owner_id": &user.user_id }, None)
-        .await
-        .map_err(|_| ApiError::Internal)?
-        .ok_or_else(|| ApiError::NotFound("File not found".into()))?;
+    let id = parse_file_id(path.into_inner())?;
 
-    let filepath = format!("{}/{}", UPLOAD_DIR, file.stored_name);
-    let _ = fs::remove_file(&filepath);
+    let (file, can_modify) = find_accessible(&state, &user, id).await?;
+    if !can_modify {
+        return Err(ApiError::Forbidden(
+            "Only the uploader or an organization admin can delete this file".into(),
+        ));
+    }
+
+    storage::remove(&file.stored_name);
 
-    col.delete_one(doc! { "_id": id, "owner_id": &user.user_id }, None)
+    files_collection(&state)
+        .delete_one(doc! { "_id": id }, None)
         .await
         .map_err(|_| ApiError::Internal)?;
+    comments::remove_for_files(&state, vec![file.id.to_hex()]).await?;
+    search_index::remove_for_files(&state, vec![file.id]).await;
+    office::remove_for_files(&state, vec![file.id]).await;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::FileDelete)
+            .by(&user)
+            .target("file", file.id.to_hex())
+            .details(doc! { "name": &file.original_name, "size": file.size }),
+    )
+    .await;
+    webhooks::dispatch(
+        &state,
+        WebhookEvent::FileDeleted,
+        &file_audience(&state, &file).await,
+        file_payload(&file),
+    )
+    .await;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
 }
+
+// Eventos de auditoría del archivo, solo para quien puede modificarlo
+async fn file_events(
+    state: &AppState,
+    user: &AuthUser,
+    raw_id: String,
+    what: &str,
+) -> Result<Vec<AuditEventDoc>, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let id = parse_file_id(raw_id)?;
+    let (file, can_modify) = find_accessible(state, user, id).await?;
+    if !can_modify {
+        return Err(ApiError::Forbidden(format!(
+            "Only the uploader or an organization admin can see this {}",
+            what
+        )));
+    }
+
+    let options = FindOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .limit(HISTORY_LIMIT)
+        .build();
+
+    let mut cursor = audit_collection(state)
+        .find(
+            doc! { "target_type": "file", "target_id": file.id.to_hex() },
+            options,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut events = Vec::new();
+    while let Some(item) = cursor.next().await {
+        events.push(item.map_err(|_| ApiError::Internal)?);
+    }
+    Ok(events)
+}
+
+// Historial de auditoría del archivo
+#[get("/{id}/history")]
+pub async fn file_history(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let events = file_events(&state, &user, path.into_inner(), "history").await?;
+    let out: Vec<AuditEventOut> = events.into_iter().map(AuditEventOut::from).collect();
+    Ok(HttpResponse::Ok().json(out))
+}
+
+// Timeline legible del archivo: subida, renombres, visibilidad y descargas con quién las hizo
+#[get("/{id}/activity")]
+pub async fn file_activity(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let events = file_events(&state, &user, path.into_inner(), "activity").await?;
+
+    // Nombres actuales de quienes aparecen en el timeline
+    let actor_ids: Vec<ObjectId> = events
+        .iter()
+        .filter_map(|e| e.actor_id.as_deref())
+        .filter_map(|id| ObjectId::parse_str(id).ok())
+        .collect();
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut users = users_collection(&state)
+        .find(doc! { "_id": { "$in": actor_ids } }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    while let Some(item) = users.next().await {
+        let u = item.map_err(|_| ApiError::Internal)?;
+        names.insert(u.id.to_hex(), u.name);
+    }
+
+    let out: Vec<FileActivityOut> = events
+        .into_iter()
+        .map(|e| FileActivityOut {
+            action: e.action,
+            actor: e.actor_id.map(|id| ActivityActor {
+                name: names.get(&id).cloned(),
+                email: e.actor_email,
+                id,
+            }),
+            details: e.details,
+            at: e.created_at,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+// Texto extraído (o reconocido por OCR) que alimenta la búsqueda
+#[get("/{id}/text")]
+pub async fn file_text(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let id = parse_file_id(path.into_inner())?;
+    let (file, _) = find_accessible(&state, &user, id).await?;
+
+    let text = search_index::texts_collection(&state)
+        .find_one(doc! { "_id": file.id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("Text not available for this file".into()))?;
+
+    Ok(HttpResponse::Ok().json(FileTextOut::from(text)))
+}
+
+// Metadatos de una foto (cámara, dimensiones, fecha de captura); solo para quien la administra
+#[get("/{id}/metadata")]
+pub async fn file_metadata(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let id = parse_file_id(path.into_inner())?;
+    let (file, can_modify) = find_accessible(&state, &user, id).await?;
+    if !can_modify {
+        return Err(ApiError::Forbidden(
+            "Only the uploader or an organization admin can see this metadata".into(),
+        ));
+    }
+
+    // Fotos subidas antes de guardar metadatos: se leen ahora y se guardan
+    let meta = match file.image_meta {
+        Some(meta) => meta,
+        None => {
+            let (meta, _) =
+                image_meta::inspect(&file.stored_name, &file.mime, &file.original_name, false)
+                    .await?
+                    .ok_or_else(|| ApiError::NotFound("No image metadata for this file".into()))?;
+            let bson_meta = bson::to_bson(&meta).map_err(|_| ApiError::Internal)?;
+            files_collection(&state)
+                .update_one(
+                    doc! { "_id": file.id },
+                    doc! { "$set": { "image_meta": bson_meta } },
+                    None,
+                )
+                .await
+                .map_err(|_| ApiError::Internal)?;
+            meta
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(ImageMetadataOut {
+        file_id: file.id.to_hex(),
+        meta,
+    }))
+}
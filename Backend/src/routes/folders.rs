@@ -0,0 +1,177 @@
+use actix_web::{delete, get, post, web, HttpResponse};
+use bson::{doc, oid::ObjectId};
+use chrono::Utc;
+use futures::StreamExt;
+use validator::Validate;
+
+use crate::{
+    db::AppState,
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        api_key::ApiScope,
+        folder::{CreateFolderDto, FolderDoc, FolderOut, FolderQuery},
+    },
+    routes::{files::files_collection, orgs},
+};
+
+pub(crate) fn folders_collection(state: &AppState) -> mongodb::Collection<FolderDoc> {
+    state.db.collection::<FolderDoc>("folders")
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_folder)
+        .service(list_folders)
+        .service(delete_folder);
+}
+
+// Mismas reglas que los archivos: carpeta personal propia o de una organización donde es miembro.
+// El bool indica si puede modificarla (creador, o admin de la organización).
+pub(crate) async fn find_accessible_folder(
+    state: &AppState,
+    user: &AuthUser,
+    raw_id: &str,
+) -> Result<(FolderDoc, bool), ApiError> {
+    let not_found = || ApiError::NotFound("Folder not found".into());
+    let id = ObjectId::parse_str(raw_id)
+        .map_err(|_| ApiError::BadRequest("Invalid folder id".into()))?;
+
+    let folder = folders_collection(state)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(not_found)?;
+
+    match folder.org_id.as_deref() {
+        None if folder.owner_id == user.user_id => Ok((folder, true)),
+        None => Err(not_found()),
+        Some(org_id) => {
+            let (_, role) = orgs::membership(state, &user.user_id, org_id)
+                .await
+                .map_err(|e| match e {
+                    ApiError::Internal => ApiError::Internal,
+                    _ => not_found(),
+                })?;
+            let can_modify = folder.owner_id == user.user_id || role.can_manage();
+            Ok((folder, can_modify))
+        }
+    }
+}
+
+#[post("")]
+async fn create_folder(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<CreateFolderDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let mut dto = body.into_inner();
+    dto.name = sanitize_filename::sanitize(dto.name.trim());
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    // Una subcarpeta vive en el mismo espacio que su padre
+    let org_id = match dto.parent_id.as_deref() {
+        Some(parent) => {
+            find_accessible_folder(&state, &user, parent)
+                .await?
+                .0
+                .org_id
+        }
+        None => orgs::active_org(&state, &user)
+            .await?
+            .map(|(o, _)| o.id.to_hex()),
+    };
+
+    let now = Utc::now();
+    let folder = FolderDoc {
+        id: ObjectId::new(),
+        owner_id: user.user_id.clone(),
+        org_id,
+        parent_id: dto.parent_id,
+        name: dto.name,
+        created_at: now,
+        updated_at: now,
+    };
+
+    folders_collection(&state)
+        .insert_one(&folder, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (create_folder): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    Ok(HttpResponse::Created().json(FolderOut::from(folder)))
+}
+
+// Subcarpetas de `folder_id` (o de la raíz del espacio activo)
+#[get("")]
+async fn list_folders(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    query: web::Query<FolderQuery>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let filter = match query.folder_id.as_deref() {
+        Some(parent) => {
+            let (parent, _) = find_accessible_folder(&state, &user, parent).await?;
+            doc! { "parent_id": parent.id.to_hex() }
+        }
+        None => match orgs::active_org(&state, &user).await? {
+            Some((org, _)) => doc! { "org_id": org.id.to_hex(), "parent_id": null },
+            None => doc! { "owner_id": &user.user_id, "org_id": null, "parent_id": null },
+        },
+    };
+
+    let mut cursor = folders_collection(&state)
+        .find(filter, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out: Vec<FolderOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        out.push(item.map_err(|_| ApiError::Internal)?.into());
+    }
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+// Solo se borran carpetas vacías
+#[delete("/{id}")]
+async fn delete_folder(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let (folder, can_modify) = find_accessible_folder(&state, &user, &path.into_inner()).await?;
+    if !can_modify {
+        return Err(ApiError::Forbidden(
+            "Only the creator or an organization admin can delete this folder".into(),
+        ));
+    }
+
+    let folder_id = folder.id.to_hex();
+    let files = files_collection(&state)
+        .count_documents(doc! { "folder_id": &folder_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    let children = folders_collection(&state)
+        .count_documents(doc! { "parent_id": &folder_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    if files > 0 || children > 0 {
+        return Err(ApiError::BadRequest("Folder is not empty".into()));
+    }
+
+    folders_collection(&state)
+        .delete_one(doc! { "_id": folder.id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
@@ -1,16 +1,56 @@
+pub mod admin;
+pub mod api_keys;
+pub mod archive;
 pub mod auth;
+pub mod comments;
+pub mod document_requests;
+pub mod drops;
+pub mod events;
 pub mod files;
+pub mod folders;
+pub mod notifications;
+pub mod oidc;
+pub mod orgs;
+pub mod preview;
+pub mod search;
+pub mod users;
+pub mod webhooks;
+pub mod well_known;
 
 use actix_web::web;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/auth").configure(auth::configure));
+    cfg.service(
+        web::scope("/auth")
+            .configure(auth::configure)
+            .configure(oidc::configure),
+    );
     cfg.service(
         web::scope("/files")
             .service(files::upload_file)
             .service(files::list_files)
             .service(files::download_file)
+            .service(files::download_public)
+            .service(files::rename_file)
             .service(files::update_visibility)
-            .service(files::delete_file),
+            .service(files::delete_file)
+            .service(files::file_history)
+            .service(files::file_activity)
+            .service(files::file_text)
+            .service(files::file_metadata)
+            .configure(archive::configure)
+            .configure(preview::configure)
+            .configure(comments::configure),
     );
+    cfg.service(web::scope("/drops").configure(drops::configure));
+    cfg.service(web::scope("/folders").configure(folders::configure));
+    cfg.service(web::scope("/users").configure(users::configure));
+    cfg.service(web::scope("/api-keys").configure(api_keys::configure));
+    cfg.service(web::scope("/admin").configure(admin::configure));
+    cfg.service(web::scope("/orgs").configure(orgs::configure));
+    cfg.service(web::scope("/events").configure(events::configure));
+    cfg.service(web::scope("/notifications").configure(notifications::configure));
+    cfg.service(web::scope("/webhooks").configure(webhooks::configure));
+    cfg.service(web::scope("/requests").configure(document_requests::configure));
+    cfg.service(web::scope("/search").configure(search::configure));
 }
@@ -0,0 +1,201 @@
+use actix_web::{get, post, put, web, HttpResponse};
+use bson::{doc, oid::ObjectId};
+use chrono::Utc;
+use futures::StreamExt;
+use mongodb::options::FindOptions;
+use validator::Validate;
+
+use crate::{
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        admin::Page,
+        api_key::ApiScope,
+        notification::{NotificationOut, NotificationsQuery, UpdateNotificationPrefsDto},
+        user::User,
+    },
+    routes::auth::users_collection,
+    utils::notify::notifications_collection,
+};
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_notifications)
+        .service(unread_count)
+        .service(mark_all_read)
+        .service(mark_read)
+        .service(get_preferences)
+        .service(update_preferences);
+}
+
+#[get("")]
+async fn list_notifications(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    query: web::Query<NotificationsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let mut filter = doc! { "user_id": &user.user_id };
+    if query.unread == Some(true) {
+        filter.insert("read_at", bson::Bson::Null);
+    }
+
+    let col = notifications_collection(&state);
+    let total = col
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let options = FindOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .skip((page - 1) * per_page)
+        .limit(per_page as i64)
+        .build();
+
+    let mut cursor = col
+        .find(filter, options)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    let mut items: Vec<NotificationOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        items.push(item.map_err(|_| ApiError::Internal)?.into());
+    }
+
+    Ok(HttpResponse::Ok().json(Page {
+        items,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+// Para el contador de la campana sin bajar la lista
+#[get("/unread-count")]
+async fn unread_count(
+    user: AuthUser,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let unread = notifications_collection(&state)
+        .count_documents(doc! { "user_id": &user.user_id, "read_at": null }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "unread": unread })))
+}
+
+#[post("/read-all")]
+async fn mark_all_read(
+    user: AuthUser,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let res = notifications_collection(&state)
+        .update_many(
+            doc! { "user_id": &user.user_id, "read_at": null },
+            doc! { "$set": { "read_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true, "marked": res.modified_count })))
+}
+
+#[post("/{id}/read")]
+async fn mark_read(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesWrite)?;
+
+    let id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| ApiError::BadRequest("Invalid notification id".into()))?;
+
+    let col = notifications_collection(&state);
+    let notification = col
+        .find_one(doc! { "_id": id, "user_id": &user.user_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("Notification not found".into()))?;
+
+    // Marcarla de nuevo no cambia la fecha original
+    if notification.read_at.is_none() {
+        col.update_one(
+            doc! { "_id": id, "read_at": null },
+            doc! { "$set": { "read_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+async fn load_account(state: &AppState, user: &AuthUser) -> Result<User, ApiError> {
+    let id = ObjectId::parse_str(&user.user_id)
+        .map_err(|_| ApiError::Unauthorized("Invalid token".into()))?;
+    users_collection(state)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::Unauthorized("User not found".into()))
+}
+
+#[get("/preferences")]
+async fn get_preferences(
+    user: AuthUser,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let account = load_account(&state, &user).await?;
+    Ok(HttpResponse::Ok().json(account.notification_prefs))
+}
+
+#[put("/preferences")]
+async fn update_preferences(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<UpdateNotificationPrefsDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+    body.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let account = load_account(&state, &user).await?;
+    let mut prefs = account.notification_prefs;
+    let dto = body.into_inner();
+    if let Some(enabled) = dto.email_enabled {
+        prefs.email_enabled = enabled;
+    }
+    if let Some(mut muted) = dto.email_muted {
+        muted.dedup();
+        prefs.email_muted = muted;
+    }
+
+    users_collection(&state)
+        .update_one(
+            doc! { "_id": account.id },
+            doc! { "$set": { "notification_prefs": bson::to_bson(&prefs).map_err(|_| ApiError::Internal)? } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    Ok(HttpResponse::Ok().json(prefs))
+}
@@ -0,0 +1,318 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId};
+use chrono::{Duration, Utc};
+use mongodb::{options::IndexOptions, IndexModel};
+
+use crate::{
+    config::AppConfig,
+    db::AppState,
+    errors::ApiError,
+    models::{
+        audit::AuditAction,
+        oidc::{OidcCallbackQuery, OidcLoginState},
+        role::Role,
+        user::{AuthResponse, ExternalIdentity, PublicUser, User},
+        webhook::WebhookEvent,
+    },
+    routes::auth::users_collection,
+    utils::{
+        audit::{self, AuditEntry},
+        jwt, oidc,
+        random::random_token,
+        webhooks,
+    },
+};
+
+const STATE_TTL_MINUTES: i64 = 10;
+
+fn states_collection(state: &AppState) -> mongodb::Collection<OidcLoginState> {
+    state.db.collection::<OidcLoginState>("oidc_states")
+}
+
+// TTL para que los estados abandonados se borren solos
+async fn ensure_state_ttl_index(state: &AppState) {
+    let options = IndexOptions::builder()
+        .expire_after(Some(std::time::Duration::from_secs(0)))
+        .name(Some("ttl_expires_at".to_string()))
+        .build();
+
+    let model = IndexModel::builder()
+        .keys(doc! { "expires_at": 1 })
+        .options(options)
+        .build();
+
+    if let Err(e) = states_collection(state).create_index(model, None).await {
+        eprintln!("Mongo create_index error (oidc ttl_expires_at): {:?}", e);
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(providers)
+        .service(oidc_login)
+        .service(oidc_callback);
+}
+
+#[get("/oidc/providers")]
+async fn providers(cfg: web::Data<AppConfig>) -> Result<HttpResponse, ApiError> {
+    let names: Vec<&str> = cfg.oidc_providers.iter().map(|p| p.name.as_str()).collect();
+    Ok(HttpResponse::Ok().json(names))
+}
+
+#[get("/oidc/{provider}/login")]
+async fn oidc_login(
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let provider = cfg
+        .oidc_provider(&path.into_inner())
+        .ok_or_else(|| ApiError::NotFound("Unknown OIDC provider".into()))?;
+
+    ensure_state_ttl_index(&state).await;
+
+    let meta = oidc::discover(&state.http, provider).await.map_err(|e| {
+        eprintln!("OIDC discovery error ({}): {}", provider.name, e);
+        ApiError::Internal
+    })?;
+
+    let (code_verifier, code_challenge) = oidc::pkce_pair();
+    let now = Utc::now();
+    let login_state = OidcLoginState {
+        state: random_token(24),
+        provider: provider.name.clone(),
+        code_verifier,
+        nonce: random_token(24),
+        created_at: now,
+        expires_at: now + Duration::minutes(STATE_TTL_MINUTES),
+    };
+
+    let url = oidc::authorization_url(
+        &meta,
+        provider,
+        &login_state.state,
+        &login_state.nonce,
+        &code_challenge,
+    )
+    .map_err(ApiError::BadRequest)?;
+
+    states_collection(&state)
+        .insert_one(&login_state, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (oidc_login): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    Ok(HttpResponse::Found()
+        .insert_header(("Location", url))
+        .finish())
+}
+
+#[get("/oidc/{provider}/callback")]
+async fn oidc_callback(
+    req: HttpRequest,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<OidcCallbackQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let provider = cfg
+        .oidc_provider(&path.into_inner())
+        .ok_or_else(|| ApiError::NotFound("Unknown OIDC provider".into()))?;
+
+    let query = query.into_inner();
+    if let Some(err) = query.error {
+        let desc = query.error_description.unwrap_or_default();
+        return Err(ApiError::Unauthorized(
+            format!("{} {}", err, desc).trim().into(),
+        ));
+    }
+
+    let (Some(code), Some(state_param)) = (query.code, query.state) else {
+        return Err(ApiError::BadRequest("Missing code or state".into()));
+    };
+
+    // Un solo uso: se borra al leerlo
+    let login_state = states_collection(&state)
+        .find_one_and_delete(
+            doc! { "_id": &state_param, "provider": &provider.name },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo find_one_and_delete error (oidc_callback): {:?}", e);
+            ApiError::Internal
+        })?
+        .ok_or_else(|| ApiError::Unauthorized("Invalid or expired state".into()))?;
+
+    if login_state.expires_at < Utc::now() {
+        return Err(ApiError::Unauthorized("Invalid or expired state".into()));
+    }
+
+    let meta = oidc::discover(&state.http, provider).await.map_err(|e| {
+        eprintln!("OIDC discovery error ({}): {}", provider.name, e);
+        ApiError::Internal
+    })?;
+
+    let id_token = oidc::exchange_code(
+        &state.http,
+        &meta,
+        provider,
+        &code,
+        &login_state.code_verifier,
+    )
+    .await
+    .map_err(|e| ApiError::Unauthorized(format!("Token exchange failed: {}", e)))?;
+
+    let claims = oidc::verify_id_token(&state.http, &meta, provider, &id_token, &login_state.nonce)
+        .await
+        .map_err(|e| ApiError::Unauthorized(format!("Invalid id_token: {}", e)))?;
+
+    let user = link_or_create_user(&state, &req, &provider.name, claims).await?;
+    if user.disabled {
+        return Err(ApiError::Forbidden("Account disabled".into()));
+    }
+
+    let token = jwt::sign_jwt(&state.jwt, &user.id.to_hex(), &user.email, user.role)
+        .map_err(ApiError::BadRequest)?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::LoginSuccess)
+            .by_account(Some(user.id.to_hex()), &user.email)
+            .details(doc! { "method": "oidc", "provider": &provider.name }),
+    )
+    .await;
+
+    // Flujo de navegador: regresamos al frontend con el token en el fragmento
+    if let Some(redirect) = cfg.oidc_success_redirect.as_deref() {
+        return Ok(HttpResponse::Found()
+            .insert_header(("Location", format!("{}#token={}", redirect, token)))
+            .finish());
+    }
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        token,
+        user: user.into(),
+    }))
+}
+
+// 1) identidad ya vinculada -> ese usuario
+// 2) email verificado que ya existe -> se vincula la identidad
+// 3) si no, se crea un usuario sin password
+async fn link_or_create_user(
+    state: &AppState,
+    req: &HttpRequest,
+    provider: &str,
+    claims: oidc::IdTokenClaims,
+) -> Result<User, ApiError> {
+    let col = users_collection(state);
+
+    let linked = col
+        .find_one(
+            doc! { "identities": { "$elemMatch": { "provider": provider, "subject": &claims.sub } } },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo find_one error (oidc link): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    if !claims.is_email_verified() {
+        return Err(ApiError::Unauthorized(
+            "Provider did not return a verified email".into(),
+        ));
+    }
+
+    let email = claims
+        .email
+        .as_deref()
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty())
+        .ok_or_else(|| ApiError::Unauthorized("Provider did not return an email".into()))?;
+
+    let identity = ExternalIdentity {
+        provider: provider.to_string(),
+        subject: claims.sub.clone(),
+        linked_at: Utc::now(),
+    };
+
+    let existing = col
+        .find_one(doc! { "email": &email }, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo find_one error (oidc link): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    if let Some(mut user) = existing {
+        let identity_bson = bson::to_bson(&identity).map_err(|_| ApiError::Internal)?;
+        col.update_one(
+            doc! { "_id": user.id },
+            doc! { "$push": { "identities": identity_bson } },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo update_one error (oidc link): {:?}", e);
+            ApiError::Internal
+        })?;
+
+        user.identities.push(identity);
+        return Ok(user);
+    }
+
+    let name = claims
+        .name
+        .map(|n| n.trim().to_string())
+        .filter(|n| n.len() >= 2)
+        .unwrap_or_else(|| email.split('@').next().unwrap_or("user").to_string());
+
+    let user = User {
+        id: ObjectId::new(),
+        name,
+        email,
+        role: Role::Cliente,
+        password_hash: None,
+        identities: vec![identity],
+        disabled: false,
+        password_reset: None,
+        tokens_valid_after: None,
+        pending_email: None,
+        avatar_key: None,
+        active_org_id: None,
+        notification_prefs: Default::default(),
+        image_metadata_policy: Default::default(),
+        created_at: Utc::now(),
+    };
+
+    col.insert_one(&user, None).await.map_err(|e| {
+        eprintln!("Mongo insert_one error (oidc create): {:?}", e);
+        ApiError::Internal
+    })?;
+
+    audit::record(
+        state,
+        req,
+        AuditEntry::new(AuditAction::Register)
+            .by_account(Some(user.id.to_hex()), &user.email)
+            .target("user", user.id.to_hex())
+            .details(doc! { "method": "oidc", "provider": provider }),
+    )
+    .await;
+    webhooks::dispatch(
+        state,
+        WebhookEvent::UserRegistered,
+        &[],
+        serde_json::to_value(PublicUser::from(user.clone())).unwrap_or_default(),
+    )
+    .await;
+
+    Ok(user)
+}
@@ -0,0 +1,752 @@
+use actix_web::{delete, get, patch, post, put, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use validator::Validate;
+
+use crate::{
+    config::AppConfig,
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        api_key::ApiScope,
+        audit::AuditAction,
+        organization::{
+            AcceptInvitationDto, CreateOrgDto, InvitationDoc, InvitationOut, InviteDto, OrgMember,
+            OrgMemberOut, OrgOut, OrgRole, OrganizationDoc, SwitchOrgDto, UpdateMemberDto,
+            UpdateOrgDto,
+        },
+        role::{Permission, Role},
+        user::User,
+    },
+    routes::{auth::users_collection, drops, files::files_collection},
+    utils::{
+        api_key,
+        audit::{self, AuditEntry},
+        random::random_token,
+    },
+};
+
+const INVITATION_TTL_DAYS: i64 = 7;
+
+pub(crate) fn orgs_collection(state: &AppState) -> mongodb::Collection<OrganizationDoc> {
+    state.db.collection::<OrganizationDoc>("organizations")
+}
+
+fn invitations_collection(state: &AppState) -> mongodb::Collection<InvitationDoc> {
+    state.db.collection::<InvitationDoc>("org_invitations")
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    // /active y /invitations/accept antes que /{id}
+    cfg.service(get_active)
+        .service(switch_active)
+        .service(accept_invitation)
+        .service(create_org)
+        .service(list_orgs)
+        .service(get_org)
+        .service(update_org)
+        .service(list_members)
+        .service(update_member)
+        .service(remove_member)
+        .service(invite)
+        .service(list_invitations)
+        .service(revoke_invitation);
+}
+
+fn parse_id(raw: &str, what: &str) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(raw).map_err(|_| ApiError::BadRequest(format!("Invalid {} id", what)))
+}
+
+pub(crate) async fn find_org(state: &AppState, org_id: &str) -> Result<OrganizationDoc, ApiError> {
+    let id = parse_id(org_id, "organization")?;
+    orgs_collection(state)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("Organization not found".into()))
+}
+
+// La organización y el rol del usuario en ella; NotFound si no es miembro
+pub(crate) async fn membership(
+    state: &AppState,
+    user_id: &str,
+    org_id: &str,
+) -> Result<(OrganizationDoc, OrgRole), ApiError> {
+    let org = find_org(state, org_id).await?;
+    let role = org
+        .role_of(user_id)
+        .ok_or_else(|| ApiError::NotFound("Organization not found".into()))?;
+    Ok((org, role))
+}
+
+// Contexto activo; si el usuario ya no es miembro se cae al espacio personal
+pub(crate) async fn active_org(
+    state: &AppState,
+    user: &AuthUser,
+) -> Result<Option<(OrganizationDoc, OrgRole)>, ApiError> {
+    let Some(org_id) = user.active_org_id.as_deref() else {
+        return Ok(None);
+    };
+
+    match membership(state, &user.user_id, org_id).await {
+        Ok(found) => Ok(Some(found)),
+        Err(ApiError::NotFound(_)) | Err(ApiError::BadRequest(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// ¿Los dos usuarios son miembros de alguna organización en común?
+pub(crate) async fn share_an_org(state: &AppState, a: &str, b: &str) -> Result<bool, ApiError> {
+    let count = orgs_collection(state)
+        .count_documents(
+            doc! { "$and": [{ "members.user_id": a }, { "members.user_id": b }] },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    Ok(count > 0)
+}
+
+// Al borrar una cuenta se quita de todas sus organizaciones
+pub(crate) async fn remove_user_from_all(state: &AppState, user_id: &str) -> Result<(), ApiError> {
+    orgs_collection(state)
+        .update_many(
+            doc! { "members.user_id": user_id },
+            doc! {
+                "$pull": { "members": { "user_id": user_id } },
+                "$set": { "updated_at": bson_time(Utc::now()) },
+            },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo update_many error (remove_user_from_all): {:?}", e);
+            ApiError::Internal
+        })?;
+    Ok(())
+}
+
+pub(crate) async fn org_usage(state: &AppState, org_id: &str) -> Result<i64, ApiError> {
+    let pipeline = vec![
+        doc! { "$match": { "org_id": org_id } },
+        doc! { "$group": { "_id": null, "bytes": { "$sum": "$size" } } },
+    ];
+
+    let mut cursor = files_collection(state)
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo aggregate error (org_usage): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    match cursor.next().await {
+        Some(item) => {
+            let d = item.map_err(|_| ApiError::Internal)?;
+            Ok(d.get_i64("bytes")
+                .or_else(|_| d.get_i32("bytes").map(i64::from))
+                .unwrap_or(0))
+        }
+        None => Ok(0),
+    }
+}
+
+async fn require_manager(
+    state: &AppState,
+    user: &AuthUser,
+    org_id: &str,
+) -> Result<OrganizationDoc, ApiError> {
+    // Un admin de la plataforma administra cualquier organización, sea miembro o no
+    if user.role == Role::Admin {
+        return find_org(state, org_id).await;
+    }
+    let (org, role) = membership(state, &user.user_id, org_id).await?;
+    if !role.can_manage() {
+        return Err(ApiError::Forbidden("Organization admins only".into()));
+    }
+    Ok(org)
+}
+
+fn owner_count(org: &OrganizationDoc) -> usize {
+    org.members
+        .iter()
+        .filter(|m| m.role == OrgRole::Owner)
+        .count()
+}
+
+#[get("/active")]
+async fn get_active(user: AuthUser, state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    match active_org(&state, &user).await? {
+        Some((org, _)) => {
+            let used = org_usage(&state, &org.id.to_hex()).await?;
+            Ok(HttpResponse::Ok().json(OrgOut::new(&org, &user.user_id, used)))
+        }
+        None => Ok(HttpResponse::Ok().json(serde_json::Value::Null)),
+    }
+}
+
+#[put("/active")]
+async fn switch_active(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<SwitchOrgDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let uid = parse_id(&user.user_id, "user")?;
+
+    let update = match body.org_id.as_deref() {
+        Some(org_id) => {
+            membership(&state, &user.user_id, org_id).await?;
+            doc! { "$set": { "active_org_id": org_id } }
+        }
+        None => doc! { "$unset": { "active_org_id": "" } },
+    };
+
+    users_collection(&state)
+        .update_one(doc! { "_id": uid }, update, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "ok": true,
+        "active_org_id": body.org_id
+    })))
+}
+
+#[post("")]
+async fn create_org(
+    user: AuthUser,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    body: web::Json<CreateOrgDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_permission(Permission::CreateOrganizations)?;
+    user.require_session()?;
+
+    let mut dto = body.into_inner();
+    dto.name = dto.name.trim().to_string();
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let now = Utc::now();
+    let org = OrganizationDoc {
+        id: ObjectId::new(),
+        name: dto.name,
+        members: vec![OrgMember {
+            user_id: user.user_id.clone(),
+            role: OrgRole::Owner,
+            joined_at: now,
+        }],
+        quota_bytes: cfg.org_default_quota_bytes,
+        created_by: user.user_id.clone(),
+        created_at: now,
+        updated_at: now,
+    };
+
+    orgs_collection(&state)
+        .insert_one(&org, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (create_org): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    Ok(HttpResponse::Created().json(OrgOut::new(&org, &user.user_id, 0)))
+}
+
+#[get("")]
+async fn list_orgs(user: AuthUser, state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let mut cursor = orgs_collection(&state)
+        .find(doc! { "members.user_id": &user.user_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out: Vec<OrgOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        let org = item.map_err(|_| ApiError::Internal)?;
+        let used = org_usage(&state, &org.id.to_hex()).await?;
+        out.push(OrgOut::new(&org, &user.user_id, used));
+    }
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+#[get("/{id}")]
+async fn get_org(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let (org, _) = membership(&state, &user.user_id, &path.into_inner()).await?;
+    let used = org_usage(&state, &org.id.to_hex()).await?;
+    Ok(HttpResponse::Ok().json(OrgOut::new(&org, &user.user_id, used)))
+}
+
+#[patch("/{id}")]
+async fn update_org(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<UpdateOrgDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let mut dto = body.into_inner();
+    dto.name = dto.name.map(|n| n.trim().to_string());
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let org = require_manager(&state, &user, &path.into_inner()).await?;
+
+    let mut set = doc! { "updated_at": bson_time(Utc::now()) };
+    if let Some(name) = dto.name {
+        set.insert("name", name);
+    }
+    if let Some(quota) = dto.quota_bytes {
+        if user.role != Role::Admin {
+            return Err(ApiError::Forbidden(
+                "Only platform admins can change quotas".into(),
+            ));
+        }
+        // 0 = sin límite
+        set.insert(
+            "quota_bytes",
+            if quota == 0 {
+                bson::Bson::Null
+            } else {
+                quota.into()
+            },
+        );
+    }
+
+    let mut changes = set.clone();
+    changes.remove("updated_at");
+    orgs_collection(&state)
+        .update_one(doc! { "_id": org.id }, doc! { "$set": set }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::OrgUpdate)
+            .by(&user)
+            .target("org", org.id.to_hex())
+            .details(changes),
+    )
+    .await;
+
+    let org = find_org(&state, &org.id.to_hex()).await?;
+    let used = org_usage(&state, &org.id.to_hex()).await?;
+    Ok(HttpResponse::Ok().json(OrgOut::new(&org, &user.user_id, used)))
+}
+
+#[get("/{id}/members")]
+async fn list_members(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let (org, _) = membership(&state, &user.user_id, &path.into_inner()).await?;
+
+    let ids: Vec<ObjectId> = org
+        .members
+        .iter()
+        .filter_map(|m| ObjectId::parse_str(&m.user_id).ok())
+        .collect();
+
+    let mut cursor = users_collection(&state)
+        .find(doc! { "_id": { "$in": ids } }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut users: Vec<User> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        users.push(item.map_err(|_| ApiError::Internal)?);
+    }
+
+    let out: Vec<OrgMemberOut> = org
+        .members
+        .into_iter()
+        .map(|m| {
+            let u = users.iter().find(|u| u.id.to_hex() == m.user_id);
+            OrgMemberOut {
+                name: u.map(|u| u.name.clone()),
+                email: u.map(|u| u.email.clone()),
+                user_id: m.user_id,
+                role: m.role,
+                joined_at: m.joined_at,
+            }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+#[patch("/{id}/members/{user_id}")]
+async fn update_member(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: web::Json<UpdateMemberDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let (org_id, member_id) = path.into_inner();
+    let org = require_manager(&state, &user, &org_id).await?;
+
+    let current = org
+        .role_of(&member_id)
+        .ok_or_else(|| ApiError::NotFound("Member not found".into()))?;
+
+    // Solo un owner nombra o degrada owners
+    let acting_is_owner =
+        org.role_of(&user.user_id) == Some(OrgRole::Owner) || user.role == Role::Admin;
+    if (current == OrgRole::Owner || body.role == OrgRole::Owner) && !acting_is_owner {
+        return Err(ApiError::Forbidden(
+            "Only owners can change ownership".into(),
+        ));
+    }
+    if current == OrgRole::Owner && body.role != OrgRole::Owner && owner_count(&org) == 1 {
+        return Err(ApiError::BadRequest(
+            "Organization needs at least one owner".into(),
+        ));
+    }
+
+    let role = bson::to_bson(&body.role).map_err(|_| ApiError::Internal)?;
+    orgs_collection(&state)
+        .update_one(
+            doc! { "_id": org.id, "members.user_id": &member_id },
+            doc! { "$set": { "members.$.role": role.clone(), "updated_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let previous = bson::to_bson(&current).map_err(|_| ApiError::Internal)?;
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::OrgUpdateMember)
+            .by(&user)
+            .target("org", org.id.to_hex())
+            .details(doc! { "user_id": &member_id, "from": previous, "to": role }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true, "role": body.role })))
+}
+
+#[delete("/{id}/members/{user_id}")]
+async fn remove_member(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let (org_id, member_id) = path.into_inner();
+
+    // Cualquiera puede salirse; sacar a otros requiere ser admin de la organización
+    let org = if member_id == user.user_id {
+        membership(&state, &user.user_id, &org_id).await?.0
+    } else {
+        require_manager(&state, &user, &org_id).await?
+    };
+
+    let role = org
+        .role_of(&member_id)
+        .ok_or_else(|| ApiError::NotFound("Member not found".into()))?;
+
+    if role == OrgRole::Owner && owner_count(&org) == 1 {
+        return Err(ApiError::BadRequest(
+            "Organization needs at least one owner".into(),
+        ));
+    }
+
+    orgs_collection(&state)
+        .update_one(
+            doc! { "_id": org.id },
+            doc! {
+                "$pull": { "members": { "user_id": &member_id } },
+                "$set": { "updated_at": bson_time(Utc::now()) },
+            },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    drops::revoke_all_of(&state, &member_id, Some(&org.id.to_hex())).await?;
+
+    // Si estaba trabajando en esta organización vuelve a su espacio personal
+    if let Ok(uid) = ObjectId::parse_str(&member_id) {
+        users_collection(&state)
+            .update_one(
+                doc! { "_id": uid, "active_org_id": org.id.to_hex() },
+                doc! { "$unset": { "active_org_id": "" } },
+                None,
+            )
+            .await
+            .map_err(|_| ApiError::Internal)?;
+    }
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::OrgRemoveMember)
+            .by(&user)
+            .target("org", org.id.to_hex())
+            .details(doc! { "user_id": &member_id }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+#[post("/{id}/invitations")]
+async fn invite(
+    req: HttpRequest,
+    user: AuthUser,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<InviteDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let mut dto = body.into_inner();
+    dto.email = dto.email.trim().to_lowercase();
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let org = require_manager(&state, &user, &path.into_inner()).await?;
+
+    if dto.role == OrgRole::Owner
+        && org.role_of(&user.user_id) != Some(OrgRole::Owner)
+        && user.role != Role::Admin
+    {
+        return Err(ApiError::Forbidden("Only owners can invite owners".into()));
+    }
+
+    let existing = users_collection(&state)
+        .find_one(doc! { "email": &dto.email }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    if existing.is_some_and(|u| org.role_of(&u.id.to_hex()).is_some()) {
+        return Err(ApiError::BadRequest("User is already a member".into()));
+    }
+
+    let token = random_token(32);
+    let now = Utc::now();
+    let invitation = InvitationDoc {
+        id: ObjectId::new(),
+        org_id: org.id.to_hex(),
+        email: dto.email,
+        role: dto.role,
+        token_hash: api_key::hash_key(&token),
+        invited_by: user.user_id.clone(),
+        expires_at: now + Duration::days(INVITATION_TTL_DAYS),
+        accepted_at: None,
+        created_at: now,
+    };
+
+    invitations_collection(&state)
+        .insert_one(&invitation, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (invite): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    let role = bson::to_bson(&invitation.role).map_err(|_| ApiError::Internal)?;
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::OrgInvite)
+            .by(&user)
+            .target("org", org.id.to_hex())
+            .details(doc! {
+                "invitation_id": invitation.id.to_hex(),
+                "email": &invitation.email,
+                "role": role,
+            }),
+    )
+    .await;
+
+    let body = format!(
+        "Hola,\n\nTe invitaron a unirte a \"{}\" en PCOSEW.\n\
+         Inicia sesión (o regístrate) con este correo y acepta la invitación con este código en \
+         POST {}/api/orgs/invitations/accept (válido {} días):\n\n{}\n",
+        org.name, cfg.public_url, INVITATION_TTL_DAYS, token
+    );
+    if let Err(e) = state
+        .mailer
+        .send(
+            &invitation.email,
+            &format!("Invitación a {}", org.name),
+            &body,
+        )
+        .await
+    {
+        eprintln!("Mail error (invitation): {}", e);
+    }
+
+    Ok(HttpResponse::Created().json(InvitationOut::from(invitation)))
+}
+
+#[get("/{id}/invitations")]
+async fn list_invitations(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let org = require_manager(&state, &user, &path.into_inner()).await?;
+
+    let mut cursor = invitations_collection(&state)
+        .find(doc! { "org_id": org.id.to_hex() }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out: Vec<InvitationOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        out.push(item.map_err(|_| ApiError::Internal)?.into());
+    }
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+#[delete("/{id}/invitations/{invitation_id}")]
+async fn revoke_invitation(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let (org_id, invitation_id) = path.into_inner();
+    let org = require_manager(&state, &user, &org_id).await?;
+    let id = parse_id(&invitation_id, "invitation")?;
+
+    let res = invitations_collection(&state)
+        .delete_one(
+            doc! { "_id": id, "org_id": org.id.to_hex(), "accepted_at": null },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    if res.deleted_count == 0 {
+        return Err(ApiError::NotFound("Invitation not found".into()));
+    }
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::OrgRevokeInvitation)
+            .by(&user)
+            .target("org", org.id.to_hex())
+            .details(doc! { "invitation_id": id.to_hex() }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+#[post("/invitations/accept")]
+async fn accept_invitation(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<AcceptInvitationDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let invalid = || ApiError::BadRequest("Invalid or expired invitation".into());
+    let invitation = invitations_collection(&state)
+        .find_one(
+            doc! { "token_hash": api_key::hash_key(body.token.trim()), "accepted_at": null },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(invalid)?;
+
+    if invitation.expires_at <= Utc::now() {
+        return Err(invalid());
+    }
+    // La invitación es para un correo concreto
+    if invitation.email != user.email.to_lowercase() {
+        return Err(ApiError::Forbidden(
+            "Invitation was sent to a different email".into(),
+        ));
+    }
+
+    // Se reclama antes de tocar la organización: de dos aceptaciones en paralelo solo una pasa
+    invitations_collection(&state)
+        .find_one_and_update(
+            doc! { "_id": invitation.id, "accepted_at": null },
+            doc! { "$set": { "accepted_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(invalid)?;
+
+    let org = find_org(&state, &invitation.org_id).await?;
+    let now = Utc::now();
+    let member = OrgMember {
+        user_id: user.user_id.clone(),
+        role: invitation.role,
+        joined_at: now,
+    };
+    let member = bson::to_bson(&member).map_err(|_| ApiError::Internal)?;
+
+    let mut update = Document::new();
+    update.insert("$push", doc! { "members": member });
+    update.insert("$set", doc! { "updated_at": bson_time(now) });
+
+    // El filtro evita duplicar al miembro si ya estaba o si entró por otra invitación
+    let joined = orgs_collection(&state)
+        .update_one(
+            doc! { "_id": org.id, "members.user_id": { "$ne": &user.user_id } },
+            update,
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .modified_count
+        > 0;
+
+    if joined {
+        let role = bson::to_bson(&invitation.role).map_err(|_| ApiError::Internal)?;
+        audit::record(
+            &state,
+            &req,
+            AuditEntry::new(AuditAction::OrgJoin)
+                .by(&user)
+                .target("org", org.id.to_hex())
+                .details(doc! { "invitation_id": invitation.id.to_hex(), "role": role }),
+        )
+        .await;
+    }
+
+    let org = find_org(&state, &invitation.org_id).await?;
+    let used = org_usage(&state, &org.id.to_hex()).await?;
+    Ok(HttpResponse::Ok().json(OrgOut::new(&org, &user.user_id, used)))
+}
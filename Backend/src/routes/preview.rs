@@ -0,0 +1,171 @@
+use actix_files::NamedFile;
+use actix_web::{
+    get,
+    http::header::{self, ContentDisposition, HeaderValue},
+    web, HttpRequest, HttpResponse,
+};
+
+use crate::{
+    config::AppConfig,
+    db::AppState,
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        api_key::ApiScope,
+        file::FileDoc,
+        preview::{PreviewDoc, PreviewPendingOut, PreviewStatus},
+    },
+    routes::files::{find_accessible, parse_file_id, record_download},
+    utils::{disposition, office, storage},
+};
+
+// Tipos que el navegador muestra sin ejecutar nada. HTML, SVG, XML y compañía nunca se
+// sirven inline: pueden llevar scripts.
+const INLINE_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+    "image/avif",
+    "application/pdf",
+    "audio/mpeg",
+    "audio/ogg",
+    "audio/wav",
+    "audio/webm",
+    "audio/aac",
+    "audio/flac",
+    "audio/mp4",
+    "video/mp4",
+    "video/webm",
+    "video/ogg",
+];
+
+// Texto que se muestra como texto plano, nunca interpretado
+const PLAIN_TEXT_TYPES: &[&str] = &["text/plain", "text/csv", "text/markdown"];
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(view_file);
+}
+
+// Content-Type con el que se sirve inline, o None si hay que forzar la descarga.
+// El MIME lo declaró el cliente al subir: solo se respeta si está en la lista.
+fn inline_type(mime: &str) -> Option<&'static str> {
+    let base = mime.split(';').next().unwrap_or("").trim().to_lowercase();
+
+    if PLAIN_TEXT_TYPES.contains(&base.as_str()) {
+        return Some("text/plain; charset=utf-8");
+    }
+    INLINE_TYPES.iter().find(|t| **t == base).copied()
+}
+
+// Sirve un archivo de storage con las cabeceras de la vista previa. NamedFile resuelve Range
+// (audio/video), ETag y Last-Modified.
+async fn serve(
+    req: &HttpRequest,
+    stored_name: &str,
+    content_type: &str,
+    content_disposition: ContentDisposition,
+) -> Result<HttpResponse, ApiError> {
+    let named = NamedFile::open_async(storage::path_for(stored_name))
+        .await
+        .map_err(|_| ApiError::NotFound("File missing on disk".into()))?
+        .set_content_type(content_type.parse().map_err(|_| ApiError::Internal)?)
+        .set_content_disposition(content_disposition);
+
+    let mut res = named.into_response(req);
+    let headers = res.headers_mut();
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static("sandbox"),
+    );
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, no-cache"),
+    );
+    Ok(res)
+}
+
+// Ver cuenta como descarga; los rangos siguientes de la misma lectura (PDF, video) no
+async fn record_view(state: &AppState, req: &HttpRequest, file: &FileDoc, user: &AuthUser) {
+    let continuation = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|r| !r.trim().starts_with("bytes=0-"));
+    if !continuation {
+        record_download(state, req, file, Some(user), "view").await;
+    }
+}
+
+// Vista previa en el navegador; lo que no está en la lista blanca se descarga. Los
+// documentos de oficina se muestran como el PDF que genera LibreOffice (202 mientras tanto).
+#[get("/{id}/view")]
+async fn view_file(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    cfg: web::Data<AppConfig>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+
+    let id = parse_file_id(path.into_inner())?;
+    let (file, _) = find_accessible(&state, &user, id).await?;
+
+    if let Some(office_cfg) = cfg.office_preview.as_ref() {
+        if office::is_office(&file.mime, &file.original_name) {
+            match office::request(&state, office_cfg, &file).await? {
+                Some(PreviewDoc {
+                    status: PreviewStatus::Ready,
+                    pdf_stored_name: Some(pdf),
+                    ..
+                }) => {
+                    let stem = file
+                        .original_name
+                        .rsplit_once('.')
+                        .map_or(file.original_name.as_str(), |(s, _)| s);
+                    let name = format!("{}.pdf", stem);
+                    record_view(&state, &req, &file, &user).await;
+                    return serve(&req, &pdf, "application/pdf", disposition::inline(&name)).await;
+                }
+                Some(PreviewDoc {
+                    status: PreviewStatus::Pending,
+                    ..
+                }) => {
+                    return Ok(HttpResponse::Accepted().json(PreviewPendingOut {
+                        status: PreviewStatus::Pending,
+                    }));
+                }
+                // Falló o es demasiado grande: se descarga el original
+                _ => {}
+            }
+        }
+    }
+
+    record_view(&state, &req, &file, &user).await;
+    match inline_type(&file.mime) {
+        Some(ct) => {
+            serve(
+                &req,
+                &file.stored_name,
+                ct,
+                disposition::inline(&file.original_name),
+            )
+            .await
+        }
+        None => {
+            serve(
+                &req,
+                &file.stored_name,
+                "application/octet-stream",
+                disposition::attachment(&file.original_name),
+            )
+            .await
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use actix_web::{get, web, HttpResponse};
+use bson::{doc, Document};
+use futures::StreamExt;
+use validator::Validate;
+
+use crate::{
+    db::AppState,
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        admin::Page,
+        api_key::ApiScope,
+        file::{FileDoc, FileOut},
+        search::{SearchHit, SearchQuery},
+    },
+    routes::orgs::orgs_collection,
+    utils::search_index::texts_collection,
+};
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 50;
+// Caracteres de contexto a cada lado del primer término encontrado
+const SNIPPET_CONTEXT: usize = 80;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(search_files);
+}
+
+// Mismas reglas que find_accessible: personales propios o de organizaciones donde es miembro
+async fn access_filter(state: &AppState, user: &AuthUser) -> Result<Document, ApiError> {
+    let mut cursor = orgs_collection(state)
+        .find(doc! { "members.user_id": &user.user_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut org_ids = Vec::new();
+    while let Some(item) = cursor.next().await {
+        org_ids.push(item.map_err(|_| ApiError::Internal)?.id.to_hex());
+    }
+
+    Ok(doc! { "$or": [
+        { "owner_id": &user.user_id, "org_id": null },
+        { "org_id": { "$in": org_ids } },
+    ] })
+}
+
+// Búsqueda por nombre y contenido, ordenada por relevancia
+#[get("")]
+async fn search_files(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    query: web::Query<SearchQuery>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_scope(ApiScope::FilesRead)?;
+    query
+        .validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err(ApiError::BadRequest("q is required".into()));
+    }
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let mut filter = access_filter(&state, &user).await?;
+    filter.insert("$text", doc! { "$search": q });
+
+    let pipeline = vec![
+        doc! { "$match": filter },
+        doc! { "$addFields": { "score": { "$meta": "textScore" } } },
+        doc! { "$sort": { "score": -1, "_id": 1 } },
+        doc! { "$facet": {
+            "items": [
+                { "$skip": ((page - 1) * per_page) as i64 },
+                { "$limit": per_page as i64 },
+                { "$lookup": { "from": "files", "localField": "_id", "foreignField": "_id", "as": "file" } },
+                { "$unwind": "$file" },
+                { "$project": { "file": 1, "score": 1, "text": 1 } },
+            ],
+            "total": [{ "$count": "n" }],
+        } },
+    ];
+
+    let mut cursor = texts_collection(&state)
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo aggregate error (search): {:?}", e);
+            ApiError::Internal
+        })?;
+    let result = match cursor.next().await {
+        Some(item) => item.map_err(|_| ApiError::Internal)?,
+        None => Document::new(),
+    };
+
+    let total = result
+        .get_array("total")
+        .ok()
+        .and_then(|t| t.first())
+        .and_then(|t| t.as_document())
+        .and_then(|t| t.get_i32("n").ok())
+        .unwrap_or(0) as u64;
+
+    let terms = query_terms(q);
+    let mut items = Vec::new();
+    for hit in result
+        .get_array("items")
+        .map(|a| a.as_slice())
+        .unwrap_or_default()
+    {
+        let Some(hit) = hit.as_document() else {
+            continue;
+        };
+        let Some(file) = hit
+            .get_document("file")
+            .ok()
+            .and_then(|f| bson::from_document::<FileDoc>(f.clone()).ok())
+        else {
+            continue;
+        };
+
+        items.push(SearchHit {
+            file: FileOut::from(file),
+            score: hit.get_f64("score").unwrap_or(0.0),
+            snippet: hit.get_str("text").ok().map(|text| snippet(text, &terms)),
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(Page {
+        items,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+// Términos a resaltar: sin negaciones (-palabra) ni comillas, en minúsculas y sin acentos
+fn query_terms(q: &str) -> Vec<String> {
+    q.split_whitespace()
+        .filter(|w| !w.starts_with('-'))
+        .flat_map(|w| w.split(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .map(fold)
+        .collect()
+}
+
+fn fold(word: &str) -> String {
+    word.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            'ñ' => 'n',
+            'ç' => 'c',
+            c => c,
+        })
+        .collect()
+}
+
+// El índice aplica stemming ("contratos" encuentra "contrato"), así que una palabra se
+// resalta si comparte raíz con algún término
+fn matches(word: &str, terms: &[String]) -> bool {
+    let word = fold(word);
+    terms.iter().any(|t| {
+        let n = t.chars().count();
+        let stem: String = if n >= 6 {
+            t.chars().take(n - 2).collect()
+        } else {
+            t.clone()
+        };
+        word.starts_with(&stem)
+    })
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Palabras del texto como rangos de bytes
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                out.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        out.push((s, text.len()));
+    }
+    out
+}
+
+// Ventana de texto alrededor de la primera coincidencia, escapada y con <mark>
+fn snippet(text: &str, terms: &[String]) -> String {
+    let words = words(text);
+    let first = words.iter().find(|(s, e)| matches(&text[*s..*e], terms));
+
+    let (from, to) = match first {
+        Some((s, e)) => {
+            let from = text[..*s]
+                .char_indices()
+                .rev()
+                .nth(SNIPPET_CONTEXT - 1)
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            let to = text[*e..]
+                .char_indices()
+                .nth(SNIPPET_CONTEXT)
+                .map(|(i, _)| e + i)
+                .unwrap_or(text.len());
+            (from, to)
+        }
+        None => (
+            0,
+            text.char_indices()
+                .nth(SNIPPET_CONTEXT * 2)
+                .map(|(i, _)| i)
+                .unwrap_or(text.len()),
+        ),
+    };
+
+    let mut out = String::new();
+    if from > 0 {
+        out.push('…');
+    }
+    let mut pos = from;
+    for (s, e) in words.iter().filter(|(s, e)| *s >= from && *e <= to) {
+        if !matches(&text[*s..*e], terms) {
+            continue;
+        }
+        out.push_str(&escape_html(&text[pos..*s]));
+        out.push_str("<mark>");
+        out.push_str(&escape_html(&text[*s..*e]));
+        out.push_str("</mark>");
+        pos = *e;
+    }
+    out.push_str(&escape_html(&text[pos..to]));
+    if to < text.len() {
+        out.push('…');
+    }
+
+    out.replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_terms_fold_accents_and_skip_exclusions() {
+        assert_eq!(
+            query_terms("Contrato  ÁRBOL -borrador año-2024"),
+            ["contrato", "arbol", "ano", "2024"]
+        );
+        assert!(query_terms("  -solo ").is_empty());
+    }
+
+    #[test]
+    fn snippet_marks_matches_and_escapes_html() {
+        let terms = query_terms("contratos");
+        assert_eq!(
+            snippet("El <b>contrato</b> firmado", &terms),
+            "El &lt;b&gt;<mark>contrato</mark>&lt;/b&gt; firmado"
+        );
+    }
+
+    #[test]
+    fn snippet_windows_long_text_around_the_first_match() {
+        let text = format!("{} clave {}", "x ".repeat(200), "y ".repeat(200));
+        let out = snippet(&text, &query_terms("clave"));
+        assert!(out.starts_with('…') && out.ends_with('…'));
+        assert!(out.contains("<mark>clave</mark>"));
+        assert!(out.chars().count() < SNIPPET_CONTEXT * 2 + 30);
+    }
+
+    #[test]
+    fn snippet_without_match_is_the_start_on_one_line() {
+        let text = format!("linea\n{}", "z".repeat(SNIPPET_CONTEXT * 3));
+        let out = snippet(&text, &query_terms("nada"));
+        assert!(out.starts_with("linea z"));
+        assert!(!out.contains("<mark>"));
+        assert!(out.ends_with('…'));
+    }
+}
@@ -0,0 +1,541 @@
+use actix_multipart::Multipart;
+use actix_web::{delete, get, patch, post, put, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use validator::Validate;
+
+use crate::{
+    config::AppConfig,
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        audit::AuditAction,
+        notification::NotificationKind,
+        user::{
+            AuthResponse, AvatarQuery, ChangePasswordDto, DeleteAccountDto, PendingEmail,
+            PublicUser, UpdateProfileDto, User, VerifyEmailDto,
+        },
+    },
+    routes::{
+        api_keys::api_keys_collection,
+        auth::users_collection,
+        drops,
+        files::{delete_all_files_of, files_collection},
+        folders::folders_collection,
+        orgs,
+    },
+    utils::{
+        api_key,
+        audit::{self, AuditEntry},
+        avatar::{self, AVATAR_SIZES, MAX_AVATAR_BYTES},
+        jwt,
+        notify::{self, Notice},
+        random::random_token,
+        search_index, storage, webhooks,
+    },
+};
+
+const EMAIL_VERIFY_TTL_HOURS: i64 = 24;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_me)
+        .service(update_me)
+        .service(verify_email)
+        .service(change_password)
+        .service(delete_me)
+        .service(upload_avatar)
+        .service(delete_avatar)
+        .service(get_avatar);
+}
+
+pub(crate) fn remove_avatar_files(key: &str) {
+    for size in AVATAR_SIZES {
+        storage::remove(&avatar::stored_name(key, size));
+    }
+}
+
+async fn load_user(state: &AppState, user_id: &str) -> Result<User, ApiError> {
+    let id =
+        ObjectId::parse_str(user_id).map_err(|_| ApiError::Unauthorized("Invalid token".into()))?;
+
+    users_collection(state)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("User not found".into()))
+}
+
+async fn email_taken(state: &AppState, email: &str) -> Result<bool, ApiError> {
+    let existing = users_collection(state)
+        .find_one(doc! { "email": email }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    Ok(existing.is_some())
+}
+
+async fn check_password(state: &AppState, user: &User, password: &str) -> Result<(), ApiError> {
+    let hash = user
+        .password_hash
+        .as_deref()
+        .ok_or_else(|| ApiError::BadRequest("Account has no password set".into()))?;
+
+    let check = state
+        .passwords
+        .verify(password, hash)
+        .await
+        .map_err(ApiError::BadRequest)?;
+
+    if !check.ok {
+        return Err(ApiError::Unauthorized("Invalid credentials".into()));
+    }
+    Ok(())
+}
+
+#[get("/me")]
+async fn get_me(user: AuthUser, state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let me = load_user(&state, &user.user_id).await?;
+    Ok(HttpResponse::Ok().json(PublicUser::from(me)))
+}
+
+#[patch("/me")]
+async fn update_me(
+    user: AuthUser,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    body: web::Json<UpdateProfileDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let mut dto = body.into_inner();
+    dto.name = dto.name.map(|n| n.trim().to_string());
+    dto.email = dto.email.map(|e| e.trim().to_lowercase());
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let me = load_user(&state, &user.user_id).await?;
+    let mut set = Document::new();
+
+    if let Some(name) = dto.name {
+        set.insert("name", name);
+    }
+    if let Some(policy) = dto
+        .image_metadata_policy
+        .filter(|p| *p != me.image_metadata_policy)
+    {
+        set.insert(
+            "image_metadata_policy",
+            bson::to_bson(&policy).map_err(|_| ApiError::Internal)?,
+        );
+    }
+
+    // El email no cambia hasta que se confirma desde el nuevo correo
+    let mut verify_token = None;
+    if let Some(email) = dto.email.filter(|e| *e != me.email) {
+        if email_taken(&state, &email).await? {
+            return Err(ApiError::BadRequest("Email already registered".into()));
+        }
+
+        let token = random_token(32);
+        let pending = PendingEmail {
+            email,
+            token_hash: api_key::hash_key(&token),
+            expires_at: Utc::now() + Duration::hours(EMAIL_VERIFY_TTL_HOURS),
+        };
+        set.insert(
+            "pending_email",
+            bson::to_bson(&pending).map_err(|_| ApiError::Internal)?,
+        );
+        verify_token = Some((pending.email, token));
+    }
+
+    if set.is_empty() {
+        return Ok(HttpResponse::Ok().json(PublicUser::from(me)));
+    }
+
+    users_collection(&state)
+        .update_one(doc! { "_id": me.id }, doc! { "$set": set }, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo update_one error (update_me): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    if let Some((email, token)) = verify_token {
+        let body = format!(
+            "Hola {},\n\nPara confirmar este correo como tu nuevo email en PCOSEW, usa este código en \
+             POST {}/api/users/me/email/verify (válido {} horas):\n\n{}\n",
+            me.name, cfg.public_url, EMAIL_VERIFY_TTL_HOURS, token
+        );
+        if let Err(e) = state
+            .mailer
+            .send(&email, "Confirma tu nuevo correo", &body)
+            .await
+        {
+            eprintln!("Mail error (email change): {}", e);
+        }
+    }
+
+    let updated = load_user(&state, &user.user_id).await?;
+    Ok(HttpResponse::Ok().json(PublicUser::from(updated)))
+}
+
+#[post("/me/email/verify")]
+async fn verify_email(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<VerifyEmailDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let me = load_user(&state, &user.user_id).await?;
+    let invalid = || ApiError::BadRequest("Invalid or expired verification token".into());
+
+    let pending = me.pending_email.as_ref().ok_or_else(invalid)?;
+    if pending.token_hash != api_key::hash_key(body.token.trim())
+        || pending.expires_at <= Utc::now()
+    {
+        return Err(invalid());
+    }
+
+    // Pudo haberse registrado alguien más con ese email mientras tanto
+    if email_taken(&state, &pending.email).await? {
+        return Err(ApiError::BadRequest("Email already registered".into()));
+    }
+
+    users_collection(&state)
+        .update_one(
+            doc! { "_id": me.id },
+            doc! {
+                "$set": { "email": &pending.email },
+                "$unset": { "pending_email": "" },
+            },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo update_one error (verify_email): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::AccountChangeEmail)
+            .by(&user)
+            .target("user", me.id.to_hex())
+            .details(doc! { "from": &me.email, "to": &pending.email }),
+    )
+    .await;
+
+    let updated = load_user(&state, &user.user_id).await?;
+    let token = jwt::sign_jwt(
+        &state.jwt,
+        &updated.id.to_hex(),
+        &updated.email,
+        updated.role,
+    )
+    .map_err(ApiError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        token,
+        user: updated.into(),
+    }))
+}
+
+#[post("/me/password")]
+async fn change_password(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<ChangePasswordDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let dto = body.into_inner();
+    let me = load_user(&state, &user.user_id).await?;
+
+    check_password(&state, &me, &dto.current_password).await?;
+
+    state
+        .passwords
+        .validate_policy(&dto.new_password)
+        .map_err(ApiError::BadRequest)?;
+
+    let hash = state.passwords.hash(&dto.new_password).await.map_err(|e| {
+        eprintln!("Password hash error (change_password): {}", e);
+        ApiError::Internal
+    })?;
+
+    // Cierra las demás sesiones; se regresa un token nuevo para esta
+    users_collection(&state)
+        .update_one(
+            doc! { "_id": me.id },
+            doc! { "$set": { "password_hash": hash, "tokens_valid_after": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo update_one error (change_password): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::AccountChangePassword)
+            .by(&user)
+            .target("user", me.id.to_hex()),
+    )
+    .await;
+
+    let token = jwt::sign_jwt(&state.jwt, &me.id.to_hex(), &me.email, me.role)
+        .map_err(ApiError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        token,
+        user: me.into(),
+    }))
+}
+
+#[delete("/me")]
+async fn delete_me(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    body: web::Json<DeleteAccountDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let dto = body.into_inner();
+    let me = load_user(&state, &user.user_id).await?;
+
+    if me.password_hash.is_some() {
+        let password = dto
+            .password
+            .as_deref()
+            .ok_or_else(|| ApiError::BadRequest("password is required".into()))?;
+        check_password(&state, &me, password).await?;
+    }
+
+    let my_id = me.id.to_hex();
+
+    let transfer_to = dto.transfer_files_to.clone();
+    let (files_deleted, files_transferred) = match dto.transfer_files_to {
+        Some(email) => {
+            let email = email.trim().to_lowercase();
+            let target = users_collection(&state)
+                .find_one(doc! { "email": &email, "disabled": { "$ne": true } }, None)
+                .await
+                .map_err(|_| ApiError::Internal)?
+                .filter(|t| t.id != me.id)
+                .ok_or_else(|| ApiError::BadRequest("Transfer target not found".into()))?;
+            // Nada de regalarle archivos a un desconocido: tiene que ser alguien de tu organización
+            if !orgs::share_an_org(&state, &my_id, &target.id.to_hex()).await? {
+                return Err(ApiError::Forbidden(
+                    "Files can only be transferred to a member of one of your organizations".into(),
+                ));
+            }
+
+            let res = files_collection(&state)
+                .update_many(
+                    doc! { "owner_id": &my_id, "org_id": null },
+                    doc! { "$set": { "owner_id": target.id.to_hex(), "updated_at": bson_time(Utc::now()) } },
+                    None,
+                )
+                .await
+                .map_err(|_| ApiError::Internal)?;
+            folders_collection(&state)
+                .update_many(
+                    doc! { "owner_id": &my_id, "org_id": null },
+                    doc! { "$set": { "owner_id": target.id.to_hex(), "updated_at": bson_time(Utc::now()) } },
+                    None,
+                )
+                .await
+                .map_err(|_| ApiError::Internal)?;
+            search_index::transfer_owner(&state, &my_id, &target.id.to_hex()).await;
+            if res.modified_count > 0 {
+                notify::send(
+                    &state,
+                    vec![target.id.to_hex()],
+                    Notice::new(
+                        NotificationKind::FilesTransferred,
+                        format!("{} te transfirió sus archivos", me.name),
+                        format!(
+                            "{} ({}) borró su cuenta y te dejó {} archivo(s) en tu espacio personal.",
+                            me.name, me.email, res.modified_count
+                        ),
+                    )
+                    .link("/api/files")
+                    .data(doc! { "from": &my_id, "files": res.modified_count as i64 }),
+                )
+                .await;
+            }
+            (0, res.modified_count)
+        }
+        None => (delete_all_files_of(&state, &my_id).await?, 0),
+    };
+
+    orgs::remove_user_from_all(&state, &my_id).await?;
+    drops::revoke_all_of(&state, &my_id, None).await?;
+    webhooks::remove_all_of(&state, &my_id).await;
+    notify::remove_all_of(&state, &my_id).await;
+
+    api_keys_collection(&state)
+        .update_many(
+            doc! { "user_id": &my_id, "revoked_at": null },
+            doc! { "$set": { "revoked_at": bson_time(Utc::now()) } },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    users_collection(&state)
+        .delete_one(doc! { "_id": me.id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    if let Some(key) = me.avatar_key.as_deref() {
+        remove_avatar_files(key);
+    }
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::AccountDelete)
+            .by(&user)
+            .target("user", my_id.clone())
+            .details(doc! {
+                "files_deleted": files_deleted as i64,
+                "files_transferred": files_transferred as i64,
+                "transfer_to": transfer_to,
+            }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "ok": true,
+        "files_deleted": files_deleted,
+        "files_transferred": files_transferred
+    })))
+}
+
+#[put("/me/avatar")]
+async fn upload_avatar(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let Some(item) = payload.next().await else {
+        return Err(ApiError::BadRequest("No file uploaded".into()));
+    };
+    let mut field = item.map_err(|_| ApiError::BadRequest("Invalid multipart".into()))?;
+
+    let mut bytes: Vec<u8> = Vec::new();
+    while let Some(chunk) = field.next().await {
+        let data = chunk.map_err(|_| ApiError::BadRequest("Invalid multipart".into()))?;
+        if bytes.len() + data.len() > MAX_AVATAR_BYTES {
+            return Err(ApiError::BadRequest(format!(
+                "avatar must be at most {} MB",
+                MAX_AVATAR_BYTES / 1024 / 1024
+            )));
+        }
+        bytes.extend_from_slice(&data);
+    }
+
+    let variants = web::block(move || avatar::process_avatar(&bytes))
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .map_err(ApiError::BadRequest)?;
+
+    let me = load_user(&state, &user.user_id).await?;
+    let key = format!("{}_{}", me.id.to_hex(), ObjectId::new().to_hex());
+
+    for (size, png) in &variants {
+        storage::write(&avatar::stored_name(&key, *size), png)?;
+    }
+
+    users_collection(&state)
+        .update_one(
+            doc! { "_id": me.id },
+            doc! { "$set": { "avatar_key": &key } },
+            None,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo update_one error (avatar): {:?}", e);
+            remove_avatar_files(&key);
+            ApiError::Internal
+        })?;
+
+    if let Some(old) = me.avatar_key.as_deref() {
+        remove_avatar_files(old);
+    }
+
+    let updated = load_user(&state, &user.user_id).await?;
+    Ok(HttpResponse::Ok().json(PublicUser::from(updated)))
+}
+
+#[delete("/me/avatar")]
+async fn delete_avatar(
+    user: AuthUser,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let me = load_user(&state, &user.user_id).await?;
+    if let Some(key) = me.avatar_key.as_deref() {
+        users_collection(&state)
+            .update_one(
+                doc! { "_id": me.id },
+                doc! { "$unset": { "avatar_key": "" } },
+                None,
+            )
+            .await
+            .map_err(|_| ApiError::Internal)?;
+        remove_avatar_files(key);
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+// Público: el avatar se muestra en listados y comentarios sin necesidad de token
+#[get("/{id}/avatar")]
+async fn get_avatar(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<AvatarQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| ApiError::BadRequest("Invalid user id".into()))?;
+
+    let user = users_collection(&state)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("User not found".into()))?;
+
+    let key = user
+        .avatar_key
+        .ok_or_else(|| ApiError::NotFound("User has no avatar".into()))?;
+
+    // Tamaño pedido o el más cercano disponible
+    let wanted = query.size.unwrap_or(AVATAR_SIZES[0]);
+    let size = AVATAR_SIZES
+        .iter()
+        .copied()
+        .min_by_key(|s| s.abs_diff(wanted))
+        .unwrap_or(AVATAR_SIZES[0]);
+
+    let bytes = storage::read(&avatar::stored_name(&key, size))?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Content-Type", "image/png"))
+        .insert_header(("Cache-Control", "public, max-age=86400"))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .body(bytes))
+}
@@ -0,0 +1,300 @@
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use bson::{doc, oid::ObjectId};
+use chrono::Utc;
+use futures::StreamExt;
+use mongodb::options::FindOptions;
+use validator::Validate;
+
+use crate::{
+    config::AppConfig,
+    db::{bson_time, AppState},
+    errors::ApiError,
+    middleware::auth::AuthUser,
+    models::{
+        audit::AuditAction,
+        role::Role,
+        webhook::{
+            CreateWebhookDto, CreatedWebhook, DeliveryOut, UpdateWebhookDto, WebhookDoc, WebhookOut,
+        },
+    },
+    utils::{
+        audit::{self, AuditEntry},
+        random::random_token,
+        webhooks::{self, deliveries_collection, webhooks_collection},
+    },
+};
+
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_webhook)
+        .service(list_webhooks)
+        .service(get_webhook)
+        .service(update_webhook)
+        .service(delete_webhook)
+        .service(list_deliveries)
+        .service(redeliver);
+}
+
+fn parse_id(raw: &str, what: &str) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(raw).map_err(|_| ApiError::BadRequest(format!("Invalid {} id", what)))
+}
+
+async fn check_url(url: &str, cfg: &AppConfig) -> Result<(), ApiError> {
+    webhooks::resolve_target(url, cfg.webhooks.allow_private_targets)
+        .await
+        .map(|_| ())
+        .map_err(ApiError::BadRequest)
+}
+
+async fn find_own(state: &AppState, user: &AuthUser, raw_id: &str) -> Result<WebhookDoc, ApiError> {
+    let id = parse_id(raw_id, "webhook")?;
+    webhooks_collection(state)
+        .find_one(doc! { "_id": id, "owner_id": &user.user_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("Webhook not found".into()))
+}
+
+#[post("")]
+async fn create_webhook(
+    req: HttpRequest,
+    user: AuthUser,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    body: web::Json<CreateWebhookDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let mut dto = body.into_inner();
+    dto.url = dto.url.trim().to_string();
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+    check_url(&dto.url, &cfg).await?;
+
+    let mut events = dto.events;
+    events.sort_unstable_by_key(|e| e.as_str());
+    events.dedup();
+
+    let secret = dto
+        .secret
+        .unwrap_or_else(|| format!("whsec_{}", random_token(32)));
+    let now = Utc::now();
+    let hook = WebhookDoc {
+        id: ObjectId::new(),
+        owner_id: user.user_id.clone(),
+        platform: user.role == Role::Admin,
+        url: dto.url,
+        secret: secret.clone(),
+        events,
+        active: true,
+        created_at: now,
+        updated_at: now,
+    };
+
+    webhooks_collection(&state)
+        .insert_one(&hook, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (create_webhook): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::WebhookCreate)
+            .by(&user)
+            .target("webhook", hook.id.to_hex())
+            .details(doc! { "url": &hook.url, "platform": hook.platform }),
+    )
+    .await;
+
+    Ok(HttpResponse::Created().json(CreatedWebhook {
+        secret,
+        webhook: hook.into(),
+    }))
+}
+
+#[get("")]
+async fn list_webhooks(
+    user: AuthUser,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let mut cursor = webhooks_collection(&state)
+        .find(doc! { "owner_id": &user.user_id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out: Vec<WebhookOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        out.push(item.map_err(|_| ApiError::Internal)?.into());
+    }
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+#[get("/{id}")]
+async fn get_webhook(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let hook = find_own(&state, &user, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(WebhookOut::from(hook)))
+}
+
+#[patch("/{id}")]
+async fn update_webhook(
+    req: HttpRequest,
+    user: AuthUser,
+    cfg: web::Data<AppConfig>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<UpdateWebhookDto>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let mut dto = body.into_inner();
+    dto.url = dto.url.map(|u| u.trim().to_string());
+    dto.validate()
+        .map_err(|e: validator::ValidationErrors| ApiError::BadRequest(e.to_string()))?;
+
+    let hook = find_own(&state, &user, &path.into_inner()).await?;
+
+    let mut set = doc! { "updated_at": bson_time(Utc::now()) };
+    if let Some(url) = dto.url {
+        check_url(&url, &cfg).await?;
+        set.insert("url", url);
+    }
+    if let Some(mut events) = dto.events {
+        events.sort_unstable_by_key(|e| e.as_str());
+        events.dedup();
+        set.insert(
+            "events",
+            bson::to_bson(&events).map_err(|_| ApiError::Internal)?,
+        );
+    }
+    if let Some(active) = dto.active {
+        set.insert("active", active);
+    }
+
+    let mut changes = set.clone();
+    changes.remove("updated_at");
+    webhooks_collection(&state)
+        .update_one(doc! { "_id": hook.id }, doc! { "$set": set }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::WebhookUpdate)
+            .by(&user)
+            .target("webhook", hook.id.to_hex())
+            .details(changes),
+    )
+    .await;
+
+    let hook = find_own(&state, &user, &hook.id.to_hex()).await?;
+    Ok(HttpResponse::Ok().json(WebhookOut::from(hook)))
+}
+
+#[delete("/{id}")]
+async fn delete_webhook(
+    req: HttpRequest,
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+    let hook = find_own(&state, &user, &path.into_inner()).await?;
+
+    webhooks_collection(&state)
+        .delete_one(doc! { "_id": hook.id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+    deliveries_collection(&state)
+        .delete_many(doc! { "webhook_id": hook.id.to_hex() }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    audit::record(
+        &state,
+        &req,
+        AuditEntry::new(AuditAction::WebhookDelete)
+            .by(&user)
+            .target("webhook", hook.id.to_hex())
+            .details(doc! { "url": &hook.url }),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+}
+
+#[get("/{id}/deliveries")]
+async fn list_deliveries(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let hook = find_own(&state, &user, &path.into_inner()).await?;
+
+    let options = FindOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .limit(DELIVERY_LOG_LIMIT)
+        .build();
+
+    let mut cursor = deliveries_collection(&state)
+        .find(doc! { "webhook_id": hook.id.to_hex() }, options)
+        .await
+        .map_err(|_| ApiError::Internal)?;
+
+    let mut out: Vec<DeliveryOut> = Vec::new();
+    while let Some(item) = cursor.next().await {
+        out.push(item.map_err(|_| ApiError::Internal)?.into());
+    }
+
+    Ok(HttpResponse::Ok().json(out))
+}
+
+// Vuelve a encolar el mismo payload como una entrega nueva; el registro original se conserva
+#[post("/{id}/deliveries/{delivery_id}/redeliver")]
+async fn redeliver(
+    user: AuthUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+
+    let (hook_id, delivery_id) = path.into_inner();
+    let hook = find_own(&state, &user, &hook_id).await?;
+    let delivery_id = parse_id(&delivery_id, "delivery")?;
+
+    let original = deliveries_collection(&state)
+        .find_one(
+            doc! { "_id": delivery_id, "webhook_id": hook.id.to_hex() },
+            None,
+        )
+        .await
+        .map_err(|_| ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("Delivery not found".into()))?;
+
+    let delivery = webhooks::redelivery_of(original);
+
+    deliveries_collection(&state)
+        .insert_one(&delivery, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Mongo insert_one error (redeliver): {:?}", e);
+            ApiError::Internal
+        })?;
+
+    Ok(HttpResponse::Accepted().json(DeliveryOut::from(delivery)))
+}
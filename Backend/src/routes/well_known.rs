@@ -0,0 +1,15 @@
+use actix_web::{get, web, HttpResponse};
+
+use crate::{db::AppState, errors::ApiError};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(jwks);
+}
+
+// Llaves públicas para que otros servicios validen nuestros JWT
+#[get("/.well-known/jwks.json")]
+async fn jwks(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(state.jwt.jwks()))
+}
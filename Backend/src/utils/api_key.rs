@@ -0,0 +1,20 @@
+use sha2::{Digest, Sha256};
+
+use crate::utils::random::random_token;
+
+pub const KEY_PREFIX: &str = "pcsk_";
+
+// Regresa (llave completa, prefijo visible)
+pub fn generate_key() -> (String, String) {
+    let key = format!("{}{}", KEY_PREFIX, random_token(32));
+    let prefix = key.chars().take(KEY_PREFIX.len() + 6).collect();
+    (key, prefix)
+}
+
+// Las llaves tienen 256 bits aleatorios, un sha256 basta (no hace falta bcrypt)
+pub fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
@@ -0,0 +1,127 @@
+use actix_web::{http::header, HttpRequest};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use mongodb::{options::IndexOptions, IndexModel};
+
+use crate::{
+    db::AppState,
+    middleware::auth::AuthUser,
+    models::audit::{AuditAction, AuditEventDoc},
+    routes::auth::client_ip,
+};
+
+const MAX_USER_AGENT_LEN: usize = 512;
+
+pub(crate) fn audit_collection(state: &AppState) -> mongodb::Collection<AuditEventDoc> {
+    state.db.collection::<AuditEventDoc>("audit_events")
+}
+
+pub async fn ensure_indexes(state: &AppState) {
+    let col = audit_collection(state);
+
+    for (name, keys) in [
+        ("audit_created_at", doc! { "created_at": -1 }),
+        ("audit_actor", doc! { "actor_id": 1, "created_at": -1 }),
+        (
+            "audit_target",
+            doc! { "target_type": 1, "target_id": 1, "created_at": -1 },
+        ),
+    ] {
+        let options = IndexOptions::builder().name(Some(name.to_string())).build();
+        let model = IndexModel::builder().keys(keys).options(options).build();
+
+        if let Err(e) = col.create_index(model, None).await {
+            eprintln!("Mongo create_index error ({}): {:?}", name, e);
+        }
+    }
+}
+
+pub struct AuditEntry {
+    action: AuditAction,
+    actor_id: Option<String>,
+    actor_email: Option<String>,
+    impersonator_id: Option<String>,
+    target: Option<(String, String)>,
+    details: Option<Document>,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction) -> Self {
+        Self {
+            action,
+            actor_id: None,
+            actor_email: None,
+            impersonator_id: None,
+            target: None,
+            details: None,
+        }
+    }
+
+    pub fn by(mut self, user: &AuthUser) -> Self {
+        self.actor_id = Some(user.user_id.clone());
+        self.actor_email = Some(user.email.clone());
+        self.impersonator_id = user.impersonator.clone();
+        self
+    }
+
+    // Para eventos sin sesión todavía (login, registro)
+    pub fn by_account(mut self, user_id: Option<String>, email: &str) -> Self {
+        self.actor_id = user_id;
+        self.actor_email = Some(email.to_string());
+        self
+    }
+
+    pub fn target(mut self, kind: &str, id: impl Into<String>) -> Self {
+        self.target = Some((kind.to_string(), id.into()));
+        self
+    }
+
+    pub fn details(mut self, details: Document) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+// Un fallo al auditar se loggea pero no rompe la petición
+pub async fn record(state: &AppState, req: &HttpRequest, entry: AuditEntry) {
+    record_many(state, req, vec![entry]).await;
+}
+
+// Varios eventos de la misma petición en un solo insert (p.ej. archivos extraídos de un ZIP)
+pub async fn record_many(state: &AppState, req: &HttpRequest, entries: Vec<AuditEntry>) {
+    if entries.is_empty() {
+        return;
+    }
+
+    let user_agent: Option<String> = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+    let ip = client_ip(req);
+    let now = Utc::now();
+
+    let events: Vec<AuditEventDoc> = entries
+        .into_iter()
+        .map(|entry| {
+            let (target_type, target_id) = entry.target.unzip();
+            AuditEventDoc {
+                id: ObjectId::new(),
+                action: entry.action,
+                actor_id: entry.actor_id,
+                actor_email: entry.actor_email,
+                impersonator_id: entry.impersonator_id,
+                target_type,
+                target_id,
+                details: entry.details,
+                ip: ip.clone(),
+                user_agent: user_agent.clone(),
+                created_at: now,
+            }
+        })
+        .collect();
+
+    if let Err(e) = audit_collection(state).insert_many(&events, None).await {
+        eprintln!("Mongo insert_many error (audit): {:?}", e);
+    }
+}
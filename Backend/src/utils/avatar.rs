@@ -0,0 +1,57 @@
+use std::io::Cursor;
+
+use image::{imageops::FilterType, ImageFormat, ImageReader, Limits};
+
+pub const AVATAR_SIZES: [u32; 2] = [256, 64];
+pub const MAX_AVATAR_BYTES: usize = 5 * 1024 * 1024;
+const MIN_DIMENSION: u32 = 32;
+const MAX_DIMENSION: u32 = 8000;
+
+// Decodifica la imagen, la recorta al centro en cuadrado y genera un PNG por tamaño.
+// Es trabajo de CPU: llamarla dentro de web::block.
+pub fn process_avatar(bytes: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let format = image::guess_format(bytes).map_err(|_| "unrecognized image".to_string())?;
+    if !matches!(
+        format,
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP | ImageFormat::Gif
+    ) {
+        return Err("avatar must be PNG, JPEG, WebP or GIF".into());
+    }
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+
+    let img = reader
+        .decode()
+        .map_err(|e| format!("invalid image: {}", e))?;
+
+    let (w, h) = (img.width(), img.height());
+    if w < MIN_DIMENSION || h < MIN_DIMENSION {
+        return Err(format!(
+            "avatar must be at least {}x{}",
+            MIN_DIMENSION, MIN_DIMENSION
+        ));
+    }
+
+    let side = w.min(h);
+    let square = img.crop_imm((w - side) / 2, (h - side) / 2, side, side);
+
+    AVATAR_SIZES
+        .iter()
+        .map(|&size| {
+            let resized = square.resize_exact(size, size, FilterType::Lanczos3);
+            let mut out = Vec::new();
+            resized
+                .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+                .map_err(|e| e.to_string())?;
+            Ok((size, out))
+        })
+        .collect()
+}
+
+pub fn stored_name(key: &str, size: u32) -> String {
+    format!("avatar_{}_{}.png", key, size)
+}
@@ -0,0 +1,62 @@
+// Content-Disposition según RFC 6266: `filename` con un respaldo ASCII para clientes viejos
+// y `filename*` (RFC 5987, UTF-8 percent-encoded) con el nombre real
+use actix_web::http::header::{
+    Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue,
+};
+
+pub fn attachment(filename: &str) -> ContentDisposition {
+    build(DispositionType::Attachment, filename)
+}
+
+pub fn inline(filename: &str) -> ContentDisposition {
+    build(DispositionType::Inline, filename)
+}
+
+fn build(disposition: DispositionType, filename: &str) -> ContentDisposition {
+    ContentDisposition {
+        disposition,
+        parameters: vec![
+            DispositionParam::Filename(ascii_fallback(filename)),
+            DispositionParam::FilenameExt(ExtendedValue {
+                charset: Charset::Ext("UTF-8".into()),
+                language_tag: None,
+                value: filename.as_bytes().to_vec(),
+            }),
+        ],
+    }
+}
+
+// Acentos, emojis y caracteres de control no viajan en el `filename` clásico
+fn ascii_fallback(filename: &str) -> String {
+    filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_fallback_replaces_non_ascii_and_controls() {
+        assert_eq!(ascii_fallback("informe final.pdf"), "informe final.pdf");
+        assert_eq!(ascii_fallback("año 2024 ñandú.pdf"), "a_o 2024 _and_.pdf");
+        assert_eq!(ascii_fallback("foto 📷.jpg"), "foto _.jpg");
+        assert_eq!(ascii_fallback("a\r\nb\t.txt"), "a__b_.txt");
+    }
+
+    #[test]
+    fn header_keeps_the_utf8_name_in_filename_star() {
+        let value = attachment("año.pdf").to_string();
+        assert!(value.starts_with("attachment"));
+        assert!(value.contains("filename=\"a_o.pdf\""));
+        assert!(value.contains("filename*=UTF-8''a%C3%B1o.pdf"));
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+// Eventos que se pierden si un cliente se atrasa más que esto
+const CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum LiveEventKind {
+    #[serde(rename = "file.uploaded")]
+    FileUploaded,
+    #[serde(rename = "file.shared_with_you")]
+    FileSharedWithYou,
+    #[serde(rename = "file.visibility_changed")]
+    FileVisibilityChanged,
+    #[serde(rename = "request.approved")]
+    RequestApproved,
+    #[serde(rename = "request.rejected")]
+    RequestRejected,
+    #[serde(rename = "comment.created")]
+    CommentCreated,
+    #[serde(rename = "notification.created")]
+    NotificationCreated,
+}
+
+impl LiveEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiveEventKind::FileUploaded => "file.uploaded",
+            LiveEventKind::FileSharedWithYou => "file.shared_with_you",
+            LiveEventKind::FileVisibilityChanged => "file.visibility_changed",
+            LiveEventKind::RequestApproved => "request.approved",
+            LiveEventKind::RequestRejected => "request.rejected",
+            LiveEventKind::CommentCreated => "comment.created",
+            LiveEventKind::NotificationCreated => "notification.created",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiveEvent {
+    pub recipients: Vec<String>, // user ids
+    pub kind: LiveEventKind,
+    pub data: Value,
+}
+
+impl LiveEvent {
+    pub fn is_for(&self, user_id: &str) -> bool {
+        self.recipients.iter().any(|r| r == user_id)
+    }
+
+    // Formato text/event-stream
+    pub fn to_sse(&self) -> String {
+        format!("event: {}\ndata: {}\n\n", self.kind.as_str(), self.data)
+    }
+}
+
+// Bus en memoria: solo llega a los clientes conectados a esta instancia
+pub struct EventBus {
+    tx: broadcast::Sender<LiveEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { tx }
+    }
+
+    pub fn publish(&self, recipients: Vec<String>, kind: LiveEventKind, data: Value) {
+        if recipients.is_empty() {
+            return;
+        }
+        // Err = nadie escuchando, no es un error
+        let _ = self.tx.send(LiveEvent {
+            recipients,
+            kind,
+            data,
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,396 @@
+// Extracción de ZIP / TAR / TAR.GZ subidos. Corre en un hilo bloqueante: escribe cada
+// entrada en storage y devuelve qué se extrajo y qué se omitió (y por qué).
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{Read, Write},
+};
+
+use bson::oid::ObjectId;
+use sanitize_filename::sanitize;
+
+use crate::{config::ExtractConfig, errors::ApiError, models::file::SkippedEntry, utils::storage};
+
+const MAX_DEPTH: usize = 32;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let lower = name.to_lowercase();
+        if lower.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if lower.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+
+    // Nombre de la carpeta destino: el del archivo sin la extensión
+    pub fn folder_name(&self, name: &str) -> String {
+        let cut = match self {
+            ArchiveKind::Zip | ArchiveKind::Tar => 4,
+            ArchiveKind::TarGz if name.to_lowercase().ends_with(".tgz") => 4,
+            ArchiveKind::TarGz => 7,
+        };
+        let stem = &name[..name.len().saturating_sub(cut)];
+        if stem.trim().is_empty() {
+            "archive".to_string()
+        } else {
+            stem.to_string()
+        }
+    }
+}
+
+pub struct ExtractedFile {
+    pub dir: Vec<String>, // carpetas relativas a la raíz del archivo
+    pub name: String,
+    pub stored_name: String,
+    pub size: i64,
+}
+
+#[derive(Default)]
+pub struct Extracted {
+    pub dirs: BTreeSet<Vec<String>>, // ordenado: cada padre antes que sus hijas
+    pub files: Vec<ExtractedFile>,
+    pub skipped: Vec<SkippedEntry>,
+    pub bytes: u64,
+}
+
+impl Extracted {
+    // Si la extracción falla no quedan huérfanos en disco
+    fn discard(self) {
+        for f in self.files {
+            storage::remove(&f.stored_name);
+        }
+    }
+}
+
+// Topes de una extracción concreta
+pub struct ExtractLimits {
+    pub max_entries: usize,
+    pub max_bytes: u64, // el menor entre el tope global, el ratio y la cuota
+    pub max_entry_ratio: u64,
+    pub exceeded: fn() -> ApiError, // error al rebasar max_bytes
+}
+
+impl ExtractLimits {
+    pub fn new(
+        cfg: &ExtractConfig,
+        archive_size: u64,
+        quota: Option<(u64, fn() -> ApiError)>,
+    ) -> Self {
+        let by_ratio = archive_size.saturating_mul(cfg.max_ratio);
+        let (max_bytes, exceeded) = match quota {
+            Some((free, err)) if free < cfg.max_total_bytes.min(by_ratio) => (free, err),
+            _ => (
+                cfg.max_total_bytes.min(by_ratio),
+                too_large as fn() -> ApiError,
+            ),
+        };
+        Self {
+            max_entries: cfg.max_entries,
+            max_bytes,
+            max_entry_ratio: cfg.max_ratio,
+            exceeded,
+        }
+    }
+}
+
+fn too_large() -> ApiError {
+    ApiError::BadRequest("Archive expands beyond the allowed size".into())
+}
+
+pub fn extract(
+    stored_name: &str,
+    kind: ArchiveKind,
+    limits: &ExtractLimits,
+) -> Result<Extracted, ApiError> {
+    let file = fs::File::open(storage::path_for(stored_name)).map_err(|_| ApiError::Internal)?;
+    let mut out = Extracted::default();
+
+    let result = match kind {
+        ArchiveKind::Zip => extract_zip(file, limits, &mut out),
+        ArchiveKind::Tar => extract_tar(file, limits, &mut out),
+        ArchiveKind::TarGz => extract_tar(flate2::read::GzDecoder::new(file), limits, &mut out),
+    };
+
+    match result {
+        Ok(()) => Ok(out),
+        Err(e) => {
+            out.discard();
+            Err(e)
+        }
+    }
+}
+
+fn corrupt() -> ApiError {
+    ApiError::BadRequest("Archive is corrupt or unsupported".into())
+}
+
+fn too_many(limits: &ExtractLimits) -> ApiError {
+    ApiError::BadRequest(format!(
+        "Archive has more than {} entries",
+        limits.max_entries
+    ))
+}
+
+fn skip(out: &mut Extracted, name: &str, reason: &str) {
+    out.skipped.push(SkippedEntry {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    });
+}
+
+// Ruta segura dentro de la carpeta destino; None = absoluta, con `..` o demasiado profunda
+fn safe_path(raw: &str) -> Option<Vec<String>> {
+    if raw.starts_with('/') || raw.starts_with('\\') {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    for part in raw.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            p if p.contains(':') => return None, // C:, flujos NTFS
+            p => {
+                let clean = sanitize(p);
+                if clean.is_empty() {
+                    return None;
+                }
+                parts.push(clean);
+            }
+        }
+    }
+
+    (!parts.is_empty() && parts.len() <= MAX_DEPTH).then_some(parts)
+}
+
+fn add_dirs(out: &mut Extracted, dir: &[String]) {
+    for i in 1..=dir.len() {
+        out.dirs.insert(dir[..i].to_vec());
+    }
+}
+
+// Copia una entrada a storage sin pasarse del presupuesto total ni del ratio por entrada
+fn write_entry(
+    reader: &mut dyn Read,
+    mut path: Vec<String>,
+    compressed: Option<u64>,
+    limits: &ExtractLimits,
+    out: &mut Extracted,
+) -> Result<(), ApiError> {
+    let name = path.pop().unwrap_or_default();
+    let stored_name = format!("{}_{}", ObjectId::new().to_hex(), name);
+    let mut f = storage::create(&stored_name)?;
+
+    // Entradas diminutas comprimen muchísimo sin ser peligrosas
+    let entry_cap = compressed.map(|c| c.max(1024).saturating_mul(limits.max_entry_ratio));
+
+    let mut size: u64 = 0;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(_) => {
+                drop(f);
+                storage::remove(&stored_name);
+                return Err(corrupt());
+            }
+        };
+        size += n as u64;
+
+        let over_total = out.bytes + size > limits.max_bytes;
+        let over_ratio = entry_cap.is_some_and(|cap| size > cap);
+        if over_total || over_ratio {
+            drop(f);
+            storage::remove(&stored_name);
+            return Err(if over_ratio {
+                too_large()
+            } else {
+                (limits.exceeded)()
+            });
+        }
+
+        if f.write_all(&buf[..n]).is_err() {
+            drop(f);
+            storage::remove(&stored_name);
+            return Err(ApiError::Internal);
+        }
+    }
+
+    out.bytes += size;
+    add_dirs(out, &path);
+    out.files.push(ExtractedFile {
+        dir: path,
+        name,
+        stored_name,
+        size: size as i64,
+    });
+    Ok(())
+}
+
+fn extract_zip(
+    file: fs::File,
+    limits: &ExtractLimits,
+    out: &mut Extracted,
+) -> Result<(), ApiError> {
+    let mut archive = ::zip::ZipArchive::new(file).map_err(|_| corrupt())?;
+    if archive.len() > limits.max_entries {
+        return Err(too_many(limits));
+    }
+
+    for i in 0..archive.len() {
+        let raw = archive.name_for_index(i).unwrap_or("?").to_string();
+        let mut entry = match archive.by_index(i) {
+            Ok(entry) => entry,
+            Err(e) => {
+                // Cifradas o con un método de compresión no soportado
+                skip(out, &raw, &format!("unreadable entry: {}", e));
+                continue;
+            }
+        };
+
+        if entry.is_symlink() {
+            skip(out, &raw, "symbolic links are not extracted");
+            continue;
+        }
+        let Some(path) = safe_path(&raw) else {
+            skip(out, &raw, "unsafe path");
+            continue;
+        };
+        if entry.is_dir() {
+            add_dirs(out, &path);
+            continue;
+        }
+
+        let compressed = entry.compressed_size();
+        write_entry(&mut entry, path, Some(compressed), limits, out)?;
+    }
+    Ok(())
+}
+
+fn extract_tar<R: Read>(
+    reader: R,
+    limits: &ExtractLimits,
+    out: &mut Extracted,
+) -> Result<(), ApiError> {
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive.entries().map_err(|_| corrupt())?;
+    let mut consumed: u64 = 0;
+
+    for (count, entry) in entries.enumerate() {
+        if count >= limits.max_entries {
+            return Err(too_many(limits));
+        }
+        let mut entry = entry.map_err(|_| corrupt())?;
+        // Lo que se salta también hay que descomprimirlo para llegar a la siguiente cabecera
+        consumed = consumed.saturating_add(entry.size());
+        if consumed > limits.max_bytes {
+            return Err((limits.exceeded)());
+        }
+        let raw = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            match safe_path(&raw) {
+                Some(path) => add_dirs(out, &path),
+                None => skip(out, &raw, "unsafe path"),
+            }
+            continue;
+        }
+        if kind.is_symlink() || kind.is_hard_link() {
+            skip(out, &raw, "links are not extracted");
+            continue;
+        }
+        if !kind.is_file() && !kind.is_contiguous() {
+            // Dispositivos, FIFOs, cabeceras pax sueltas...
+            if !kind.is_pax_global_extensions() && !kind.is_pax_local_extensions() {
+                skip(out, &raw, "special files are not extracted");
+            }
+            continue;
+        }
+        let Some(path) = safe_path(&raw) else {
+            skip(out, &raw, "unsafe path");
+            continue;
+        };
+
+        // TAR no comprime por entrada: el ratio global ya lo cubre max_bytes
+        write_entry(&mut entry, path, None, limits, out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_relative_paths_into_clean_safe_path() {
+        assert_eq!(safe_path("docs/a.txt").unwrap(), ["docs", "a.txt"]);
+        assert_eq!(
+            safe_path("docs\\sub\\a.txt").unwrap(),
+            ["docs", "sub", "a.txt"]
+        );
+        assert_eq!(safe_path("./docs//a.txt").unwrap(), ["docs", "a.txt"]);
+    }
+
+    #[test]
+    fn rejects_traversal_and_absolute_paths() {
+        for raw in [
+            "../etc/passwd",
+            "docs/../../a.txt",
+            "/etc/passwd",
+            "\\windows\\system32",
+            "C:/boot.ini",
+            "a.txt:stream",
+            "",
+            "./",
+        ] {
+            assert!(safe_path(raw).is_none(), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn skipped_tar_entries_count_against_max_bytes() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Fifo);
+        header.set_size(4096);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "pipe", &[0u8; 4096][..])
+            .unwrap();
+        let data = builder.into_inner().unwrap();
+
+        let limits = ExtractLimits {
+            max_entries: 10,
+            max_bytes: 1024,
+            max_entry_ratio: 100,
+            exceeded: too_large,
+        };
+        let mut out = Extracted::default();
+        let res = extract_tar(std::io::Cursor::new(data), &limits, &mut out);
+        assert!(matches!(res, Err(ApiError::BadRequest(_))));
+        assert!(out.files.is_empty());
+    }
+
+    #[test]
+    fn rejects_paths_deeper_than_the_limit() {
+        let deep = vec!["d"; MAX_DEPTH + 1].join("/");
+        assert!(safe_path(&deep).is_none());
+        let ok = vec!["d"; MAX_DEPTH].join("/");
+        assert_eq!(safe_path(&ok).unwrap().len(), MAX_DEPTH);
+    }
+}
@@ -0,0 +1,825 @@
+// Metadatos de fotos (cámara, fecha, GPS...) y su limpieza sin recomprimir la imagen:
+// JPEG, PNG y WebP se reescriben sin los bloques EXIF/XMP/IPTC; en HEIC esos ítems se
+// rellenan con ceros en su sitio para no tener que recalcular los offsets del contenedor.
+use std::{fs, io::BufReader};
+
+use actix_web::web;
+use bson::{doc, oid::ObjectId};
+use exif::{In, Tag, Value};
+
+use crate::{
+    db::AppState,
+    errors::ApiError,
+    models::{
+        file::{FileDoc, ImageMetadata},
+        user::MetadataPolicy,
+    },
+    routes::auth::users_collection,
+    utils::storage,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    Jpeg,
+    Png,
+    Webp,
+    Heic,
+}
+
+impl ImageKind {
+    pub fn detect(mime: &str, name: &str) -> Option<Self> {
+        let ext = name
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_lowercase())
+            .unwrap_or_default();
+        match (mime, ext.as_str()) {
+            ("image/jpeg", _) | (_, "jpg" | "jpeg") => Some(ImageKind::Jpeg),
+            ("image/png", _) | (_, "png") => Some(ImageKind::Png),
+            ("image/webp", _) | (_, "webp") => Some(ImageKind::Webp),
+            ("image/heic" | "image/heif", _) | (_, "heic" | "heif") => Some(ImageKind::Heic),
+            _ => None,
+        }
+    }
+}
+
+// Preferencia del dueño del archivo
+pub async fn policy(state: &AppState, owner_id: &str) -> MetadataPolicy {
+    let Ok(id) = ObjectId::parse_str(owner_id) else {
+        return MetadataPolicy::default();
+    };
+    users_collection(state)
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .ok()
+        .flatten()
+        .map(|u| u.image_metadata_policy)
+        .unwrap_or_default()
+}
+
+// ¿Se limpia al subir? La opción de la subida manda sobre la preferencia
+pub async fn strip_on_upload(state: &AppState, owner_id: &str, requested: Option<bool>) -> bool {
+    match requested {
+        Some(strip) => strip,
+        None => policy(state, owner_id).await == MetadataPolicy::StripOnUpload,
+    }
+}
+
+// Foto recién subida: se leen sus metadatos y se limpia según la opción o la preferencia
+pub async fn on_upload(
+    state: &AppState,
+    stored_name: &str,
+    mime: &str,
+    name: &str,
+    owner_id: &str,
+    requested: Option<bool>,
+) -> Result<Option<(ImageMetadata, i64)>, ApiError> {
+    if ImageKind::detect(mime, name).is_none() {
+        return Ok(None);
+    }
+    let strip = strip_on_upload(state, owner_id, requested).await;
+    inspect(stored_name, mime, name, strip).await
+}
+
+// Foto que se va a publicar: si el dueño pidió limpiar al compartir y sigue con sus
+// metadatos, se limpia ahora. None = no había nada que hacer.
+pub async fn on_share(
+    state: &AppState,
+    file: &FileDoc,
+) -> Result<Option<(ImageMetadata, i64)>, ApiError> {
+    if ImageKind::detect(&file.mime, &file.original_name).is_none()
+        || file.image_meta.as_ref().is_some_and(|m| m.stripped)
+        || policy(state, &file.owner_id).await != MetadataPolicy::StripOnShare
+    {
+        return Ok(None);
+    }
+    inspect(&file.stored_name, &file.mime, &file.original_name, true).await
+}
+
+// Lee los metadatos de una foto en storage y, si se pide, los borra del archivo.
+// None = no es un formato soportado; devuelve también el tamaño final en disco.
+pub async fn inspect(
+    stored_name: &str,
+    mime: &str,
+    name: &str,
+    strip: bool,
+) -> Result<Option<(ImageMetadata, i64)>, ApiError> {
+    let Some(kind) = ImageKind::detect(mime, name) else {
+        return Ok(None);
+    };
+    let path = storage::path_for(stored_name);
+
+    let result = web::block(move || {
+        let mut meta = read(&path, kind);
+        if strip {
+            self::strip(&path, kind, meta.orientation)?;
+            meta.stripped = true;
+        }
+        let size = fs::metadata(&path).map_err(|e| e.to_string())?.len() as i64;
+        Ok::<_, String>((meta, size))
+    })
+    .await
+    .map_err(|_| ApiError::Internal)?;
+
+    match result {
+        Ok(out) => Ok(Some(out)),
+        Err(e) => {
+            eprintln!("Image metadata error ({}): {}", stored_name, e);
+            Err(ApiError::BadRequest(
+                "Could not remove metadata from this image".into(),
+            ))
+        }
+    }
+}
+
+pub fn read(path: &str, kind: ImageKind) -> ImageMetadata {
+    let mut meta = ImageMetadata::default();
+
+    // Dimensiones reales desde la cabecera; HEIC no lo decodifica `image`, ahí se usa EXIF
+    if kind != ImageKind::Heic {
+        if let Ok((w, h)) = image::ImageReader::open(path)
+            .and_then(|r| r.with_guessed_format())
+            .map_err(|e| e.to_string())
+            .and_then(|r| r.into_dimensions().map_err(|e| e.to_string()))
+        {
+            meta.width = Some(w);
+            meta.height = Some(h);
+        }
+    }
+
+    let Ok(file) = fs::File::open(path) else {
+        return meta;
+    };
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut BufReader::new(file)) else {
+        return meta;
+    };
+
+    let text = |tag: Tag| match exif.get_field(tag, In::PRIMARY).map(|f| &f.value) {
+        Some(Value::Ascii(parts)) => parts
+            .first()
+            .map(|p| {
+                String::from_utf8_lossy(p)
+                    .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                    .to_string()
+            })
+            .filter(|s| !s.is_empty()),
+        _ => None,
+    };
+    let uint = |tag: Tag| {
+        exif.get_field(tag, In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0))
+    };
+
+    meta.camera_make = text(Tag::Make);
+    meta.camera_model = text(Tag::Model);
+    meta.lens_model = text(Tag::LensModel);
+    meta.orientation = uint(Tag::Orientation).map(|o| o as u16);
+    meta.width = meta.width.or(uint(Tag::PixelXDimension));
+    meta.height = meta.height.or(uint(Tag::PixelYDimension));
+    meta.has_location = exif.get_field(Tag::GPSLatitude, In::PRIMARY).is_some();
+
+    if let Some(Value::Ascii(parts)) = exif
+        .get_field(Tag::DateTimeOriginal, In::PRIMARY)
+        .map(|f| &f.value)
+    {
+        if let Some(mut dt) = parts
+            .first()
+            .and_then(|p| exif::DateTime::from_ascii(p).ok())
+        {
+            if let Some(Value::Ascii(off)) = exif
+                .get_field(Tag::OffsetTimeOriginal, In::PRIMARY)
+                .map(|f| &f.value)
+            {
+                if let Some(off) = off.first() {
+                    let _ = dt.parse_offset(off);
+                }
+            }
+            let mut out = format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+            );
+            if let Some(offset) = dt.offset {
+                let sign = if offset < 0 { '-' } else { '+' };
+                out.push_str(&format!(
+                    "{}{:02}:{:02}",
+                    sign,
+                    offset.abs() / 60,
+                    offset.abs() % 60
+                ));
+            }
+            meta.taken_at = Some(out);
+        }
+    }
+
+    meta
+}
+
+// Reescribe el archivo sin metadatos (archivo temporal + rename: nunca queda a medias)
+pub fn strip(path: &str, kind: ImageKind, orientation: Option<u16>) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let out = match kind {
+        ImageKind::Jpeg => strip_jpeg(&data, orientation)?,
+        ImageKind::Png => strip_png(&data)?,
+        ImageKind::Webp => strip_webp(&data)?,
+        ImageKind::Heic => blank_heic(data)?,
+    };
+
+    let tmp = format!("{}.strip", path);
+    fs::write(&tmp, &out).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        e.to_string()
+    })
+}
+
+fn be16(data: &[u8], pos: usize) -> Option<usize> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+}
+
+fn be32(data: &[u8], pos: usize) -> Option<usize> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+}
+
+// Entero big-endian de 0, 4 u 8 bytes (tamaños variables del iloc de HEIF)
+fn be_n(data: &[u8], pos: usize, n: usize) -> Option<usize> {
+    let bytes = data.get(pos..pos + n)?;
+    Some(bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize))
+}
+
+fn truncated() -> String {
+    "truncated or corrupt image".to_string()
+}
+
+// EXIF mínimo con solo la orientación: sin ella las fotos del móvil se verían giradas
+fn orientation_app1(orientation: u16) -> Vec<u8> {
+    let mut tiff = Vec::new();
+    tiff.extend_from_slice(b"Exif\0\0MM\0\x2a\0\0\0\x08");
+    tiff.extend_from_slice(&1u16.to_be_bytes()); // una entrada
+    tiff.extend_from_slice(&0x0112u16.to_be_bytes()); // Orientation
+    tiff.extend_from_slice(&3u16.to_be_bytes()); // SHORT
+    tiff.extend_from_slice(&1u32.to_be_bytes());
+    tiff.extend_from_slice(&orientation.to_be_bytes());
+    tiff.extend_from_slice(&[0, 0]);
+    tiff.extend_from_slice(&0u32.to_be_bytes()); // sin más IFDs
+
+    let mut out = vec![0xFF, 0xE1];
+    out.extend_from_slice(&((tiff.len() + 2) as u16).to_be_bytes());
+    out.extend_from_slice(&tiff);
+    out
+}
+
+// Fuera APP1 (EXIF/XMP), APP13 (IPTC), el resto de APPn y comentarios. Se quedan JFIF (APP0),
+// el perfil de color ICC (APP2) y Adobe (APP14), que cambian cómo se ven los colores.
+fn strip_jpeg(data: &[u8], orientation: Option<u16>) -> Result<Vec<u8>, String> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err("not a JPEG".into());
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&[0xFF, 0xD8]);
+    let mut orientation = orientation.filter(|o| *o != 1).map(orientation_app1);
+    let mut pos = 2;
+
+    loop {
+        if data.get(pos) != Some(&0xFF) {
+            return Err(truncated());
+        }
+        let marker = *data.get(pos + 1).ok_or_else(truncated)?;
+        match marker {
+            0xFF => {
+                pos += 1; // relleno
+                continue;
+            }
+            0x01 | 0xD0..=0xD7 => {
+                out.extend_from_slice(&data[pos..pos + 2]);
+                pos += 2;
+                continue;
+            }
+            _ => {}
+        }
+
+        // La orientación va justo después de JFIF
+        if marker != 0xE0 {
+            if let Some(app1) = orientation.take() {
+                out.extend_from_slice(&app1);
+            }
+        }
+        // EOI cierra la imagen principal. Lo que venga pegado detrás (segundas imágenes o
+        // vistas previas, cada una con su propio EXIF) se descarta
+        if marker == 0xD9 {
+            out.extend_from_slice(&[0xFF, 0xD9]);
+            return Ok(out);
+        }
+
+        let len = be16(data, pos + 2)
+            .filter(|l| *l >= 2)
+            .ok_or_else(truncated)?;
+        let end = pos + 2 + len;
+        let segment = data.get(pos..end).ok_or_else(truncated)?;
+        let keep = match marker {
+            0xE0 | 0xEE => true,
+            0xE2 => segment[4..].starts_with(b"ICC_PROFILE\0"),
+            0xE1 | 0xE3..=0xED | 0xEF | 0xFE => false,
+            _ => true,
+        };
+        if keep {
+            out.extend_from_slice(segment);
+        }
+        pos = end;
+
+        // Tras SOS vienen los datos comprimidos hasta el siguiente marcador
+        if marker == 0xDA {
+            let next = scan_end(data, pos);
+            out.extend_from_slice(&data[pos..next]);
+            if next == data.len() {
+                return Ok(out); // sin EOI: se conserva tal cual
+            }
+            pos = next;
+        }
+    }
+}
+
+// Dentro de un scan 0xFF00 (byte escapado), RSTn y el relleno 0xFFFF no cortan los datos
+fn scan_end(data: &[u8], from: usize) -> usize {
+    let mut i = from;
+    while i + 1 < data.len() {
+        if data[i] == 0xFF && !matches!(data[i + 1], 0x00 | 0xD0..=0xD7 | 0xFF) {
+            return i;
+        }
+        i += 1;
+    }
+    data.len()
+}
+
+// Fuera eXIf, los chunks de texto (tEXt/zTXt/iTXt, donde va el XMP) y tIME
+fn strip_png(data: &[u8]) -> Result<Vec<u8>, String> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !data.starts_with(SIGNATURE) {
+        return Err("not a PNG".into());
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(SIGNATURE);
+    let mut pos = SIGNATURE.len();
+
+    while pos < data.len() {
+        let len = be32(data, pos).ok_or_else(truncated)?;
+        let end = pos + 12 + len; // largo + tipo + datos + CRC
+        let chunk = data.get(pos..end).ok_or_else(truncated)?;
+        let kind = &chunk[4..8];
+
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            out.extend_from_slice(chunk);
+        }
+        pos = end;
+        if kind == b"IEND" {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+// Fuera los chunks EXIF y XMP; VP8X deja de anunciarlos en sus flags
+fn strip_webp(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err("not a WebP".into());
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[0..12]);
+    let mut pos = 12;
+
+    while pos + 8 <= data.len() {
+        let fourcc = &data[pos..pos + 4];
+        let b = &data[pos + 4..pos + 8];
+        let len = u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize;
+        let end = pos + 8 + len + (len & 1); // relleno a tamaño par
+        let chunk = data.get(pos..end.min(data.len())).ok_or_else(truncated)?;
+
+        match fourcc {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" if chunk.len() > 8 => {
+                let start = out.len();
+                out.extend_from_slice(chunk);
+                out[start + 8] &= !(0x08 | 0x04);
+            }
+            _ => out.extend_from_slice(chunk),
+        }
+        pos = end;
+    }
+
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(out)
+}
+
+// Cajas ISOBMFF hijas dentro de data[start..end]: (tipo, inicio del contenido, fin)
+fn boxes(data: &[u8], start: usize, end: usize) -> Result<Vec<([u8; 4], usize, usize)>, String> {
+    let mut out = Vec::new();
+    let mut pos = start;
+    while pos + 8 <= end {
+        let size = be32(data, pos).ok_or_else(truncated)?;
+        let kind: [u8; 4] = data
+            .get(pos + 4..pos + 8)
+            .and_then(|k| k.try_into().ok())
+            .ok_or_else(truncated)?;
+        let (header, size) = match size {
+            0 => (8, end - pos),
+            1 => (16, be_n(data, pos + 8, 8).ok_or_else(truncated)?),
+            n => (8, n),
+        };
+        if size < header || pos.checked_add(size).is_none_or(|e| e > end) {
+            return Err(truncated());
+        }
+        out.push((kind, pos + header, pos + size));
+        pos += size;
+    }
+    Ok(out)
+}
+
+// Rellena con ceros los ítems Exif y XMP de un HEIC/HEIF (los ubica vía iinf + iloc)
+fn blank_heic(mut data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let top = boxes(&data, 0, data.len())?;
+    let (_, meta_start, meta_end) = *top
+        .iter()
+        .find(|b| &b.0 == b"meta")
+        .ok_or("HEIF without meta box")?;
+    // meta es FullBox: 4 bytes de versión + flags antes de sus hijas
+    let children = boxes(&data, meta_start + 4, meta_end)?;
+
+    let mut targets = Vec::new();
+    if let Some((_, start, end)) = children.iter().find(|b| &b.0 == b"iinf") {
+        let version = *data
+            .get(*start..*end)
+            .and_then(|b| b.first())
+            .ok_or_else(truncated)?;
+        let first = start + 4 + if version == 0 { 2 } else { 4 };
+        for (kind, s, e) in boxes(&data, first, *end)? {
+            if &kind != b"infe" {
+                continue;
+            }
+            let infe = &data[s..e];
+            let version = *infe.first().ok_or_else(truncated)?;
+            if version < 2 {
+                continue;
+            }
+            let id_len = if version == 2 { 2 } else { 4 };
+            let id = be_n(infe, 4, id_len).ok_or_else(truncated)?;
+            let item_type = infe
+                .get(4 + id_len + 2..4 + id_len + 6)
+                .ok_or_else(truncated)?;
+            let is_xmp = item_type == b"mime"
+                && infe[4 + id_len + 6..]
+                    .split(|b| *b == 0)
+                    .nth(1)
+                    .is_some_and(|ct| ct.starts_with(b"application/rdf+xml"));
+            if item_type == b"Exif" || is_xmp {
+                targets.push(id);
+            }
+        }
+    }
+    if targets.is_empty() {
+        return Ok(data);
+    }
+
+    let idat = children.iter().find(|b| &b.0 == b"idat").map(|b| b.1);
+    let (_, start, end) = *children
+        .iter()
+        .find(|b| &b.0 == b"iloc")
+        .ok_or("HEIF without iloc box")?;
+    // Se lee solo dentro de la caja: un iloc corto no sigue leyendo la caja de al lado
+    let iloc = &data[start..end];
+    let version = *iloc.first().ok_or_else(truncated)?;
+    let sizes = iloc.get(4..6).ok_or_else(truncated)?;
+    let (offset_size, length_size) = ((sizes[0] >> 4) as usize, (sizes[0] & 0x0F) as usize);
+    let (base_size, index_size) = ((sizes[1] >> 4) as usize, (sizes[1] & 0x0F) as usize);
+    let wide = version >= 2;
+    let mut pos = 6;
+    let count = be_n(iloc, pos, if wide { 4 } else { 2 }).ok_or_else(truncated)?;
+    pos += if wide { 4 } else { 2 };
+
+    let mut ranges = Vec::new();
+    for _ in 0..count {
+        let id = be_n(iloc, pos, if wide { 4 } else { 2 }).ok_or_else(truncated)?;
+        pos += if wide { 4 } else { 2 };
+        let method = if version >= 1 {
+            pos += 2;
+            be16(iloc, pos - 2).ok_or_else(truncated)? & 0x0F
+        } else {
+            0
+        };
+        pos += 2; // data_reference_index
+        let base = be_n(iloc, pos, base_size).ok_or_else(truncated)?;
+        pos += base_size;
+        let extents = be16(iloc, pos).ok_or_else(truncated)?;
+        pos += 2;
+
+        for _ in 0..extents {
+            if version >= 1 {
+                pos += index_size;
+            }
+            let offset = be_n(iloc, pos, offset_size).ok_or_else(truncated)?;
+            pos += offset_size;
+            let length = be_n(iloc, pos, length_size).ok_or_else(truncated)?;
+            pos += length_size;
+
+            if !targets.contains(&id) {
+                continue;
+            }
+            let origin = match (method, idat) {
+                (0, _) => 0,
+                (1, Some(idat)) => idat,
+                _ => return Err("unsupported HEIF item construction".into()),
+            };
+            let from = [base, offset]
+                .iter()
+                .try_fold(origin, |acc, v| acc.checked_add(*v))
+                .ok_or_else(truncated)?;
+            ranges.push((from, length));
+        }
+    }
+
+    for (from, length) in ranges {
+        let to = from
+            .checked_add(length)
+            .filter(|to| *to <= data.len())
+            .ok_or_else(truncated)?;
+        data[from..to].fill(0);
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{ImageEncoder, RgbImage};
+
+    use super::*;
+
+    // TIFF big-endian con Make y un IFD GPS (latitud 40°26'N)
+    fn tiff_with_gps() -> Vec<u8> {
+        let mut t = b"MM\0\x2a\0\0\0\x08".to_vec();
+        t.extend_from_slice(&2u16.to_be_bytes());
+        t.extend_from_slice(&[0x01, 0x0F, 0, 2, 0, 0, 0, 4]); // Make, ASCII
+        t.extend_from_slice(b"Cam\0");
+        t.extend_from_slice(&[0x88, 0x25, 0, 4, 0, 0, 0, 1]); // GPSInfo, LONG
+        t.extend_from_slice(&38u32.to_be_bytes());
+        t.extend_from_slice(&0u32.to_be_bytes());
+
+        t.extend_from_slice(&2u16.to_be_bytes());
+        t.extend_from_slice(&[0, 1, 0, 2, 0, 0, 0, 2]); // GPSLatitudeRef
+        t.extend_from_slice(b"N\0\0\0");
+        t.extend_from_slice(&[0, 2, 0, 5, 0, 0, 0, 3]); // GPSLatitude, 3 RATIONAL
+        t.extend_from_slice(&68u32.to_be_bytes());
+        t.extend_from_slice(&0u32.to_be_bytes());
+        for v in [40u32, 26, 0] {
+            t.extend_from_slice(&v.to_be_bytes());
+            t.extend_from_slice(&1u32.to_be_bytes());
+        }
+        t
+    }
+
+    fn exif_of(data: &[u8]) -> Option<exif::Exif> {
+        exif::Reader::new()
+            .read_from_container(&mut Cursor::new(data))
+            .ok()
+    }
+
+    fn has_gps(data: &[u8]) -> bool {
+        exif_of(data).is_some_and(|e| e.get_field(Tag::GPSLatitude, In::PRIMARY).is_some())
+    }
+
+    fn contains(data: &[u8], needle: &[u8]) -> bool {
+        data.windows(needle.len()).any(|w| w == needle)
+    }
+
+    fn pixels() -> RgbImage {
+        RgbImage::from_fn(8, 8, |x, y| image::Rgb([x as u8 * 30, y as u8 * 30, 128]))
+    }
+
+    fn jpeg_with_gps() -> Vec<u8> {
+        let mut plain = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new(&mut plain)
+            .encode_image(&pixels())
+            .unwrap();
+
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend_from_slice(&tiff_with_gps());
+        let mut out = plain[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&app1);
+        out.extend_from_slice(&plain[2..]);
+        out
+    }
+
+    fn png_chunk(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = (body.len() as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(kind);
+        crc.update(body);
+        out.extend_from_slice(&crc.finalize().to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn strips_gps_from_jpeg() {
+        let data = jpeg_with_gps();
+        assert!(has_gps(&data));
+
+        let out = strip_jpeg(&data, None).unwrap();
+        assert!(!has_gps(&out));
+        assert!(!contains(&out, b"Exif\0\0"));
+        assert!(image::load_from_memory(&out).is_ok());
+    }
+
+    #[test]
+    fn keeps_only_orientation_in_jpeg() {
+        let out = strip_jpeg(&jpeg_with_gps(), Some(6)).unwrap();
+        let exif = exif_of(&out).unwrap();
+        assert_eq!(
+            exif.get_field(Tag::Orientation, In::PRIMARY)
+                .and_then(|f| f.value.get_uint(0)),
+            Some(6)
+        );
+        assert!(exif.get_field(Tag::GPSLatitude, In::PRIMARY).is_none());
+        assert!(exif.get_field(Tag::Make, In::PRIMARY).is_none());
+    }
+
+    #[test]
+    fn drops_images_appended_after_the_jpeg_eoi() {
+        let primary = jpeg_with_gps();
+        let mut data = primary.clone();
+        data.extend_from_slice(&jpeg_with_gps());
+
+        let out = strip_jpeg(&data, None).unwrap();
+        assert!(!contains(&out, b"Exif\0\0"));
+        assert!(!contains(&out, &tiff_with_gps()));
+        assert!(out.ends_with(&[0xFF, 0xD9]));
+        assert!(out.len() < primary.len());
+        assert!(image::load_from_memory(&out).is_ok());
+    }
+
+    #[test]
+    fn strips_gps_and_text_from_png() {
+        let mut plain = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut plain)
+            .write_image(&pixels(), 8, 8, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        let ihdr_end = 8 + 25;
+        let mut data = plain[..ihdr_end].to_vec();
+        data.extend_from_slice(&png_chunk(b"eXIf", &tiff_with_gps()));
+        data.extend_from_slice(&png_chunk(
+            b"iTXt",
+            b"XML:com.adobe.xmp\0\0\0\0\0<x:xmpmeta/>",
+        ));
+        data.extend_from_slice(&plain[ihdr_end..]);
+        assert!(has_gps(&data));
+
+        let out = strip_png(&data).unwrap();
+        assert!(!has_gps(&out));
+        assert!(!contains(&out, b"eXIf") && !contains(&out, b"xmpmeta"));
+        assert_eq!(out.len(), plain.len());
+        assert!(image::load_from_memory(&out).is_ok());
+    }
+
+    #[test]
+    fn strips_gps_from_webp_and_clears_vp8x_flags() {
+        let mut plain = Vec::new();
+        image::codecs::webp::WebPEncoder::new_lossless(&mut plain)
+            .write_image(&pixels(), 8, 8, image::ExtendedColorType::Rgb8)
+            .unwrap();
+
+        let mut vp8x = vec![0x08 | 0x04, 0, 0, 0]; // EXIF + XMP
+        vp8x.extend_from_slice(&[7, 0, 0, 7, 0, 0]); // lienzo 8x8
+        let tiff = tiff_with_gps();
+        let mut data = b"RIFF\0\0\0\0WEBP".to_vec();
+        for (fourcc, body) in [(&b"VP8X"[..], &vp8x[..]), (b"EXIF", &tiff[..])] {
+            data.extend_from_slice(fourcc);
+            data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            data.extend_from_slice(body);
+            if body.len() % 2 == 1 {
+                data.push(0);
+            }
+        }
+        data.extend_from_slice(&plain[12..]); // chunk VP8L
+        let size = (data.len() - 8) as u32;
+        data[4..8].copy_from_slice(&size.to_le_bytes());
+        assert!(has_gps(&data));
+
+        let out = strip_webp(&data).unwrap();
+        assert!(!has_gps(&out));
+        assert!(!contains(&out, b"EXIF"));
+        assert_eq!(out[12 + 8] & (0x08 | 0x04), 0);
+        assert_eq!(
+            u32::from_le_bytes(out[4..8].try_into().unwrap()) as usize,
+            out.len() - 8
+        );
+        assert!(image::load_from_memory(&out).is_ok());
+    }
+
+    fn isobmff_box(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    // ftyp + meta (hdlr, iinf, iloc) + mdat con el ítem Exif
+    fn heic(iinf_body: Option<Vec<u8>>) -> (Vec<u8>, Vec<u8>) {
+        let mut item = 0u32.to_be_bytes().to_vec(); // offset al encabezado TIFF
+        item.extend_from_slice(&tiff_with_gps());
+
+        let mut infe = vec![2, 0, 0, 0, 0, 1, 0, 0];
+        infe.extend_from_slice(b"Exif\0");
+        let mut iinf = vec![0, 0, 0, 0, 0, 1];
+        iinf.extend_from_slice(&isobmff_box(b"infe", &infe));
+        let iinf = iinf_body.unwrap_or(iinf);
+
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(b"pict");
+        hdlr.extend_from_slice(&[0; 13]);
+
+        let ftyp = isobmff_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        let build = |offset: u32| {
+            let mut iloc = vec![0, 0, 0, 0, 0x44, 0x00, 0, 1, 0, 1, 0, 0, 0, 1];
+            iloc.extend_from_slice(&offset.to_be_bytes());
+            iloc.extend_from_slice(&(item.len() as u32).to_be_bytes());
+
+            let mut meta = vec![0; 4];
+            meta.extend_from_slice(&isobmff_box(b"hdlr", &hdlr));
+            meta.extend_from_slice(&isobmff_box(b"iinf", &iinf));
+            meta.extend_from_slice(&isobmff_box(b"iloc", &iloc));
+
+            let mut out = ftyp.clone();
+            out.extend_from_slice(&isobmff_box(b"meta", &meta));
+            out.extend_from_slice(&isobmff_box(b"mdat", &item));
+            out
+        };
+        // El offset del ítem depende del largo de lo anterior al contenido de mdat
+        let offset = (build(0).len() - item.len()) as u32;
+        (build(offset), item)
+    }
+
+    #[test]
+    fn blanks_gps_in_heic() {
+        let (data, item) = heic(None);
+        assert!(contains(&data, &item));
+
+        let out = blank_heic(data.clone()).unwrap();
+        assert_eq!(out.len(), data.len());
+        assert!(!contains(&out, &tiff_with_gps()));
+        assert!(!has_gps(&out));
+    }
+
+    #[test]
+    fn malformed_heic_is_an_error_not_a_panic() {
+        // iinf vacío
+        let (data, _) = heic(Some(Vec::new()));
+        assert!(blank_heic(data).is_err());
+
+        // infe sin contenido
+        let mut iinf = vec![0, 0, 0, 0, 0, 1];
+        iinf.extend_from_slice(&isobmff_box(b"infe", &[]));
+        let (data, _) = heic(Some(iinf));
+        assert!(blank_heic(data).is_err());
+
+        // infe mime cortado antes del content type
+        let mut iinf = vec![0, 0, 0, 0, 0, 1];
+        iinf.extend_from_slice(&isobmff_box(b"infe", &[2, 0, 0, 0, 0, 1, 0, 0, b'm', b'i']));
+        let (data, _) = heic(Some(iinf));
+        assert!(blank_heic(data).is_err());
+
+        // Recortado en cualquier punto
+        let (data, _) = heic(None);
+        for len in 0..data.len() {
+            let _ = blank_heic(data[..len].to_vec());
+        }
+    }
+
+    #[test]
+    fn truncated_jpeg_png_and_webp_do_not_panic() {
+        let jpeg = jpeg_with_gps();
+        for len in 0..jpeg.len() {
+            let _ = strip_jpeg(&jpeg[..len], Some(6));
+        }
+        let mut png = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut png)
+            .write_image(&pixels(), 8, 8, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        for len in 0..png.len() {
+            let _ = strip_png(&png[..len]);
+        }
+        let mut webp = Vec::new();
+        image::codecs::webp::WebPEncoder::new_lossless(&mut webp)
+            .write_image(&pixels(), 8, 8, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        for len in 0..webp.len() {
+            let _ = strip_webp(&webp[..len]);
+        }
+    }
+}
@@ -1,6 +1,25 @@
+use std::fs;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::{
+    pkcs1::DecodeRsaPublicKey, pkcs8::DecodePublicKey, traits::PublicKeyParts, RsaPublicKey,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{config::AppConfig, models::role::Role};
+
+// Tolerancia para relojes desfasados entre servicios
+const LEEWAY_SECS: i64 = 60;
+
+// Prefijo DER (SubjectPublicKeyInfo) de una llave pública Ed25519
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
@@ -8,29 +27,246 @@ pub struct Claims {
     pub email: String,
     pub role: String,
     pub exp: usize,
+    pub iat: usize,
+    pub iss: String,
+    pub aud: String,
+    pub jti: String,
+    // Admin que está suplantando al usuario (RFC 8693 "act")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<String>,
+}
+
+struct VerifyKey {
+    kid: String,
+    alg: Algorithm,
+    key: DecodingKey,
+    jwk: Option<Value>, // None para HS256 (nunca se publica)
+}
+
+pub struct JwtKeys {
+    alg: Algorithm,
+    kid: String,
+    signing: EncodingKey,
+    verifying: Vec<VerifyKey>,
+    issuer: String,
+    audience: String,
+    exp_minutes: i64,
+}
+
+impl JwtKeys {
+    pub fn from_config(cfg: &AppConfig) -> Result<Self, String> {
+        let mut verifying = Vec::new();
+
+        let (alg, signing) = match cfg.jwt_algorithm.as_str() {
+            "HS256" => {
+                let secret = cfg
+                    .jwt_secret
+                    .as_deref()
+                    .ok_or("JWT_SECRET is required for HS256")?;
+                verifying.push(VerifyKey {
+                    kid: cfg.jwt_key_id.clone(),
+                    alg: Algorithm::HS256,
+                    key: DecodingKey::from_secret(secret.as_bytes()),
+                    jwk: None,
+                });
+                (
+                    Algorithm::HS256,
+                    EncodingKey::from_secret(secret.as_bytes()),
+                )
+            }
+            "RS256" | "EdDSA" => {
+                let private_path = cfg
+                    .jwt_private_key_file
+                    .as_deref()
+                    .ok_or("JWT_PRIVATE_KEY_FILE is required for asymmetric JWT")?;
+                let public_path = cfg
+                    .jwt_public_key_file
+                    .as_deref()
+                    .ok_or("JWT_PUBLIC_KEY_FILE is required for asymmetric JWT")?;
+
+                let private_pem = fs::read(private_path)
+                    .map_err(|e| format!("cannot read {}: {}", private_path, e))?;
+
+                let (alg, signing) = if cfg.jwt_algorithm == "RS256" {
+                    let key = EncodingKey::from_rsa_pem(&private_pem).map_err(|e| e.to_string())?;
+                    (Algorithm::RS256, key)
+                } else {
+                    let key = EncodingKey::from_ed_pem(&private_pem).map_err(|e| e.to_string())?;
+                    (Algorithm::EdDSA, key)
+                };
+
+                let current = load_public_key(&cfg.jwt_key_id, public_path)?;
+                if current.alg != alg {
+                    return Err("JWT_PUBLIC_KEY_FILE does not match JWT_ALGORITHM".into());
+                }
+                verifying.push(current);
+                (alg, signing)
+            }
+            other => return Err(format!("unsupported JWT_ALGORITHM: {}", other)),
+        };
+
+        for (kid, path) in &cfg.jwt_previous_public_keys {
+            if verifying.iter().any(|k| &k.kid == kid) {
+                return Err(format!("duplicate JWT key id: {}", kid));
+            }
+            verifying.push(load_public_key(kid, path)?);
+        }
+
+        Ok(Self {
+            alg,
+            kid: cfg.jwt_key_id.clone(),
+            signing,
+            verifying,
+            issuer: cfg.jwt_issuer.clone(),
+            audience: cfg.jwt_audience.clone(),
+            exp_minutes: cfg.jwt_exp_minutes,
+        })
+    }
+
+    // Documento JWKS con las llaves públicas vigentes (vacío con HS256)
+    pub fn jwks(&self) -> Value {
+        let keys: Vec<&Value> = self
+            .verifying
+            .iter()
+            .filter_map(|k| k.jwk.as_ref())
+            .collect();
+        json!({ "keys": keys })
+    }
 }
 
-pub fn sign_jwt(user_id: &str, email: &str, role: &str, secret: &str, exp_minutes: i64) -> Result<String, String> {
-    let exp = (Utc::now() + Duration::minutes(exp_minutes)).timestamp() as usize;
+fn load_public_key(kid: &str, path: &str) -> Result<VerifyKey, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
 
-    let claims = Claims {
+    // RSA (SPKI "PUBLIC KEY" o PKCS#1 "RSA PUBLIC KEY")
+    let rsa =
+        RsaPublicKey::from_public_key_pem(&raw).or_else(|_| RsaPublicKey::from_pkcs1_pem(&raw));
+    if let Ok(rsa) = rsa {
+        let n = rsa.n().to_bytes_be();
+        let e = rsa.e().to_bytes_be();
+        let key = DecodingKey::from_rsa_raw_components(&n, &e);
+        return Ok(VerifyKey {
+            kid: kid.to_string(),
+            alg: Algorithm::RS256,
+            key,
+            jwk: Some(json!({
+                "kty": "RSA",
+                "use": "sig",
+                "alg": "RS256",
+                "kid": kid,
+                "n": URL_SAFE_NO_PAD.encode(n),
+                "e": URL_SAFE_NO_PAD.encode(e),
+            })),
+        });
+    }
+
+    // Ed25519 (SPKI)
+    let parsed = pem::parse(&raw).map_err(|e| format!("invalid PEM {}: {}", path, e))?;
+    let der = parsed.contents();
+    if der.len() == 44 && der[..12] == ED25519_SPKI_PREFIX {
+        let x = &der[12..];
+        return Ok(VerifyKey {
+            kid: kid.to_string(),
+            alg: Algorithm::EdDSA,
+            key: DecodingKey::from_ed_der(x),
+            jwk: Some(json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "use": "sig",
+                "alg": "EdDSA",
+                "kid": kid,
+                "x": URL_SAFE_NO_PAD.encode(x),
+            })),
+        });
+    }
+
+    Err(format!("unsupported public key type in {}", path))
+}
+
+pub fn sign_jwt(keys: &JwtKeys, user_id: &str, email: &str, role: Role) -> Result<String, String> {
+    let claims = build_claims(keys, user_id, email, role, keys.exp_minutes, None);
+    encode_claims(keys, &claims)
+}
+
+// Token corto a nombre de `user_id` emitido por el admin `actor_id`
+pub fn sign_impersonation_jwt(
+    keys: &JwtKeys,
+    user_id: &str,
+    email: &str,
+    role: Role,
+    actor_id: &str,
+    minutes: i64,
+) -> Result<(String, Claims), String> {
+    let claims = build_claims(
+        keys,
+        user_id,
+        email,
+        role,
+        minutes,
+        Some(actor_id.to_string()),
+    );
+    let token = encode_claims(keys, &claims)?;
+    Ok((token, claims))
+}
+
+fn build_claims(
+    keys: &JwtKeys,
+    user_id: &str,
+    email: &str,
+    role: Role,
+    minutes: i64,
+    act: Option<String>,
+) -> Claims {
+    let now = Utc::now();
+
+    Claims {
         sub: user_id.to_string(),
         email: email.to_string(),
-        role: role.to_string(),
-        exp,
-    };
+        role: role.as_str().to_string(),
+        exp: (now + Duration::minutes(minutes)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+        iss: keys.issuer.clone(),
+        aud: keys.audience.clone(),
+        jti: uuid::Uuid::new_v4().to_string(),
+        act,
+    }
+}
 
-    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes()))
-        .map_err(|e| e.to_string())
+fn encode_claims(keys: &JwtKeys, claims: &Claims) -> Result<String, String> {
+    let mut header = Header::new(keys.alg);
+    header.kid = Some(keys.kid.clone());
+
+    encode(&header, claims, &keys.signing).map_err(|e| e.to_string())
 }
 
-pub fn verify_jwt(token: &str, secret: &str) -> Result<Claims, String> {
-    let data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|e| e.to_string())?;
+pub fn verify_jwt(keys: &JwtKeys, token: &str) -> Result<Claims, String> {
+    let header = decode_header(token).map_err(|e| e.to_string())?;
+
+    // Sin kid solo se acepta la llave actual (tokens emitidos antes de la rotación)
+    let key = match header.kid.as_deref() {
+        Some(kid) => keys.verifying.iter().find(|k| k.kid == kid),
+        None => keys.verifying.first(),
+    }
+    .ok_or_else(|| "unknown key id".to_string())?;
+
+    if header.alg != key.alg {
+        return Err("algorithm mismatch".into());
+    }
+
+    let mut validation = Validation::new(key.alg);
+    validation.leeway = LEEWAY_SECS as u64;
+    validation.set_issuer(&[keys.issuer.as_str()]);
+    validation.set_audience(&[keys.audience.as_str()]);
+    validation.set_required_spec_claims(&["exp", "iat", "iss", "aud", "sub", "jti"]);
+
+    let data = decode::<Claims>(token, &key.key, &validation).map_err(|e| e.to_string())?;
+    let claims = data.claims;
+
+    if claims.iat as i64 > Utc::now().timestamp() + LEEWAY_SECS {
+        return Err("token issued in the future".into());
+    }
+    if uuid::Uuid::parse_str(&claims.jti).is_err() {
+        return Err("invalid jti".into());
+    }
 
-    Ok(data.claims)
+    Ok(claims)
 }
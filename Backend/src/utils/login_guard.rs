@@ -0,0 +1,471 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use bson::doc;
+use chrono::{DateTime, Duration, Utc};
+use mongodb::{
+    options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument},
+    Database, IndexModel,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::LoginGuardConfig,
+    db::bson_time,
+    errors::ApiError,
+    utils::{api_key::hash_key, random::random_token},
+};
+
+// Registros sin fallos recientes se olvidan después de este tiempo
+const RECORD_TTL_HOURS: i64 = 24;
+const MEMORY_PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttemptRecord {
+    #[serde(rename = "_id")]
+    pub key: String, // "ip:<addr>" | "email:<email>"
+
+    pub failures: u32,
+    // Fecha BSON real para el índice TTL
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub last_failure_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub unlock_token_hash: Option<String>,
+}
+
+// In-memory para una sola instancia; Mongo para compartir el estado entre varias
+pub enum AttemptStore {
+    Memory(Mutex<HashMap<String, AttemptRecord>>),
+    Mongo(mongodb::Collection<AttemptRecord>),
+}
+
+impl AttemptStore {
+    async fn get(&self, key: &str) -> Result<Option<AttemptRecord>, ApiError> {
+        match self {
+            AttemptStore::Memory(map) => Ok(map
+                .lock()
+                .map_err(|_| ApiError::Internal)?
+                .get(key)
+                .cloned()),
+            AttemptStore::Mongo(col) => {
+                col.find_one(doc! { "_id": key }, None).await.map_err(|e| {
+                    eprintln!("Mongo find_one error (login_attempts): {:?}", e);
+                    ApiError::Internal
+                })
+            }
+        }
+    }
+
+    // Suma un intento en una sola operación y devuelve el registro como estaba antes
+    async fn incr(&self, key: &str, now: DateTime<Utc>) -> Result<Option<AttemptRecord>, ApiError> {
+        match self {
+            AttemptStore::Memory(map) => {
+                let mut map = map.lock().map_err(|_| ApiError::Internal)?;
+                if map.len() >= MEMORY_PRUNE_THRESHOLD {
+                    let cutoff = now - Duration::hours(RECORD_TTL_HOURS);
+                    map.retain(|_, r| r.last_failure_at > cutoff);
+                }
+                let before = map.get(key).cloned();
+                let record = map.entry(key.to_string()).or_insert_with(|| AttemptRecord {
+                    key: key.to_string(),
+                    failures: 0,
+                    last_failure_at: now,
+                    locked_until: None,
+                    unlock_token_hash: None,
+                });
+                record.failures += 1;
+                record.last_failure_at = now;
+                Ok(before)
+            }
+            AttemptStore::Mongo(col) => {
+                let options = FindOneAndUpdateOptions::builder()
+                    .upsert(true)
+                    .return_document(ReturnDocument::Before)
+                    .build();
+                col.find_one_and_update(
+                    doc! { "_id": key },
+                    doc! {
+                        "$inc": { "failures": 1 },
+                        "$set": { "last_failure_at": bson::DateTime::from_chrono(now) },
+                        "$setOnInsert": { "locked_until": null, "unlock_token_hash": null },
+                    },
+                    options,
+                )
+                .await
+                .map_err(|e| {
+                    eprintln!("Mongo find_one_and_update error (login_attempts): {:?}", e);
+                    ApiError::Internal
+                })
+            }
+        }
+    }
+
+    // Descuenta un intento que resultó válido
+    async fn decr(&self, key: &str) -> Result<(), ApiError> {
+        match self {
+            AttemptStore::Memory(map) => {
+                if let Some(r) = map.lock().map_err(|_| ApiError::Internal)?.get_mut(key) {
+                    r.failures = r.failures.saturating_sub(1);
+                }
+                Ok(())
+            }
+            AttemptStore::Mongo(col) => {
+                col.update_one(
+                    doc! { "_id": key, "failures": { "$gt": 0 } },
+                    doc! { "$inc": { "failures": -1 } },
+                    None,
+                )
+                .await
+                .map_err(|e| {
+                    eprintln!("Mongo update_one error (login_attempts): {:?}", e);
+                    ApiError::Internal
+                })?;
+                Ok(())
+            }
+        }
+    }
+
+    // Un bloqueo vencido empieza de cero, contando el intento actual. El filtro por la
+    // fecha del bloqueo evita reiniciar dos veces
+    async fn reset_expired(&self, key: &str, until: DateTime<Utc>) -> Result<(), ApiError> {
+        match self {
+            AttemptStore::Memory(map) => {
+                let mut map = map.lock().map_err(|_| ApiError::Internal)?;
+                if let Some(r) = map.get_mut(key).filter(|r| r.locked_until == Some(until)) {
+                    r.failures = 1;
+                    r.locked_until = None;
+                    r.unlock_token_hash = None;
+                }
+                Ok(())
+            }
+            AttemptStore::Mongo(col) => {
+                col.update_one(
+                    doc! { "_id": key, "locked_until": bson_time(until) },
+                    doc! { "$set": { "failures": 1, "locked_until": null, "unlock_token_hash": null } },
+                    None,
+                )
+                .await
+                .map_err(|e| {
+                    eprintln!("Mongo update_one error (login_attempts): {:?}", e);
+                    ApiError::Internal
+                })?;
+                Ok(())
+            }
+        }
+    }
+
+    // Bloquea solo si no lo estaba: con fallos en paralelo sale un único token de desbloqueo
+    async fn lock(
+        &self,
+        key: &str,
+        until: DateTime<Utc>,
+        token_hash: &str,
+    ) -> Result<bool, ApiError> {
+        match self {
+            AttemptStore::Memory(map) => {
+                let mut map = map.lock().map_err(|_| ApiError::Internal)?;
+                match map.get_mut(key).filter(|r| r.locked_until.is_none()) {
+                    Some(r) => {
+                        r.locked_until = Some(until);
+                        r.unlock_token_hash = Some(token_hash.to_string());
+                        Ok(true)
+                    }
+                    None => Ok(false),
+                }
+            }
+            AttemptStore::Mongo(col) => {
+                let res = col
+                    .update_one(
+                        doc! { "_id": key, "locked_until": null },
+                        doc! { "$set": { "locked_until": bson_time(until), "unlock_token_hash": token_hash } },
+                        None,
+                    )
+                    .await
+                    .map_err(|e| {
+                        eprintln!("Mongo update_one error (login_attempts): {:?}", e);
+                        ApiError::Internal
+                    })?;
+                Ok(res.modified_count == 1)
+            }
+        }
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), ApiError> {
+        match self {
+            AttemptStore::Memory(map) => {
+                map.lock().map_err(|_| ApiError::Internal)?.remove(key);
+                Ok(())
+            }
+            AttemptStore::Mongo(col) => {
+                col.delete_one(doc! { "_id": key }, None)
+                    .await
+                    .map_err(|e| {
+                        eprintln!("Mongo delete_one error (login_attempts): {:?}", e);
+                        ApiError::Internal
+                    })?;
+                Ok(())
+            }
+        }
+    }
+
+    async fn find_by_unlock_hash(&self, hash: &str) -> Result<Option<AttemptRecord>, ApiError> {
+        match self {
+            AttemptStore::Memory(map) => Ok(map
+                .lock()
+                .map_err(|_| ApiError::Internal)?
+                .values()
+                .find(|r| r.unlock_token_hash.as_deref() == Some(hash))
+                .cloned()),
+            AttemptStore::Mongo(col) => col
+                .find_one(doc! { "unlock_token_hash": hash }, None)
+                .await
+                .map_err(|e| {
+                    eprintln!("Mongo find_one error (login_attempts): {:?}", e);
+                    ApiError::Internal
+                }),
+        }
+    }
+}
+
+pub struct LoginGuard {
+    cfg: LoginGuardConfig,
+    store: AttemptStore,
+}
+
+impl LoginGuard {
+    pub fn new(cfg: &LoginGuardConfig, db: &Database) -> Self {
+        let store = match cfg.store.as_str() {
+            "mongo" => AttemptStore::Mongo(db.collection::<AttemptRecord>("login_attempts")),
+            _ => AttemptStore::Memory(Mutex::new(HashMap::new())),
+        };
+        Self {
+            cfg: cfg.clone(),
+            store,
+        }
+    }
+
+    pub async fn ensure_indexes(&self) {
+        let AttemptStore::Mongo(col) = &self.store else {
+            return;
+        };
+
+        let options = IndexOptions::builder()
+            .expire_after(Some(std::time::Duration::from_secs(
+                RECORD_TTL_HOURS as u64 * 3600,
+            )))
+            .name(Some("ttl_last_failure_at".to_string()))
+            .build();
+
+        let model = IndexModel::builder()
+            .keys(doc! { "last_failure_at": 1 })
+            .options(options)
+            .build();
+
+        if let Err(e) = col.create_index(model, None).await {
+            eprintln!("Mongo create_index error (ttl_last_failure_at): {:?}", e);
+        }
+    }
+
+    // Cuenta el intento antes de verificar la contraseña, así los intentos en paralelo no se
+    // saltan la espera. Rechaza con 429 si la IP o el email estaban bloqueados o esperando.
+    pub async fn attempt(&self, ip: &str, email: &str) -> Result<(), ApiError> {
+        let now = Utc::now();
+        let mut wait = Duration::zero();
+
+        for key in [ip_key(ip), email_key(email)] {
+            let Some(before) = self.store.incr(&key, now).await? else {
+                continue;
+            };
+
+            if let Some(until) = before.locked_until {
+                if until <= now {
+                    self.store.reset_expired(&key, until).await?;
+                    continue;
+                }
+                wait = wait.max(until - now);
+            }
+            let next_allowed = before.last_failure_at + self.backoff(before.failures);
+            wait = wait.max(next_allowed - now);
+        }
+
+        if wait > Duration::zero() {
+            // Redondeo hacia arriba: medio segundo de espera no es cero
+            let secs = (wait.num_milliseconds() + 999) / 1000;
+            return Err(ApiError::TooManyRequests(secs as u64));
+        }
+        Ok(())
+    }
+
+    // El intento ya se contó en `attempt`; aquí solo se decide el bloqueo.
+    // Regresa Some(unlock_token) cuando este fallo provoca el bloqueo de la cuenta
+    pub async fn record_failure(&self, ip: &str, email: &str) -> Result<Option<String>, ApiError> {
+        self.lock_if_exceeded(ip_key(ip), self.cfg.ip_max_failures)
+            .await?;
+        self.lock_if_exceeded(email_key(email), self.cfg.max_failures)
+            .await
+    }
+
+    // Intento válido que no debe contar como fallo, sin borrar los fallos anteriores
+    pub async fn release(&self, ip: &str, email: &str) -> Result<(), ApiError> {
+        self.store.decr(&ip_key(ip)).await?;
+        self.store.decr(&email_key(email)).await
+    }
+
+    // Solo se limpia la cuenta: el contador de la IP vence en su ventana normal, si no
+    // entrar con una cuenta propia entre intentos lo reiniciaría
+    pub async fn record_success(&self, email: &str) -> Result<(), ApiError> {
+        self.store.remove(&email_key(email)).await
+    }
+
+    // Desbloqueo desde el enlace del correo
+    pub async fn unlock_with_token(&self, token: &str) -> Result<bool, ApiError> {
+        match self.store.find_by_unlock_hash(&hash_key(token)).await? {
+            Some(record) => {
+                self.store.remove(&record.key).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Desbloqueo manual (admin)
+    pub async fn unlock_email(&self, email: &str) -> Result<(), ApiError> {
+        self.store.remove(&email_key(email)).await
+    }
+
+    async fn lock_if_exceeded(
+        &self,
+        key: String,
+        max_failures: u32,
+    ) -> Result<Option<String>, ApiError> {
+        let Some(record) = self.store.get(&key).await? else {
+            return Ok(None);
+        };
+        if record.failures < max_failures || record.locked_until.is_some() {
+            return Ok(None);
+        }
+
+        let until = Utc::now() + Duration::minutes(self.cfg.lockout_minutes);
+        let token = random_token(32);
+        let locked = self.store.lock(&key, until, &hash_key(&token)).await?;
+        Ok(locked.then_some(token))
+    }
+
+    // Espera exponencial: base * 2^(fallos-1), con tope
+    fn backoff(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return Duration::zero();
+        }
+        let exp = (failures - 1).min(20);
+        let secs = self
+            .cfg
+            .backoff_base_secs
+            .saturating_mul(1i64 << exp)
+            .min(self.cfg.backoff_max_secs);
+        Duration::seconds(secs)
+    }
+}
+
+fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+fn email_key(email: &str) -> String {
+    format!("email:{}", email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> LoginGuard {
+        LoginGuard {
+            cfg: LoginGuardConfig {
+                store: "memory".into(),
+                max_failures: 3,
+                ip_max_failures: 100,
+                lockout_minutes: 15,
+                backoff_base_secs: 1,
+                backoff_max_secs: 300,
+            },
+            store: AttemptStore::Memory(Mutex::new(HashMap::new())),
+        }
+    }
+
+    #[actix_web::test]
+    async fn concurrent_attempts_do_not_skip_backoff() {
+        let guard = guard();
+        // Los dos intentos llegan antes de que el primero se marque como fallido
+        assert!(guard.attempt("1.2.3.4", "a@x.com").await.is_ok());
+        assert!(matches!(
+            guard.attempt("1.2.3.4", "a@x.com").await,
+            Err(ApiError::TooManyRequests(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn locks_once_after_max_failures() {
+        let guard = guard();
+        let key = email_key("a@x.com");
+        guard.store.incr(&key, Utc::now()).await.unwrap();
+        guard.store.incr(&key, Utc::now()).await.unwrap();
+        assert!(guard
+            .record_failure("1.2.3.4", "a@x.com")
+            .await
+            .unwrap()
+            .is_none());
+
+        guard.store.incr(&key, Utc::now()).await.unwrap();
+        let token = guard.record_failure("1.2.3.4", "a@x.com").await.unwrap();
+        assert!(token.is_some());
+        // Un segundo fallo en paralelo no genera otro token
+        assert!(guard
+            .record_failure("1.2.3.4", "a@x.com")
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(guard.unlock_with_token(&token.unwrap()).await.unwrap());
+        assert!(guard.store.get(&key).await.unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn release_discounts_a_valid_attempt() {
+        let guard = guard();
+        guard.attempt("1.2.3.4", "drop:1").await.unwrap();
+        guard.release("1.2.3.4", "drop:1").await.unwrap();
+        let record = guard.store.get(&ip_key("1.2.3.4")).await.unwrap().unwrap();
+        assert_eq!(record.failures, 0);
+        assert!(guard.attempt("1.2.3.4", "drop:1").await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn success_keeps_the_ip_count() {
+        let guard = guard();
+        guard.attempt("1.2.3.4", "own@x.com").await.unwrap();
+        guard.record_success("own@x.com").await.unwrap();
+
+        let record = guard.store.get(&ip_key("1.2.3.4")).await.unwrap().unwrap();
+        assert_eq!(record.failures, 1);
+        assert!(guard
+            .store
+            .get(&email_key("own@x.com"))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[actix_web::test]
+    async fn expired_lock_starts_over() {
+        let guard = guard();
+        let key = email_key("a@x.com");
+        let past = Utc::now() - Duration::hours(1);
+        for _ in 0..3 {
+            guard.store.incr(&key, past).await.unwrap();
+        }
+        guard.store.lock(&key, past, "hash").await.unwrap();
+
+        assert!(guard.attempt("1.2.3.4", "a@x.com").await.is_ok());
+        let record = guard.store.get(&key).await.unwrap().unwrap();
+        assert_eq!((record.failures, record.locked_until), (1, None));
+    }
+}
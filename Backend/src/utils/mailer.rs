@@ -0,0 +1,56 @@
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::config::AppConfig;
+
+pub struct Mailer {
+    // None = sin SMTP configurado: los correos no se envían (modo dev)
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn from_config(cfg: &AppConfig) -> Result<Self, String> {
+        let from: Mailbox = cfg
+            .mail_from
+            .parse()
+            .map_err(|e| format!("MAIL_FROM: {}", e))?;
+
+        let transport = match &cfg.smtp {
+            Some(smtp) => {
+                let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+                    .map_err(|e| e.to_string())?
+                    .port(smtp.port);
+                if let (Some(user), Some(pass)) = (&smtp.username, &smtp.password) {
+                    builder = builder.credentials(Credentials::new(user.clone(), pass.clone()));
+                }
+                Some(builder.build())
+            }
+            None => None,
+        };
+
+        Ok(Self { transport, from })
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String> {
+        let Some(transport) = &self.transport else {
+            // El cuerpo lleva tokens de desbloqueo, reseteo e invitación: nunca a los logs
+            println!("[mail] SMTP not configured, mail not sent: {}", subject);
+            return Ok(());
+        };
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to
+                .parse()
+                .map_err(|e| format!("invalid recipient: {}", e))?)
+            .subject(subject)
+            .body(body.to_string())
+            .map_err(|e| e.to_string())?;
+
+        transport.send(message).await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
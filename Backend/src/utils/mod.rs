@@ -1,2 +1,21 @@
+pub mod api_key;
+pub mod audit;
+pub mod avatar;
+pub mod disposition;
+pub mod events;
+pub mod extract;
+pub mod image_meta;
 pub mod jwt;
+pub mod login_guard;
+pub mod mailer;
+pub mod notify;
+pub mod ocr;
+pub mod office;
+pub mod oidc;
 pub mod password;
+pub mod random;
+pub mod search_index;
+pub mod storage;
+pub mod text_extract;
+pub mod webhooks;
+pub mod zip;
@@ -0,0 +1,156 @@
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures::StreamExt;
+use mongodb::{options::IndexOptions, IndexModel};
+
+use crate::{
+    db::AppState,
+    models::notification::{NotificationDoc, NotificationKind, NotificationOut},
+    routes::auth::users_collection,
+    utils::events::LiveEventKind,
+};
+
+pub(crate) fn notifications_collection(state: &AppState) -> mongodb::Collection<NotificationDoc> {
+    state.db.collection::<NotificationDoc>("notifications")
+}
+
+pub async fn ensure_indexes(state: &AppState) {
+    let col = notifications_collection(state);
+
+    for (name, keys) in [
+        (
+            "notifications_inbox",
+            doc! { "user_id": 1, "created_at": -1 },
+        ),
+        ("notifications_unread", doc! { "user_id": 1, "read_at": 1 }),
+    ] {
+        let options = IndexOptions::builder().name(Some(name.to_string())).build();
+        let model = IndexModel::builder().keys(keys).options(options).build();
+
+        if let Err(e) = col.create_index(model, None).await {
+            eprintln!("Mongo create_index error ({}): {:?}", name, e);
+        }
+    }
+}
+
+pub struct Notice {
+    kind: NotificationKind,
+    title: String,
+    body: String,
+    link: Option<String>,
+    data: Option<Document>,
+}
+
+impl Notice {
+    pub fn new(kind: NotificationKind, title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            kind,
+            title: title.into(),
+            body: body.into(),
+            link: None,
+            data: None,
+        }
+    }
+
+    pub fn link(mut self, link: impl Into<String>) -> Self {
+        self.link = Some(link.into());
+        self
+    }
+
+    pub fn data(mut self, data: Document) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+
+// Guarda la notificación en la bandeja de cada destinatario, la empuja por SSE y
+// encola el correo para quien lo tenga activado. Nunca falla la petición que la originó.
+pub async fn send(state: &AppState, recipients: Vec<String>, notice: Notice) {
+    if recipients.is_empty() {
+        return;
+    }
+
+    let now = Utc::now();
+    let docs: Vec<NotificationDoc> = recipients
+        .iter()
+        .map(|user_id| NotificationDoc {
+            id: ObjectId::new(),
+            user_id: user_id.clone(),
+            kind: notice.kind,
+            title: notice.title.clone(),
+            body: notice.body.clone(),
+            link: notice.link.clone(),
+            data: notice.data.clone(),
+            read_at: None,
+            created_at: now,
+        })
+        .collect();
+
+    if let Err(e) = notifications_collection(state)
+        .insert_many(&docs, None)
+        .await
+    {
+        eprintln!("Mongo insert_many error (notify): {:?}", e);
+        return;
+    }
+
+    for n in &docs {
+        let out = serde_json::to_value(NotificationOut::from(n.clone())).unwrap_or_default();
+        state.events.publish(
+            vec![n.user_id.clone()],
+            LiveEventKind::NotificationCreated,
+            out,
+        );
+    }
+
+    // El SMTP puede tardar: el correo sale en segundo plano sin retener la petición
+    let state = state.clone();
+    actix_web::rt::spawn(async move {
+        email(&state, &recipients, &notice).await;
+    });
+}
+
+async fn email(state: &AppState, recipients: &[String], notice: &Notice) {
+    let ids: Vec<ObjectId> = recipients
+        .iter()
+        .filter_map(|id| ObjectId::parse_str(id).ok())
+        .collect();
+
+    let mut cursor = match users_collection(state)
+        .find(
+            doc! { "_id": { "$in": ids }, "disabled": { "$ne": true } },
+            None,
+        )
+        .await
+    {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            eprintln!("Mongo find error (notify email): {:?}", e);
+            return;
+        }
+    };
+
+    while let Some(Ok(user)) = cursor.next().await {
+        if !user.notification_prefs.wants_email(notice.kind) {
+            continue;
+        }
+
+        let body = format!("Hola {},\n\n{}\n", user.name, notice.body);
+        if let Err(e) = state.mailer.send(&user.email, &notice.title, &body).await {
+            eprintln!("Mail error (notify): {}", e);
+        }
+    }
+}
+
+// Al borrar una cuenta se va su bandeja
+pub async fn remove_all_of(state: &AppState, user_id: &str) {
+    if let Err(e) = notifications_collection(state)
+        .delete_many(doc! { "user_id": user_id }, None)
+        .await
+    {
+        eprintln!(
+            "Mongo delete_many error (notifications remove_all_of): {:?}",
+            e
+        );
+    }
+}
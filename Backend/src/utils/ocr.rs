@@ -0,0 +1,88 @@
+// OCR con el binario local de Tesseract. Las imágenes van directo; los PDF sin texto se
+// rasterizan antes con pdftoppm (poppler), una imagen por página.
+use std::{path::Path, process::Stdio, time::Duration};
+
+use bson::oid::ObjectId;
+use tokio::process::Command;
+
+use crate::config::OcrConfig;
+
+// Ejecuta el comando y devuelve su stdout; al vencer el plazo el proceso se mata
+async fn run(cfg: &OcrConfig, cmd: &mut Command) -> Result<Vec<u8>, String> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let output = tokio::time::timeout(Duration::from_secs(cfg.timeout_secs), cmd.output())
+        .await
+        .map_err(|_| "OCR timed out".to_string())?
+        .map_err(|e| format!("could not start OCR tool: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "OCR tool failed ({}): {}",
+            output.status,
+            stderr.trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+pub async fn recognize_image(cfg: &OcrConfig, path: &str) -> Result<String, String> {
+    let stdout = run(
+        cfg,
+        Command::new(&cfg.tesseract_bin)
+            .arg(path)
+            .arg("stdout")
+            .arg("-l")
+            .arg(&cfg.languages),
+    )
+    .await?;
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+pub async fn recognize_pdf(cfg: &OcrConfig, path: &str) -> Result<String, String> {
+    let dir = std::env::temp_dir().join(format!("pcosew_ocr_{}", ObjectId::new().to_hex()));
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let result = recognize_pages(cfg, path, &dir).await;
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    result
+}
+
+async fn recognize_pages(cfg: &OcrConfig, path: &str, dir: &Path) -> Result<String, String> {
+    run(
+        cfg,
+        Command::new(&cfg.pdftoppm_bin)
+            .arg("-r")
+            .arg(cfg.dpi.to_string())
+            .arg("-l")
+            .arg(cfg.max_pdf_pages.to_string())
+            .arg("-png")
+            .arg(path)
+            .arg(dir.join("page")),
+    )
+    .await?;
+
+    // page-01.png, page-02.png...: el relleno de ceros mantiene el orden alfabético
+    let mut pages = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await.map_err(|e| e.to_string())?;
+    while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+        pages.push(entry.path());
+    }
+    pages.sort();
+
+    let mut text = String::new();
+    for page in pages {
+        let page_text = recognize_image(cfg, &page.to_string_lossy()).await?;
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&page_text);
+    }
+    Ok(text)
+}
@@ -0,0 +1,335 @@
+// Vista previa de documentos de oficina: LibreOffice headless los convierte a PDF y el PDF
+// queda guardado junto al original. Se convierte la primera vez que alguien abre /view.
+use std::{path::Path, process::Stdio, sync::Arc, time::Duration};
+
+use bson::{doc, oid::ObjectId};
+use chrono::Utc;
+use futures::StreamExt;
+use mongodb::{
+    options::{FindOneAndUpdateOptions, IndexOptions},
+    IndexModel,
+};
+use tokio::{process::Command, sync::Semaphore};
+
+use crate::{
+    config::OfficePreviewConfig,
+    db::{bson_time, AppState},
+    errors::ApiError,
+    models::{
+        file::FileDoc,
+        preview::{PreviewDoc, PreviewStatus},
+    },
+    utils::storage,
+};
+
+// Una conversión que truena dos veces no va a funcionar a la tercera
+const MAX_ATTEMPTS: u32 = 2;
+const RETRY_DELAY_SECS: i64 = 60;
+const MAX_ERROR_LEN: usize = 500;
+
+const OFFICE_EXTENSIONS: &[&str] = &[
+    "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp", "rtf",
+];
+
+pub(crate) fn previews_collection(state: &AppState) -> mongodb::Collection<PreviewDoc> {
+    state.db.collection::<PreviewDoc>("file_previews")
+}
+
+pub async fn ensure_indexes(state: &AppState) {
+    let options = IndexOptions::builder()
+        .name(Some("file_previews_queue".to_string()))
+        .build();
+    let model = IndexModel::builder()
+        .keys(doc! { "status": 1, "next_attempt_at": 1 })
+        .options(options)
+        .build();
+
+    if let Err(e) = previews_collection(state).create_index(model, None).await {
+        eprintln!("Mongo create_index error (file_previews_queue): {:?}", e);
+    }
+}
+
+pub fn is_office(mime: &str, name: &str) -> bool {
+    let ext = name
+        .rsplit_once('.')
+        .map(|(_, e)| e.to_lowercase())
+        .unwrap_or_default();
+    OFFICE_EXTENSIONS.contains(&ext.as_str())
+        || mime.starts_with("application/vnd.openxmlformats-officedocument.")
+        || mime.starts_with("application/vnd.oasis.opendocument.")
+        || matches!(
+            mime,
+            "application/msword" | "application/vnd.ms-excel" | "application/vnd.ms-powerpoint"
+        )
+}
+
+// Estado de la vista previa del archivo; la primera vez la encola. None = no se convierte
+// (demasiado grande).
+pub async fn request(
+    state: &AppState,
+    cfg: &OfficePreviewConfig,
+    file: &FileDoc,
+) -> Result<Option<PreviewDoc>, ApiError> {
+    let col = previews_collection(state);
+    if let Some(existing) = col
+        .find_one(doc! { "_id": file.id }, None)
+        .await
+        .map_err(|_| ApiError::Internal)?
+    {
+        return Ok(Some(existing));
+    }
+    if file.size > cfg.max_file_bytes {
+        return Ok(None);
+    }
+
+    let now = Utc::now();
+    let preview = PreviewDoc {
+        id: file.id,
+        source_stored_name: file.stored_name.clone(),
+        pdf_stored_name: None,
+        status: PreviewStatus::Pending,
+        attempts: 0,
+        next_attempt_at: now,
+        error: None,
+        created_at: now,
+        updated_at: now,
+    };
+    // Dos vistas simultáneas: la segunda choca con el _id y lee la que ya está
+    if col.insert_one(&preview, None).await.is_err() {
+        return col
+            .find_one(doc! { "_id": file.id }, None)
+            .await
+            .map_err(|_| ApiError::Internal);
+    }
+    Ok(Some(preview))
+}
+
+// Al borrar archivos se van también sus PDFs derivados
+pub async fn remove_for_files(state: &AppState, file_ids: Vec<ObjectId>) {
+    if file_ids.is_empty() {
+        return;
+    }
+
+    let col = previews_collection(state);
+    let filter = doc! { "_id": { "$in": file_ids } };
+    if let Ok(mut cursor) = col.find(filter.clone(), None).await {
+        while let Some(Ok(preview)) = cursor.next().await {
+            if let Some(pdf) = preview.pdf_stored_name.as_deref() {
+                storage::remove(pdf);
+            }
+        }
+    }
+    if let Err(e) = col.delete_many(filter, None).await {
+        eprintln!(
+            "Mongo delete_many error (previews remove_for_files): {:?}",
+            e
+        );
+    }
+}
+
+// Worker: convierte lo pendiente con a lo sumo `max_concurrent` procesos de LibreOffice a la vez
+pub fn spawn_worker(state: AppState, cfg: OfficePreviewConfig) {
+    actix_web::rt::spawn(async move {
+        let slots = Arc::new(Semaphore::new(cfg.max_concurrent.max(1)));
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(cfg.poll_secs.max(1)));
+        loop {
+            interval.tick().await;
+            loop {
+                let Ok(slot) = slots.clone().acquire_owned().await else {
+                    return;
+                };
+                let Some(preview) = claim_next(&state, &cfg).await else {
+                    break;
+                };
+
+                let (state, cfg) = (state.clone(), cfg.clone());
+                actix_web::rt::spawn(async move {
+                    convert(&state, &cfg, preview).await;
+                    drop(slot);
+                });
+            }
+        }
+    });
+}
+
+async fn claim_next(state: &AppState, cfg: &OfficePreviewConfig) -> Option<PreviewDoc> {
+    let now = Utc::now();
+    let options = FindOneAndUpdateOptions::builder()
+        .sort(doc! { "next_attempt_at": 1 })
+        .build();
+    // El lease cubre la conversión completa aunque llegue al timeout
+    let lease = chrono::Duration::seconds(cfg.timeout_secs as i64 + 60);
+
+    previews_collection(state)
+        .find_one_and_update(
+            doc! {
+                "status": "pending",
+                "next_attempt_at": { "$lte": bson::DateTime::from_chrono(now) },
+            },
+            doc! { "$set": { "next_attempt_at": bson::DateTime::from_chrono(now + lease) } },
+            options,
+        )
+        .await
+        .map_err(|e| eprintln!("Mongo find_one_and_update error (preview worker): {:?}", e))
+        .ok()
+        .flatten()
+}
+
+async fn convert(state: &AppState, cfg: &OfficePreviewConfig, preview: PreviewDoc) {
+    let pdf_name = format!("preview_{}.pdf", preview.id.to_hex());
+
+    let (status, error) = match soffice(cfg, &preview.source_stored_name, &pdf_name).await {
+        Ok(()) => (PreviewStatus::Ready, None),
+        Err(e) if preview.attempts + 1 < MAX_ATTEMPTS => {
+            retry(state, &preview, &e).await;
+            return;
+        }
+        Err(e) => (PreviewStatus::Failed, Some(e)),
+    };
+
+    let pdf = (status == PreviewStatus::Ready).then_some(pdf_name);
+    let status = bson::to_bson(&status).unwrap_or(bson::Bson::Null);
+    let updated = previews_collection(state)
+        .update_one(
+            doc! { "_id": preview.id },
+            doc! { "$set": {
+                "status": status,
+                "pdf_stored_name": &pdf,
+                "attempts": preview.attempts + 1,
+                "error": error.as_deref().map(truncate),
+                "updated_at": bson_time(Utc::now()),
+            } },
+            None,
+        )
+        .await;
+
+    match updated {
+        // El archivo se borró mientras se convertía: el PDF quedaría huérfano
+        Ok(res) if res.matched_count == 0 => {
+            if let Some(pdf) = pdf.as_deref() {
+                storage::remove(pdf);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Mongo update_one error (preview finish): {:?}", e),
+    }
+}
+
+async fn retry(state: &AppState, preview: &PreviewDoc, error: &str) {
+    let next = Utc::now() + chrono::Duration::seconds(RETRY_DELAY_SECS);
+    if let Err(e) = previews_collection(state)
+        .update_one(
+            doc! { "_id": preview.id },
+            doc! { "$set": {
+                "attempts": preview.attempts + 1,
+                "next_attempt_at": bson::DateTime::from_chrono(next),
+                "error": truncate(error),
+            } },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_one error (preview retry): {:?}", e);
+    }
+}
+
+// soffice --headless --convert-to pdf en un directorio de trabajo propio, con su propio perfil
+// de usuario: dos instancias con el mismo perfil se bloquean entre sí
+async fn soffice(cfg: &OfficePreviewConfig, source: &str, pdf_name: &str) -> Result<(), String> {
+    let work =
+        Path::new(storage::UPLOAD_DIR).join(format!("tmp_preview_{}", ObjectId::new().to_hex()));
+    tokio::fs::create_dir_all(&work)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let result = run_soffice(cfg, source, &work).await;
+    let result = match result {
+        Ok(()) => {
+            // Sale con el nombre del original y extensión .pdf; es el único .pdf del directorio
+            let stem = Path::new(source)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let out = work.join(format!("{}.pdf", stem));
+            tokio::fs::rename(&out, storage::path_for(pdf_name))
+                .await
+                .map_err(|_| "conversion produced no PDF".to_string())
+        }
+        Err(e) => Err(e),
+    };
+
+    let _ = tokio::fs::remove_dir_all(&work).await;
+    result
+}
+
+async fn run_soffice(cfg: &OfficePreviewConfig, source: &str, work: &Path) -> Result<(), String> {
+    let work_abs = std::fs::canonicalize(work).map_err(|e| e.to_string())?;
+    let profile = work_abs.join("profile");
+
+    let mut cmd = Command::new(&cfg.soffice_bin);
+    cmd.arg("--headless")
+        .arg("--norestore")
+        .arg("--nolockcheck")
+        .arg(format!(
+            "-env:UserInstallation=file://{}",
+            profile.to_string_lossy()
+        ))
+        .arg("--convert-to")
+        .arg("pdf")
+        .arg("--outdir")
+        .arg(&work_abs)
+        .arg(storage::path_for(source))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Grupo de procesos propio: `soffice` es un lanzador que deja hijos (soffice.bin)
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let child = cmd
+        .spawn()
+        .map_err(|e| format!("could not start soffice: {}", e))?;
+    let pid = child.id();
+
+    match tokio::time::timeout(
+        Duration::from_secs(cfg.timeout_secs),
+        child.wait_with_output(),
+    )
+    .await
+    {
+        Ok(Ok(output)) if output.status.success() => Ok(()),
+        Ok(Ok(output)) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(format!(
+                "soffice failed ({}): {}",
+                output.status,
+                stderr.trim()
+            ))
+        }
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => {
+            kill_group(pid);
+            Err("conversion timed out".to_string())
+        }
+    }
+}
+
+#[cfg(unix)]
+fn kill_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        // SAFETY: kill(2) solo recibe enteros; -pid apunta al grupo creado con process_group(0)
+        unsafe {
+            libc::kill(-(pid as i32), libc::SIGKILL);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pid: Option<u32>) {}
+
+fn truncate(s: &str) -> String {
+    s.chars().take(MAX_ERROR_LEN).collect()
+}
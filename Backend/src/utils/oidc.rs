@@ -0,0 +1,178 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{config::OidcProviderConfig, utils::random::random_token};
+
+#[derive(Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: Option<serde_json::Value>,
+    pub name: Option<String>,
+    pub nonce: Option<String>,
+}
+
+impl IdTokenClaims {
+    // Algunos IdPs mandan email_verified como string ("true")
+    pub fn is_email_verified(&self) -> bool {
+        match &self.email_verified {
+            Some(serde_json::Value::Bool(b)) => *b,
+            Some(serde_json::Value::String(s)) => s.eq_ignore_ascii_case("true"),
+            _ => false,
+        }
+    }
+}
+
+// (code_verifier, code_challenge) con método S256
+pub fn pkce_pair() -> (String, String) {
+    let verifier = random_token(32);
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    (verifier, challenge)
+}
+
+pub async fn discover(
+    http: &reqwest::Client,
+    provider: &OidcProviderConfig,
+) -> Result<ProviderMetadata, String> {
+    let url = format!("{}/.well-known/openid-configuration", provider.issuer);
+
+    let meta: ProviderMetadata = http
+        .get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if meta.issuer.trim_end_matches('/') != provider.issuer {
+        return Err(format!("issuer mismatch: {}", meta.issuer));
+    }
+
+    Ok(meta)
+}
+
+pub fn authorization_url(
+    meta: &ProviderMetadata,
+    provider: &OidcProviderConfig,
+    state: &str,
+    nonce: &str,
+    code_challenge: &str,
+) -> Result<String, String> {
+    let url = reqwest::Url::parse_with_params(
+        &meta.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", provider.redirect_uri.as_str()),
+            ("scope", provider.scopes.as_str()),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", code_challenge),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(url.to_string())
+}
+
+pub async fn exchange_code(
+    http: &reqwest::Client,
+    meta: &ProviderMetadata,
+    provider: &OidcProviderConfig,
+    code: &str,
+    code_verifier: &str,
+) -> Result<String, String> {
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", provider.redirect_uri.as_str()),
+        ("client_id", provider.client_id.as_str()),
+        ("code_verifier", code_verifier),
+    ];
+    if let Some(secret) = provider.client_secret.as_deref() {
+        form.push(("client_secret", secret));
+    }
+
+    let res: TokenResponse = http
+        .post(&meta.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    res.id_token
+        .ok_or_else(|| "token response has no id_token".to_string())
+}
+
+pub async fn verify_id_token(
+    http: &reqwest::Client,
+    meta: &ProviderMetadata,
+    provider: &OidcProviderConfig,
+    id_token: &str,
+    expected_nonce: &str,
+) -> Result<IdTokenClaims, String> {
+    let header = decode_header(id_token).map_err(|e| e.to_string())?;
+
+    // Solo firmas asimétricas: un HS* con el client_secret no prueba nada del IdP
+    if matches!(
+        header.alg,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+        return Err("symmetric id_token signatures are not accepted".into());
+    }
+
+    let jwks: JwkSet = http
+        .get(&meta.jwks_uri)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let jwk = match header.kid.as_deref() {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or_else(|| "no matching JWK for id_token".to_string())?;
+
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| e.to_string())?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[meta.issuer.as_str()]);
+    validation.set_audience(&[provider.client_id.as_str()]);
+
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| e.to_string())?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(expected_nonce) {
+        return Err("nonce mismatch".into());
+    }
+
+    Ok(claims)
+}
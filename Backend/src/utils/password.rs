@@ -1,9 +1,137 @@
-use bcrypt::{hash, verify, DEFAULT_COST};
+use std::{collections::HashSet, fs};
 
-pub fn hash_password(password: &str) -> Result<String, String> {
-    hash(password, DEFAULT_COST).map_err(|e| e.to_string())
+use actix_web::web;
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+
+use crate::config::PasswordConfig;
+
+pub struct Verification {
+    pub ok: bool,
+    // El hash es bcrypt o argon2 con parámetros viejos: conviene regenerarlo
+    pub needs_rehash: bool,
+}
+
+pub struct Passwords {
+    params: Params,
+    min_length: usize,
+    max_length: usize,
+    breached: HashSet<String>,
+}
+
+impl Passwords {
+    pub fn from_config(cfg: &PasswordConfig) -> Result<Self, String> {
+        let params = Params::new(
+            cfg.argon2_memory_kib,
+            cfg.argon2_iterations,
+            cfg.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| format!("invalid Argon2 params: {}", e))?;
+
+        let breached = match &cfg.breached_list_file {
+            Some(path) => fs::read_to_string(path)
+                .map_err(|e| format!("cannot read {}: {}", path, e))?
+                .lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        Ok(Self {
+            params,
+            min_length: cfg.min_length,
+            max_length: cfg.max_length,
+            breached,
+        })
+    }
+
+    pub fn validate_policy(&self, password: &str) -> Result<(), String> {
+        let len = password.chars().count();
+        if len < self.min_length {
+            return Err(format!(
+                "password must be at least {} characters",
+                self.min_length
+            ));
+        }
+        if len > self.max_length {
+            return Err(format!(
+                "password must be at most {} characters",
+                self.max_length
+            ));
+        }
+        if self.breached.contains(password) {
+            return Err("password appears in a list of breached passwords".into());
+        }
+        Ok(())
+    }
+
+    // Hashing en el thread pool de bloqueo para no frenar al worker de actix
+    pub async fn hash(&self, password: &str) -> Result<String, String> {
+        let argon = self.argon2();
+        let password = password.to_string();
+
+        web::block(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            argon
+                .hash_password(password.as_bytes(), &salt)
+                .map(|h| h.to_string())
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    pub async fn verify(&self, password: &str, hash_str: &str) -> Result<Verification, String> {
+        let argon = self.argon2();
+        let current = self.params.clone();
+        let password = password.to_string();
+        let hash_str = hash_str.to_string();
+
+        web::block(move || verify_blocking(&argon, &current, &password, &hash_str))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
 }
 
-pub fn verify_password(password: &str, hash_str: &str) -> Result<bool, String> {
-    verify(password, hash_str).map_err(|e| e.to_string())
+// Detección por formato PHC: "$argon2id$..." o bcrypt "$2a$/$2b$/$2y$"
+fn verify_blocking(
+    argon: &Argon2<'static>,
+    current: &Params,
+    password: &str,
+    hash_str: &str,
+) -> Result<Verification, String> {
+    if hash_str.starts_with("$argon2") {
+        let parsed = PasswordHash::new(hash_str).map_err(|e| e.to_string())?;
+        let ok = argon.verify_password(password.as_bytes(), &parsed).is_ok();
+
+        let outdated = parsed.algorithm != Algorithm::Argon2id.ident()
+            || Params::try_from(&parsed).map_or(true, |p| {
+                p.m_cost() != current.m_cost()
+                    || p.t_cost() != current.t_cost()
+                    || p.p_cost() != current.p_cost()
+            });
+
+        return Ok(Verification {
+            ok,
+            needs_rehash: ok && outdated,
+        });
+    }
+
+    if hash_str.starts_with("$2") {
+        let ok = bcrypt::verify(password, hash_str).map_err(|e| e.to_string())?;
+        return Ok(Verification {
+            ok,
+            needs_rehash: ok,
+        });
+    }
+
+    Err("unknown password hash format".into())
 }
@@ -0,0 +1,368 @@
+// Índice de búsqueda: cada archivo subido se encola en `file_texts` y un worker en segundo
+// plano extrae su texto (con OCR opcional). Mongo mantiene el índice de texto sobre nombre + contenido.
+use std::collections::HashSet;
+
+use actix_web::web;
+use bson::{doc, oid::ObjectId};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use mongodb::{
+    options::{FindOneAndUpdateOptions, IndexOptions, InsertManyOptions},
+    IndexModel,
+};
+
+use crate::{
+    config::{OcrConfig, SearchConfig},
+    db::{bson_time, AppState},
+    models::{
+        file::FileDoc,
+        search::{FileTextDoc, TextStatus},
+    },
+    routes::files::files_collection,
+    utils::{
+        ocr, storage,
+        text_extract::{self, TextFormat},
+    },
+};
+
+// Holgado: un PDF escaneado pasa por OCR página a página
+const CLAIM_LEASE_SECS: i64 = 1800;
+const EXTRACT_TIMEOUT_SECS: u64 = 120;
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_SECS: i64 = 60;
+const MAX_ERROR_LEN: usize = 500;
+const BACKFILL_BATCH: usize = 500;
+
+pub(crate) fn texts_collection(state: &AppState) -> mongodb::Collection<FileTextDoc> {
+    state.db.collection::<FileTextDoc>("file_texts")
+}
+
+pub async fn ensure_indexes(state: &AppState, cfg: &SearchConfig) {
+    let col = texts_collection(state);
+
+    let text_options = IndexOptions::builder()
+        .name(Some("file_texts_search".to_string()))
+        .weights(Some(doc! { "name": 5, "text": 1 }))
+        .default_language(Some(cfg.language.clone()))
+        .build();
+    let queue_options = IndexOptions::builder()
+        .name(Some("file_texts_queue".to_string()))
+        .build();
+
+    for (name, model) in [
+        (
+            "file_texts_search",
+            IndexModel::builder()
+                .keys(doc! { "name": "text", "text": "text" })
+                .options(text_options)
+                .build(),
+        ),
+        (
+            "file_texts_queue",
+            IndexModel::builder()
+                .keys(doc! { "status": 1, "next_attempt_at": 1 })
+                .options(queue_options)
+                .build(),
+        ),
+    ] {
+        if let Err(e) = col.create_index(model, None).await {
+            eprintln!("Mongo create_index error ({}): {:?}", name, e);
+        }
+    }
+}
+
+fn pending(file: &FileDoc) -> FileTextDoc {
+    let now = Utc::now();
+    FileTextDoc {
+        id: file.id,
+        owner_id: file.owner_id.clone(),
+        org_id: file.org_id.clone(),
+        name: file.original_name.clone(),
+        mime: file.mime.clone(),
+        stored_name: file.stored_name.clone(),
+        size: file.size,
+        text: None,
+        ocr: false,
+        status: TextStatus::Pending,
+        attempts: 0,
+        next_attempt_at: now,
+        error: None,
+        updated_at: now,
+    }
+}
+
+// Encola archivos recién creados; nunca falla la subida que los originó
+pub async fn enqueue(state: &AppState, files: &[FileDoc]) {
+    if files.is_empty() {
+        return;
+    }
+
+    // Sin orden: un _id ya encolado no impide insertar el resto
+    let options = InsertManyOptions::builder().ordered(false).build();
+    let docs: Vec<FileTextDoc> = files.iter().map(pending).collect();
+    if let Err(e) = texts_collection(state).insert_many(docs, options).await {
+        eprintln!("Mongo insert_many error (search enqueue): {:?}", e);
+    }
+}
+
+pub async fn rename(state: &AppState, file_id: ObjectId, name: &str) {
+    if let Err(e) = texts_collection(state)
+        .update_one(
+            doc! { "_id": file_id },
+            doc! { "$set": { "name": name } },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_one error (search rename): {:?}", e);
+    }
+}
+
+pub async fn remove_for_files(state: &AppState, file_ids: Vec<ObjectId>) {
+    if file_ids.is_empty() {
+        return;
+    }
+    if let Err(e) = texts_collection(state)
+        .delete_many(doc! { "_id": { "$in": file_ids } }, None)
+        .await
+    {
+        eprintln!("Mongo delete_many error (search remove_for_files): {:?}", e);
+    }
+}
+
+// Los archivos personales cambian de dueño al transferirse antes de borrar una cuenta
+pub async fn transfer_owner(state: &AppState, from: &str, to: &str) {
+    if let Err(e) = texts_collection(state)
+        .update_many(
+            doc! { "owner_id": from, "org_id": null },
+            doc! { "$set": { "owner_id": to } },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_many error (search transfer_owner): {:?}", e);
+    }
+}
+
+// Worker en segundo plano: primero encola lo subido antes de existir el índice, luego
+// procesa la cola de uno en uno
+pub fn spawn_worker(state: AppState, cfg: SearchConfig, ocr: Option<OcrConfig>) {
+    actix_web::rt::spawn(async move {
+        backfill(&state).await;
+        if ocr.is_some() {
+            requeue_for_ocr(&state).await;
+        }
+
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(cfg.poll_secs.max(1)));
+        loop {
+            interval.tick().await;
+            while let Some(item) = claim_next(&state).await {
+                process(&state, &cfg, ocr.as_ref(), item).await;
+            }
+        }
+    });
+}
+
+async fn backfill(state: &AppState) {
+    let mut known = HashSet::new();
+    match texts_collection(state).distinct("_id", None, None).await {
+        Ok(ids) => known.extend(ids.into_iter().filter_map(|id| id.as_object_id())),
+        Err(e) => {
+            eprintln!("Mongo distinct error (search backfill): {:?}", e);
+            return;
+        }
+    }
+
+    let mut cursor = match files_collection(state).find(None, None).await {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            eprintln!("Mongo find error (search backfill): {:?}", e);
+            return;
+        }
+    };
+
+    let mut batch = Vec::new();
+    while let Some(Ok(file)) = cursor.next().await {
+        if known.contains(&file.id) {
+            continue;
+        }
+        batch.push(file);
+        if batch.len() >= BACKFILL_BATCH {
+            enqueue(state, &batch).await;
+            batch.clear();
+        }
+    }
+    enqueue(state, &batch).await;
+}
+
+// Al activar OCR, las imágenes y PDFs que quedaron sin texto vuelven a la cola una vez
+async fn requeue_for_ocr(state: &AppState) {
+    let mut mimes = text_extract::IMAGE_MIMES.to_vec();
+    mimes.push("application/pdf");
+
+    if let Err(e) = texts_collection(state)
+        .update_many(
+            doc! {
+                "status": { "$in": ["unsupported", "empty"] },
+                "ocr": { "$ne": true },
+                "mime": { "$in": mimes },
+            },
+            doc! { "$set": {
+                "status": "pending",
+                "attempts": 0,
+                "next_attempt_at": bson::DateTime::from_chrono(Utc::now()),
+            } },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_many error (search requeue_for_ocr): {:?}", e);
+    }
+}
+
+async fn claim_next(state: &AppState) -> Option<FileTextDoc> {
+    let now = Utc::now();
+    let options = FindOneAndUpdateOptions::builder()
+        .sort(doc! { "next_attempt_at": 1 })
+        .build();
+
+    texts_collection(state)
+        .find_one_and_update(
+            doc! {
+                "status": "pending",
+                "next_attempt_at": { "$lte": bson::DateTime::from_chrono(now) },
+            },
+            doc! { "$set": {
+                "next_attempt_at": bson::DateTime::from_chrono(now + Duration::seconds(CLAIM_LEASE_SECS)),
+            } },
+            options,
+        )
+        .await
+        .map_err(|e| eprintln!("Mongo find_one_and_update error (search worker): {:?}", e))
+        .ok()
+        .flatten()
+}
+
+async fn process(state: &AppState, cfg: &SearchConfig, ocr: Option<&OcrConfig>, item: FileTextDoc) {
+    let format = TextFormat::detect(&item.mime, &item.name);
+    let Some(format) = format.filter(|f| *f != TextFormat::Image || ocr.is_some()) else {
+        finish(state, &item, TextStatus::Unsupported, None, false, None).await;
+        return;
+    };
+    if item.size > cfg.max_file_bytes {
+        finish(state, &item, TextStatus::Skipped, None, false, None).await;
+        return;
+    }
+
+    let error = match read_text(cfg, ocr, &item, format).await {
+        Ok((text, used_ocr)) if text.trim().is_empty() => {
+            finish(state, &item, TextStatus::Empty, None, used_ocr, None).await;
+            return;
+        }
+        Ok((text, used_ocr)) => {
+            finish(
+                state,
+                &item,
+                TextStatus::Indexed,
+                Some(text),
+                used_ocr,
+                None,
+            )
+            .await;
+            return;
+        }
+        Err(e) => e,
+    };
+
+    let attempts = item.attempts + 1;
+    if attempts >= MAX_ATTEMPTS {
+        finish(state, &item, TextStatus::Failed, None, false, Some(error)).await;
+        return;
+    }
+
+    let next = Utc::now() + Duration::seconds(RETRY_BASE_SECS * i64::from(attempts));
+    if let Err(e) = texts_collection(state)
+        .update_one(
+            doc! { "_id": item.id },
+            doc! { "$set": {
+                "attempts": attempts,
+                "next_attempt_at": bson::DateTime::from_chrono(next),
+                "error": truncate(&error),
+            } },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_one error (search retry): {:?}", e);
+    }
+}
+
+// Texto del archivo y si hizo falta OCR: primero el texto propio, OCR solo para imágenes y
+// PDFs escaneados (sin capa de texto)
+async fn read_text(
+    cfg: &SearchConfig,
+    ocr: Option<&OcrConfig>,
+    item: &FileTextDoc,
+    format: TextFormat,
+) -> Result<(String, bool), String> {
+    let path = storage::path_for(&item.stored_name);
+
+    if format != TextFormat::Image {
+        let (p, max_chars) = (path.clone(), cfg.max_text_chars);
+        // Un PDF patológico puede colgar al parser: se deja de esperar y cuenta como intento
+        // fallido. El timeout no detiene el hilo de web::block (no se puede cancelar): sigue
+        // ocupando un hilo del pool bloqueante hasta que el parser termine por su cuenta.
+        let text = tokio::time::timeout(
+            std::time::Duration::from_secs(EXTRACT_TIMEOUT_SECS),
+            web::block(move || text_extract::extract(&p, format, max_chars)),
+        )
+        .await
+        .map_err(|_| "extraction timed out".to_string())?
+        .map_err(|_| "extraction thread failed".to_string())??;
+
+        if !text.trim().is_empty() {
+            return Ok((text, false));
+        }
+    }
+
+    let raw = match (ocr, format) {
+        (Some(ocr), TextFormat::Image) => ocr::recognize_image(ocr, &path).await?,
+        (Some(ocr), TextFormat::Pdf) => ocr::recognize_pdf(ocr, &path).await?,
+        _ => return Ok((String::new(), false)),
+    };
+    Ok((text_extract::normalize(&raw, cfg.max_text_chars), true))
+}
+
+async fn finish(
+    state: &AppState,
+    item: &FileTextDoc,
+    status: TextStatus,
+    text: Option<String>,
+    used_ocr: bool,
+    error: Option<String>,
+) {
+    let status = bson::to_bson(&status).unwrap_or(bson::Bson::Null);
+
+    if let Err(e) = texts_collection(state)
+        .update_one(
+            doc! { "_id": item.id },
+            doc! { "$set": {
+                "status": status,
+                "text": text,
+                "ocr": used_ocr,
+                "attempts": item.attempts + 1,
+                "error": error.as_deref().map(truncate),
+                "updated_at": bson_time(Utc::now()),
+            } },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_one error (search finish): {:?}", e);
+    }
+}
+
+fn truncate(s: &str) -> String {
+    s.chars().take(MAX_ERROR_LEN).collect()
+}
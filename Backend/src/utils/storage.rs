@@ -0,0 +1,35 @@
+use std::{fs, path::Path};
+
+use crate::errors::ApiError;
+
+// Todo lo que se guarda en disco (archivos, avatares) pasa por aquí
+pub const UPLOAD_DIR: &str = "uploads";
+
+pub fn ensure_upload_dir() -> Result<(), ApiError> {
+    if !Path::new(UPLOAD_DIR).exists() {
+        fs::create_dir_all(UPLOAD_DIR).map_err(|_| ApiError::Internal)?;
+    }
+    Ok(())
+}
+
+pub fn path_for(stored_name: &str) -> String {
+    format!("{}/{}", UPLOAD_DIR, stored_name)
+}
+
+pub fn create(stored_name: &str) -> Result<fs::File, ApiError> {
+    ensure_upload_dir()?;
+    fs::File::create(path_for(stored_name)).map_err(|_| ApiError::Internal)
+}
+
+pub fn write(stored_name: &str, bytes: &[u8]) -> Result<(), ApiError> {
+    ensure_upload_dir()?;
+    fs::write(path_for(stored_name), bytes).map_err(|_| ApiError::Internal)
+}
+
+pub fn read(stored_name: &str) -> Result<Vec<u8>, ApiError> {
+    fs::read(path_for(stored_name)).map_err(|_| ApiError::NotFound("File missing on disk".into()))
+}
+
+pub fn remove(stored_name: &str) {
+    let _ = fs::remove_file(path_for(stored_name));
+}
@@ -0,0 +1,216 @@
+// Texto plano de los formatos que se indexan para la búsqueda. Funciones bloqueantes:
+// se llaman desde el worker dentro de web::block.
+use std::{fs, io::Read};
+
+// Imágenes que Tesseract sabe leer
+pub const IMAGE_MIMES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/tiff",
+    "image/bmp",
+    "image/webp",
+    "image/gif",
+];
+
+// Qué extractor le toca a un archivo según su MIME o su extensión
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    Plain,
+    Pdf,
+    Docx,
+    Odt,
+    Image, // sin texto propio; solo aporta algo con OCR
+}
+
+impl TextFormat {
+    pub fn detect(mime: &str, name: &str) -> Option<Self> {
+        let ext = name
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_lowercase())
+            .unwrap_or_default();
+        match (mime, ext.as_str()) {
+            ("application/pdf", _) | (_, "pdf") => Some(TextFormat::Pdf),
+            ("application/vnd.openxmlformats-officedocument.wordprocessingml.document", _)
+            | (_, "docx") => Some(TextFormat::Docx),
+            ("application/vnd.oasis.opendocument.text", _) | (_, "odt") => Some(TextFormat::Odt),
+            (m, _) if IMAGE_MIMES.contains(&m) => Some(TextFormat::Image),
+            (_, "png" | "jpg" | "jpeg" | "tif" | "tiff" | "bmp" | "webp" | "gif") => {
+                Some(TextFormat::Image)
+            }
+            (m, _) if m.starts_with("text/") => Some(TextFormat::Plain),
+            (_, "txt" | "md" | "markdown" | "csv") => Some(TextFormat::Plain),
+            _ => None,
+        }
+    }
+}
+
+pub fn extract(path: &str, format: TextFormat, max_chars: usize) -> Result<String, String> {
+    let text = match format {
+        TextFormat::Plain => {
+            let mut bytes = Vec::new();
+            fs::File::open(path)
+                .and_then(|f| f.take(max_chars as u64 * 4).read_to_end(&mut bytes))
+                .map_err(|e| e.to_string())?;
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        TextFormat::Pdf => {
+            // pdf-extract entra en pánico con algunos PDF mal formados
+            std::panic::catch_unwind(|| pdf_extract::extract_text(path))
+                .map_err(|_| "PDF parser panicked".to_string())?
+                .map_err(|e| e.to_string())?
+        }
+        TextFormat::Docx => xml_text(&zip_member(path, "word/document.xml", max_chars)?),
+        TextFormat::Odt => xml_text(&zip_member(path, "content.xml", max_chars)?),
+        TextFormat::Image => String::new(),
+    };
+
+    Ok(normalize(&text, max_chars))
+}
+
+// Se lee con tope: un XML comprimido de pocos KB puede descomprimirse a GB (zip bomb).
+// El margen cubre el marcado que rodea al texto; lo que queda fuera no se indexaría igual.
+fn zip_member(path: &str, member: &str, max_chars: usize) -> Result<String, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = ::zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let entry = archive.by_name(member).map_err(|e| e.to_string())?;
+
+    let mut xml = Vec::new();
+    entry
+        .take(max_chars as u64 * 8)
+        .read_to_end(&mut xml)
+        .map_err(|e| e.to_string())?;
+    Ok(String::from_utf8_lossy(&xml).into_owned())
+}
+
+// Texto de un XML de Office/ODF: fin de párrafo = salto de línea, etiquetas fuera
+fn xml_text(xml: &str) -> String {
+    let mut out = String::with_capacity(xml.len() / 4);
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        out.push_str(&decode_entities(&rest[..start]));
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        let name = tag
+            .trim_start_matches('/')
+            .split([' ', '/'])
+            .next()
+            .unwrap_or("");
+        match name {
+            "w:p" | "text:p" | "text:h" | "w:br" | "text:line-break"
+                if tag.starts_with('/') || tag.ends_with('/') =>
+            {
+                out.push('\n')
+            }
+            "w:tab" | "text:tab" | "text:s" => out.push(' '),
+            _ => {}
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(&decode_entities(rest));
+    out
+}
+
+fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i..];
+        let Some(semi) = after.find(';').filter(|n| *n <= 10) else {
+            out.push('&');
+            rest = &after[1..];
+            continue;
+        };
+
+        let entity = &after[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            e if e.starts_with("#x") || e.starts_with("#X") => u32::from_str_radix(&e[2..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            e if e.starts_with('#') => e[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &after[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &after[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Colapsa espacios en blanco y recorta al máximo indexable
+pub fn normalize(text: &str, max_chars: usize) -> String {
+    let mut out = String::new();
+    let mut count = 0;
+
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+            count += 1;
+        }
+        for c in line.chars() {
+            if count >= max_chars {
+                return out;
+            }
+            out.push(c);
+            count += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn caps_decompressed_office_xml() {
+        let path = std::env::temp_dir().join(format!(
+            "text-extract-{}.docx",
+            bson::oid::ObjectId::new().to_hex()
+        ));
+        let mut zip = ::zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        zip.start_file(
+            "word/document.xml",
+            ::zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        // ~12 MB de XML que comprime a unos pocos KB
+        for _ in 0..500_000 {
+            zip.write_all(b"<w:p><w:t>aaaa</w:t></w:p>").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let xml = zip_member(path.to_str().unwrap(), "word/document.xml", 1000);
+        let text = extract(path.to_str().unwrap(), TextFormat::Docx, 1000);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(xml.unwrap().len(), 8000);
+        assert!(text.unwrap().chars().count() <= 1000);
+    }
+}
@@ -0,0 +1,642 @@
+use std::net::{IpAddr, SocketAddr};
+
+use bson::{doc, oid::ObjectId};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use mongodb::{
+    options::{FindOneAndUpdateOptions, IndexOptions},
+    IndexModel,
+};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::{
+    config::WebhookConfig,
+    db::{bson_time, AppState},
+    models::webhook::{DeliveryDoc, DeliveryStatus, WebhookDoc, WebhookEvent},
+};
+
+// Mientras un worker intenta una entrega nadie más la toma
+const CLAIM_LEASE_SECS: i64 = 60;
+const MAX_RETRY_DELAY_SECS: i64 = 6 * 3600;
+const MAX_ERROR_LEN: usize = 500;
+
+pub(crate) fn webhooks_collection(state: &AppState) -> mongodb::Collection<WebhookDoc> {
+    state.db.collection::<WebhookDoc>("webhooks")
+}
+
+pub(crate) fn deliveries_collection(state: &AppState) -> mongodb::Collection<DeliveryDoc> {
+    state.db.collection::<DeliveryDoc>("webhook_deliveries")
+}
+
+pub async fn ensure_indexes(state: &AppState) {
+    let col = deliveries_collection(state);
+
+    for (name, keys) in [
+        (
+            "deliveries_queue",
+            doc! { "status": 1, "next_attempt_at": 1 },
+        ),
+        (
+            "deliveries_webhook",
+            doc! { "webhook_id": 1, "created_at": -1 },
+        ),
+    ] {
+        let options = IndexOptions::builder().name(Some(name.to_string())).build();
+        let model = IndexModel::builder().keys(keys).options(options).build();
+
+        if let Err(e) = col.create_index(model, None).await {
+            eprintln!("Mongo create_index error ({}): {:?}", name, e);
+        }
+    }
+}
+
+// Encola una entrega por cada webhook suscrito: los de quienes ven el recurso y los de plataforma.
+// Nunca falla la petición que originó el evento.
+pub async fn dispatch(state: &AppState, event: WebhookEvent, owners: &[String], data: Value) {
+    dispatch_many(state, event, owners, vec![data]).await;
+}
+
+// Mismo evento sobre varios recursos con la misma audiencia: una consulta de webhooks y
+// un solo insert de entregas
+pub async fn dispatch_many(
+    state: &AppState,
+    event: WebhookEvent,
+    owners: &[String],
+    items: Vec<Value>,
+) {
+    if items.is_empty() {
+        return;
+    }
+
+    let mut scope = vec![doc! { "platform": true }];
+    if !owners.is_empty() {
+        scope.push(doc! { "owner_id": { "$in": owners } });
+    }
+
+    let mut cursor = match webhooks_collection(state)
+        .find(
+            doc! { "active": true, "events": event.as_str(), "$or": scope },
+            None,
+        )
+        .await
+    {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            eprintln!("Mongo find error (webhook dispatch): {:?}", e);
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    let mut deliveries = Vec::new();
+    while let Some(Ok(hook)) = futures::StreamExt::next(&mut cursor).await {
+        for data in &items {
+            let id = ObjectId::new();
+            let payload = json!({
+                "id": id.to_hex(),
+                "event": event,
+                "created_at": now,
+                "data": data,
+            });
+
+            deliveries.push(DeliveryDoc {
+                id,
+                webhook_id: hook.id.to_hex(),
+                event,
+                payload: payload.to_string(),
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                next_attempt_at: now,
+                last_status_code: None,
+                last_error: None,
+                created_at: now,
+                delivered_at: None,
+            });
+        }
+    }
+
+    if deliveries.is_empty() {
+        return;
+    }
+    if let Err(e) = deliveries_collection(state)
+        .insert_many(deliveries, None)
+        .await
+    {
+        eprintln!("Mongo insert_many error (webhook dispatch): {:?}", e);
+    }
+}
+
+// Quien deja de ser admin deja de recibir los eventos de toda la plataforma
+pub async fn revoke_platform_of(state: &AppState, owner_id: &str) {
+    if let Err(e) = webhooks_collection(state)
+        .update_many(
+            doc! { "owner_id": owner_id, "platform": true },
+            doc! { "$set": { "platform": false } },
+            None,
+        )
+        .await
+    {
+        eprintln!(
+            "Mongo update_many error (webhooks revoke_platform_of): {:?}",
+            e
+        );
+    }
+}
+
+// Al borrar una cuenta sus webhooks dejan de recibir eventos
+pub async fn remove_all_of(state: &AppState, owner_id: &str) {
+    if let Err(e) = webhooks_collection(state)
+        .delete_many(doc! { "owner_id": owner_id }, None)
+        .await
+    {
+        eprintln!("Mongo delete_many error (webhooks remove_all_of): {:?}", e);
+    }
+}
+
+// Las entregas solo salen a direcciones públicas: nada de loopback, red privada ni link-local
+pub(crate) fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64)) // 100.64.0.0/10 (CGNAT)
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public_ip(IpAddr::V4(v4)),
+            None => {
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    || v6.is_multicast()
+                    || v6.is_unique_local()
+                    || v6.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+// Resuelve el host de la URL; falla si alguna dirección no es pública
+pub(crate) async fn resolve_target(
+    url: &str,
+    allow_private: bool,
+) -> Result<(String, Vec<SocketAddr>), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "invalid url".to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("url must be http(s)".into());
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "url has no host".to_string())?
+        .to_string();
+    let port = parsed.port_or_known_default().unwrap_or(443);
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .map_err(|_| "could not resolve host".to_string())?
+        .collect();
+    if addrs.is_empty() {
+        return Err("could not resolve host".into());
+    }
+    if !allow_private && addrs.iter().any(|a| !is_public_ip(a.ip())) {
+        return Err("url resolves to a private or local address".into());
+    }
+    Ok((host, addrs))
+}
+
+// `sha256=<hex>` de HMAC-SHA256(secret, "<timestamp>.<body>")
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+// Worker en segundo plano: toma entregas pendientes y las intenta con backoff exponencial
+pub fn spawn_worker(state: AppState, cfg: WebhookConfig) {
+    actix_web::rt::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(cfg.poll_secs.max(1)));
+        loop {
+            interval.tick().await;
+            while let Some(delivery) = claim_next(&state).await {
+                attempt(&state, &cfg, delivery).await;
+            }
+        }
+    });
+}
+
+async fn claim_next(state: &AppState) -> Option<DeliveryDoc> {
+    let now = Utc::now();
+    let options = FindOneAndUpdateOptions::builder()
+        .sort(doc! { "next_attempt_at": 1 })
+        .build();
+
+    deliveries_collection(state)
+        .find_one_and_update(
+            doc! {
+                "status": "pending",
+                "next_attempt_at": { "$lte": bson::DateTime::from_chrono(now) },
+            },
+            doc! { "$set": {
+                "next_attempt_at": bson::DateTime::from_chrono(now + Duration::seconds(CLAIM_LEASE_SECS)),
+            } },
+            options,
+        )
+        .await
+        .map_err(|e| eprintln!("Mongo find_one_and_update error (webhook worker): {:?}", e))
+        .ok()
+        .flatten()
+}
+
+async fn attempt(state: &AppState, cfg: &WebhookConfig, delivery: DeliveryDoc) {
+    let hook = match ObjectId::parse_str(&delivery.webhook_id) {
+        Ok(id) => webhooks_collection(state)
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .ok()
+            .flatten(),
+        Err(_) => None,
+    };
+
+    let result = match hook.as_ref().filter(|h| h.active) {
+        Some(hook) => send(cfg, hook, &delivery).await,
+        None => {
+            finish(
+                state,
+                &delivery,
+                DeliveryStatus::Failed,
+                None,
+                Some("webhook inactive or deleted".into()),
+            )
+            .await;
+            return;
+        }
+    };
+
+    match result {
+        Ok(code) if (200..300).contains(&code) => {
+            finish(
+                state,
+                &delivery,
+                DeliveryStatus::Succeeded,
+                Some(code),
+                None,
+            )
+            .await;
+        }
+        other => {
+            let (code, error) = match other {
+                Ok(code) => (Some(code), format!("HTTP {}", code)),
+                Err(e) => (None, e),
+            };
+            let attempts = delivery.attempts + 1;
+            if attempts >= cfg.max_attempts {
+                finish(state, &delivery, DeliveryStatus::Failed, code, Some(error)).await;
+                return;
+            }
+
+            let next = Utc::now() + Duration::seconds(retry_delay(cfg, attempts));
+
+            if let Err(e) = deliveries_collection(state)
+                .update_one(
+                    doc! { "_id": delivery.id },
+                    doc! { "$set": {
+                        "attempts": attempts,
+                        "next_attempt_at": bson::DateTime::from_chrono(next),
+                        "last_status_code": code.map(i32::from),
+                        "last_error": truncate(&error),
+                    } },
+                    None,
+                )
+                .await
+            {
+                eprintln!("Mongo update_one error (webhook retry): {:?}", e);
+            }
+        }
+    }
+}
+
+// base * 2^(intentos-1), con tope
+fn retry_delay(cfg: &WebhookConfig, attempts: u32) -> i64 {
+    cfg.retry_base_secs
+        .saturating_mul(1i64 << attempts.saturating_sub(1).min(20))
+        .min(MAX_RETRY_DELAY_SECS)
+}
+
+// Copia de una entrega para volver a intentarla; la original queda en el registro
+pub(crate) fn redelivery_of(original: DeliveryDoc) -> DeliveryDoc {
+    let now = Utc::now();
+    DeliveryDoc {
+        id: ObjectId::new(),
+        webhook_id: original.webhook_id,
+        event: original.event,
+        payload: original.payload,
+        status: DeliveryStatus::Pending,
+        attempts: 0,
+        next_attempt_at: now,
+        last_status_code: None,
+        last_error: None,
+        created_at: now,
+        delivered_at: None,
+    }
+}
+
+async fn send(
+    cfg: &WebhookConfig,
+    hook: &WebhookDoc,
+    delivery: &DeliveryDoc,
+) -> Result<u16, String> {
+    // Se resuelve otra vez en cada intento y la conexión queda fijada a esas direcciones,
+    // así un cambio de DNS después de validar no desvía la entrega a la red interna
+    let (host, addrs) = resolve_target(&hook.url, cfg.allow_private_targets).await?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&host, &addrs)
+        .build()
+        .map_err(|e| {
+            eprintln!("HTTP client error (webhook send): {:?}", e);
+            "internal error".to_string()
+        })?;
+    let timestamp = Utc::now().timestamp();
+
+    let res = client
+        .post(&hook.url)
+        .header("Content-Type", "application/json")
+        .header("User-Agent", "PCOSEW-Webhooks/1.0")
+        .header("X-Webhook-Id", hook.id.to_hex())
+        .header("X-Webhook-Delivery", delivery.id.to_hex())
+        .header("X-Webhook-Event", delivery.event.as_str())
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header(
+            "X-Webhook-Signature",
+            sign(&hook.secret, timestamp, &delivery.payload),
+        )
+        .body(delivery.payload.clone())
+        .send()
+        .await
+        // Sin detalles: distinguir "rechazada" de "timeout" serviría para escanear puertos
+        .map_err(|_| "request failed".to_string())?;
+
+    Ok(res.status().as_u16())
+}
+
+async fn finish(
+    state: &AppState,
+    delivery: &DeliveryDoc,
+    status: DeliveryStatus,
+    code: Option<u16>,
+    error: Option<String>,
+) {
+    let now = Utc::now();
+    let status = bson::to_bson(&status).unwrap_or(bson::Bson::Null);
+    let delivered_at = if error.is_none() {
+        bson_time(now)
+    } else {
+        bson::Bson::Null
+    };
+
+    if let Err(e) = deliveries_collection(state)
+        .update_one(
+            doc! { "_id": delivery.id },
+            doc! { "$set": {
+                "status": status,
+                "attempts": delivery.attempts + 1,
+                "last_status_code": code.map(i32::from),
+                "last_error": error.as_deref().map(truncate),
+                "delivered_at": delivered_at,
+            } },
+            None,
+        )
+        .await
+    {
+        eprintln!("Mongo update_one error (webhook finish): {:?}", e);
+    }
+}
+
+fn truncate(s: &str) -> String {
+    s.chars().take(MAX_ERROR_LEN).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    use super::*;
+
+    fn config(allow_private: bool) -> WebhookConfig {
+        WebhookConfig {
+            poll_secs: 1,
+            max_attempts: 3,
+            retry_base_secs: 30,
+            allow_private_targets: allow_private,
+        }
+    }
+
+    fn hook(url: String) -> WebhookDoc {
+        let now = Utc::now();
+        WebhookDoc {
+            id: ObjectId::new(),
+            owner_id: "u1".into(),
+            platform: false,
+            url,
+            secret: "whsec_test".into(),
+            events: vec![WebhookEvent::FileUploaded],
+            active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn delivery(webhook_id: &str) -> DeliveryDoc {
+        let now = Utc::now();
+        DeliveryDoc {
+            id: ObjectId::new(),
+            webhook_id: webhook_id.into(),
+            event: WebhookEvent::FileUploaded,
+            payload: r#"{"a":1}"#.into(),
+            status: DeliveryStatus::Failed,
+            attempts: 3,
+            next_attempt_at: now,
+            last_status_code: Some(500),
+            last_error: Some("HTTP 500".into()),
+            created_at: now,
+            delivered_at: None,
+        }
+    }
+
+    // Servidor HTTP de una sola petición: responde `response` y devuelve lo que recibió
+    fn stub(response: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 1024];
+            loop {
+                let n = conn.read(&mut chunk).unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                let text = String::from_utf8_lossy(&buf).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let len = text[..end]
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length: "))
+                        .and_then(|v| v.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if buf.len() >= end + 4 + len || n == 0 {
+                        break;
+                    }
+                }
+            }
+            conn.write_all(response.as_bytes()).unwrap();
+            tx.send(String::from_utf8_lossy(&buf).to_string()).unwrap();
+        });
+        (url, rx)
+    }
+
+    fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request
+            .lines()
+            .find_map(|l| l.strip_prefix(&format!("{}: ", name)))
+    }
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, r#"{"a":1}"#),
+            "sha256=38877139021993b830af32feea6e18a8da83eb2f6e49ee50bd9e4cf4ca4d3789"
+        );
+        assert_ne!(
+            sign("whsec_test", 1_700_000_001, r#"{"a":1}"#),
+            sign("whsec_test", 1_700_000_000, r#"{"a":1}"#)
+        );
+    }
+
+    #[test]
+    fn only_public_addresses_are_targets() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[actix_web::test]
+    async fn rejects_local_urls() {
+        for url in [
+            "http://127.0.0.1/hook",
+            "http://localhost:8080/hook",
+            "http://[::1]/hook",
+            "http://169.254.169.254/latest/meta-data",
+        ] {
+            assert!(resolve_target(url, false).await.is_err(), "{}", url);
+        }
+        assert!(resolve_target("ftp://example.com/", false).await.is_err());
+        assert!(resolve_target("http://127.0.0.1/hook", true).await.is_ok());
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_with_cap() {
+        let cfg = config(false);
+        assert_eq!(retry_delay(&cfg, 1), 30);
+        assert_eq!(retry_delay(&cfg, 2), 60);
+        assert_eq!(retry_delay(&cfg, 4), 240);
+        assert_eq!(retry_delay(&cfg, 40), MAX_RETRY_DELAY_SECS);
+    }
+
+    #[test]
+    fn redelivery_reuses_payload_as_a_new_pending_delivery() {
+        let original = delivery("w1");
+        let copy = redelivery_of(original.clone());
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.webhook_id, "w1");
+        assert_eq!(copy.event, original.event);
+        assert_eq!(copy.payload, original.payload);
+        assert!(matches!(copy.status, DeliveryStatus::Pending));
+        assert_eq!(copy.attempts, 0);
+        assert!(copy.last_status_code.is_none() && copy.last_error.is_none());
+    }
+
+    #[actix_web::test]
+    async fn sends_signed_payload() {
+        let (url, rx) = stub("HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n");
+        let hook = hook(url);
+        let delivery = delivery(&hook.id.to_hex());
+
+        assert_eq!(send(&config(true), &hook, &delivery).await, Ok(204));
+
+        let request = rx.recv().unwrap();
+        assert!(request.ends_with(r#"{"a":1}"#));
+        assert_eq!(header(&request, "x-webhook-event"), Some("file.uploaded"));
+        let delivery_id = delivery.id.to_hex();
+        assert_eq!(
+            header(&request, "x-webhook-delivery"),
+            Some(delivery_id.as_str())
+        );
+        let timestamp: i64 = header(&request, "x-webhook-timestamp")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            header(&request, "x-webhook-signature"),
+            Some(sign(&hook.secret, timestamp, &delivery.payload).as_str())
+        );
+    }
+
+    #[actix_web::test]
+    async fn does_not_follow_redirects() {
+        let (url, _rx) = stub(
+            "HTTP/1.1 302 Found\r\nLocation: http://169.254.169.254/\r\nContent-Length: 0\r\n\r\n",
+        );
+        let hook = hook(url);
+        assert_eq!(
+            send(&config(true), &hook, &delivery(&hook.id.to_hex())).await,
+            Ok(302)
+        );
+    }
+
+    #[actix_web::test]
+    async fn refuses_local_targets_before_connecting() {
+        let (url, rx) = stub("HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n");
+        let hook = hook(url);
+        assert!(send(&config(false), &hook, &delivery(&hook.id.to_hex()))
+            .await
+            .is_err());
+        assert!(rx.try_recv().is_err());
+    }
+}
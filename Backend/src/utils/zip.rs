@@ -0,0 +1,509 @@
+// Escritor ZIP en streaming: las entradas van sin comprimir (stored) y sin armar el
+// archivo en disco. El CRC de cada archivo se calcula con una lectura previa y va en el
+// encabezado local: lectores en streaming (ZipInputStream de Java) no aceptan entradas
+// stored con data descriptor. Usa ZIP64 solo cuando algún tamaño u offset lo exige.
+use std::{collections::VecDeque, io::SeekFrom};
+
+use actix_web::web::Bytes;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use futures::Stream;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::utils::storage;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const U32_MAX: u64 = 0xFFFF_FFFF;
+const U16_MAX: u64 = 0xFFFF;
+
+const FLAG_UTF8: u16 = 1 << 11;
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+
+pub enum ZipEntry {
+    Dir {
+        path: String,
+        modified: DateTime<Utc>,
+    },
+    File {
+        path: String,
+        stored_name: String,
+        modified: DateTime<Utc>,
+    },
+}
+
+struct CentralRecord {
+    name: String,
+    dos_time: u16,
+    dos_date: u16,
+    flags: u16,
+    crc: u32,
+    size: u64,
+    offset: u64,
+    dir: bool,
+}
+
+struct Current {
+    file: tokio::fs::File,
+    hasher: crc32fast::Hasher,
+    read: u64,
+    record: CentralRecord,
+}
+
+struct Writer {
+    entries: VecDeque<ZipEntry>,
+    current: Option<Current>,
+    central: Vec<CentralRecord>,
+    offset: u64,
+    finished: bool,
+}
+
+// Devuelve el ZIP como stream de chunks; un error de disco corta la respuesta
+pub fn stream(entries: Vec<ZipEntry>) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let writer = Writer {
+        entries: entries.into(),
+        current: None,
+        central: Vec::new(),
+        offset: 0,
+        finished: false,
+    };
+
+    futures::stream::unfold(writer, |mut w| async move {
+        match w.next_chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), w)),
+            Ok(None) => None,
+            Err(e) => {
+                eprintln!("ZIP stream error: {}", e);
+                w.finished = true;
+                w.entries.clear();
+                w.current = None;
+                Some((Err(actix_web::error::ErrorInternalServerError(e)), w))
+            }
+        }
+    })
+}
+
+impl Writer {
+    async fn next_chunk(&mut self) -> std::io::Result<Option<Bytes>> {
+        if let Some(cur) = self.current.as_mut() {
+            let mut buf = vec![0u8; CHUNK_SIZE];
+            let n = cur.file.read(&mut buf).await?;
+            if n > 0 {
+                buf.truncate(n);
+                cur.hasher.update(&buf);
+                cur.read += n as u64;
+                self.offset += n as u64;
+                return Ok(Some(Bytes::from(buf)));
+            }
+
+            // El encabezado ya salió con CRC y tamaño: si el archivo cambió el ZIP quedaría corrupto
+            let cur = self.current.take().expect("current entry");
+            if cur.read != cur.record.size || cur.hasher.finalize() != cur.record.crc {
+                return Err(std::io::Error::other("file changed while archiving"));
+            }
+            self.central.push(cur.record);
+        }
+
+        if let Some(entry) = self.entries.pop_front() {
+            let out = match entry {
+                ZipEntry::Dir { path, modified } => {
+                    let record = record(
+                        format!("{}/", path.trim_end_matches('/')),
+                        modified,
+                        self.offset,
+                        true,
+                    );
+                    let out = local_header(&record);
+                    self.central.push(record);
+                    out
+                }
+                ZipEntry::File {
+                    path,
+                    stored_name,
+                    modified,
+                } => {
+                    let mut file = tokio::fs::File::open(storage::path_for(&stored_name)).await?;
+                    let mut record = record(path, modified, self.offset, false);
+                    (record.crc, record.size) = checksum(&mut file).await?;
+                    file.seek(SeekFrom::Start(0)).await?;
+                    let out = local_header(&record);
+                    self.current = Some(Current {
+                        file,
+                        hasher: crc32fast::Hasher::new(),
+                        read: 0,
+                        record,
+                    });
+                    out
+                }
+            };
+            self.offset += out.len() as u64;
+            return Ok(Some(Bytes::from(out)));
+        }
+
+        if self.finished {
+            return Ok(None);
+        }
+        self.finished = true;
+        Ok(Some(Bytes::from(self.central_directory())))
+    }
+
+    fn central_directory(&self) -> Vec<u8> {
+        let cd_offset = self.offset;
+        let mut out = Vec::new();
+
+        for r in &self.central {
+            let mut extra = Vec::new();
+            if r.size >= U32_MAX {
+                extra.extend_from_slice(&r.size.to_le_bytes()); // sin comprimir
+                extra.extend_from_slice(&r.size.to_le_bytes()); // comprimido
+            }
+            if r.offset >= U32_MAX {
+                extra.extend_from_slice(&r.offset.to_le_bytes());
+            }
+            let zip64 = !extra.is_empty();
+
+            put32(&mut out, 0x0201_4b50);
+            put16(
+                &mut out,
+                if zip64 {
+                    VERSION_ZIP64
+                } else {
+                    VERSION_DEFAULT
+                } | (3 << 8),
+            ); // creado en Unix
+            put16(
+                &mut out,
+                if zip64 {
+                    VERSION_ZIP64
+                } else {
+                    VERSION_DEFAULT
+                },
+            );
+            put16(&mut out, r.flags);
+            put16(&mut out, 0); // stored
+            put16(&mut out, r.dos_time);
+            put16(&mut out, r.dos_date);
+            put32(&mut out, r.crc);
+            put32(&mut out, clamp32(r.size));
+            put32(&mut out, clamp32(r.size));
+            put16(&mut out, r.name.len() as u16);
+            put16(&mut out, if zip64 { extra.len() as u16 + 4 } else { 0 });
+            put16(&mut out, 0); // comentario
+            put16(&mut out, 0); // disco
+            put16(&mut out, 0); // atributos internos
+            let mode: u32 = if r.dir { 0o040755 } else { 0o100644 };
+            put32(&mut out, (mode << 16) | if r.dir { 0x10 } else { 0 });
+            put32(&mut out, clamp32(r.offset));
+            out.extend_from_slice(r.name.as_bytes());
+            if zip64 {
+                put16(&mut out, 0x0001);
+                put16(&mut out, extra.len() as u16);
+                out.extend_from_slice(&extra);
+            }
+        }
+
+        let cd_size = out.len() as u64;
+        let count = self.central.len() as u64;
+        let eocd_offset = cd_offset + cd_size;
+
+        if count >= U16_MAX || cd_size >= U32_MAX || cd_offset >= U32_MAX {
+            // Registro ZIP64 de fin de directorio + localizador
+            put32(&mut out, 0x0606_4b50);
+            put64(&mut out, 44);
+            put16(&mut out, VERSION_ZIP64 | (3 << 8));
+            put16(&mut out, VERSION_ZIP64);
+            put32(&mut out, 0);
+            put32(&mut out, 0);
+            put64(&mut out, count);
+            put64(&mut out, count);
+            put64(&mut out, cd_size);
+            put64(&mut out, cd_offset);
+
+            put32(&mut out, 0x0706_4b50);
+            put32(&mut out, 0);
+            put64(&mut out, eocd_offset);
+            put32(&mut out, 1);
+        }
+
+        put32(&mut out, 0x0605_4b50);
+        put16(&mut out, 0);
+        put16(&mut out, 0);
+        put16(&mut out, count.min(U16_MAX) as u16);
+        put16(&mut out, count.min(U16_MAX) as u16);
+        put32(&mut out, clamp32(cd_size));
+        put32(&mut out, clamp32(cd_offset));
+        put16(&mut out, 0);
+        out
+    }
+}
+
+// Primera pasada sobre el archivo: CRC y tamaño para el encabezado local
+async fn checksum(file: &mut tokio::fs::File) -> std::io::Result<(u32, u64)> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut size = 0u64;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            return Ok((hasher.finalize(), size));
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+}
+
+fn record(name: String, modified: DateTime<Utc>, offset: u64, dir: bool) -> CentralRecord {
+    let (dos_time, dos_date) = dos_datetime(modified);
+    CentralRecord {
+        name,
+        dos_time,
+        dos_date,
+        flags: FLAG_UTF8,
+        crc: 0,
+        size: 0,
+        offset,
+        dir,
+    }
+}
+
+fn local_header(r: &CentralRecord) -> Vec<u8> {
+    let zip64 = r.size >= U32_MAX;
+    let mut out = Vec::with_capacity(30 + r.name.len() + 20);
+    put32(&mut out, 0x0403_4b50);
+    put16(
+        &mut out,
+        if zip64 {
+            VERSION_ZIP64
+        } else {
+            VERSION_DEFAULT
+        },
+    );
+    put16(&mut out, r.flags);
+    put16(&mut out, 0); // stored
+    put16(&mut out, r.dos_time);
+    put16(&mut out, r.dos_date);
+    put32(&mut out, r.crc);
+    put32(&mut out, clamp32(r.size));
+    put32(&mut out, clamp32(r.size));
+    put16(&mut out, r.name.len() as u16);
+    put16(&mut out, if zip64 { 20 } else { 0 });
+    out.extend_from_slice(r.name.as_bytes());
+    if zip64 {
+        put16(&mut out, 0x0001);
+        put16(&mut out, 16);
+        put64(&mut out, r.size);
+        put64(&mut out, r.size);
+    }
+    out
+}
+
+// MS-DOS solo representa 1980..2107 con resolución de 2 segundos
+fn dos_datetime(dt: DateTime<Utc>) -> (u16, u16) {
+    let year = dt.year().clamp(1980, 2107) as u16;
+    let time = ((dt.hour() as u16) << 11) | ((dt.minute() as u16) << 5) | (dt.second() as u16 / 2);
+    let date = ((year - 1980) << 9) | ((dt.month() as u16) << 5) | dt.day() as u16;
+    (time, date)
+}
+
+fn clamp32(v: u64) -> u32 {
+    v.min(U32_MAX) as u32
+}
+
+fn put16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Seek, Write};
+
+    use super::*;
+
+    fn writer(entries: Vec<ZipEntry>, offset: u64) -> Writer {
+        Writer {
+            entries: entries.into(),
+            current: None,
+            central: Vec::new(),
+            offset,
+            finished: false,
+        }
+    }
+
+    async fn collect(mut w: Writer) -> Vec<u8> {
+        let mut out = Vec::new();
+        while let Some(chunk) = w.next_chunk().await.unwrap() {
+            out.extend_from_slice(&chunk);
+        }
+        out
+    }
+
+    // Archivo temporal en el directorio de subidas; se borra al salir
+    struct Stored(String);
+
+    impl Stored {
+        fn new(contents: &[u8]) -> Self {
+            let name = format!("zip-test-{}", bson::oid::ObjectId::new().to_hex());
+            storage::create(&name).unwrap().write_all(contents).unwrap();
+            Stored(name)
+        }
+    }
+
+    impl Drop for Stored {
+        fn drop(&mut self) {
+            storage::remove(&self.0);
+        }
+    }
+
+    // Simula `pad` bytes en cero antes del ZIP sin reservarlos en memoria
+    struct Padded {
+        pad: u64,
+        data: Vec<u8>,
+        pos: u64,
+    }
+
+    impl Read for Padded {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.pad + self.data.len() as u64;
+            let n = (buf.len() as u64).min(len.saturating_sub(self.pos)) as usize;
+            for (i, b) in buf[..n].iter_mut().enumerate() {
+                let at = self.pos + i as u64;
+                *b = if at < self.pad {
+                    0
+                } else {
+                    self.data[(at - self.pad) as usize]
+                };
+            }
+            self.pos += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl Seek for Padded {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            let len = (self.pad + self.data.len() as u64) as i64;
+            self.pos = match pos {
+                SeekFrom::Start(p) => p as i64,
+                SeekFrom::End(d) => len + d,
+                SeekFrom::Current(d) => self.pos as i64 + d,
+            } as u64;
+            Ok(self.pos)
+        }
+    }
+
+    fn read_entry<R: Read + Seek>(archive: &mut ::zip::ZipArchive<R>, name: &str) -> Vec<u8> {
+        let mut entry = archive.by_name(name).unwrap();
+        let mut out = Vec::new();
+        entry.read_to_end(&mut out).unwrap(); // también valida el CRC
+        out
+    }
+
+    #[actix_web::test]
+    async fn round_trips_small_archive() {
+        let a = Stored::new(b"hola mundo");
+        let b = Stored::new(&vec![7u8; CHUNK_SIZE * 2 + 3]);
+        let now = Utc::now();
+        let bytes = collect(writer(
+            vec![
+                ZipEntry::Dir {
+                    path: "docs".into(),
+                    modified: now,
+                },
+                ZipEntry::File {
+                    path: "docs/a.txt".into(),
+                    stored_name: a.0.clone(),
+                    modified: now,
+                },
+                ZipEntry::File {
+                    path: "ñandú.bin".into(),
+                    stored_name: b.0.clone(),
+                    modified: now,
+                },
+            ],
+            0,
+        ))
+        .await;
+
+        let mut archive = ::zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 3);
+        assert!(archive.by_name("docs/").unwrap().is_dir());
+        assert_eq!(read_entry(&mut archive, "docs/a.txt"), b"hola mundo");
+        assert_eq!(
+            read_entry(&mut archive, "ñandú.bin"),
+            vec![7u8; CHUNK_SIZE * 2 + 3]
+        );
+        // Sin data descriptor: el encabezado local ya trae CRC y tamaño
+        let entry = archive.by_name("docs/a.txt").unwrap();
+        assert_eq!(entry.crc32(), crc32fast::hash(b"hola mundo"));
+        assert_eq!(entry.size(), 10);
+    }
+
+    #[actix_web::test]
+    async fn uses_zip64_offsets_past_4_gib() {
+        let a = Stored::new(b"lejos");
+        let pad = 5 << 30;
+        let data = collect(writer(
+            vec![ZipEntry::File {
+                path: "far.txt".into(),
+                stored_name: a.0.clone(),
+                modified: Utc::now(),
+            }],
+            pad,
+        ))
+        .await;
+
+        let mut archive = ::zip::ZipArchive::new(Padded { pad, data, pos: 0 }).unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(read_entry(&mut archive, "far.txt"), b"lejos");
+    }
+
+    #[actix_web::test]
+    async fn uses_zip64_count_past_65535_entries() {
+        let now = Utc::now();
+        let entries = (0..70_000)
+            .map(|i| ZipEntry::Dir {
+                path: format!("d{}", i),
+                modified: now,
+            })
+            .collect();
+        let bytes = collect(writer(entries, 0)).await;
+
+        let mut archive = ::zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 70_000);
+        assert!(archive.by_name("d69999/").unwrap().is_dir());
+    }
+
+    #[actix_web::test]
+    async fn fails_if_the_file_changes_while_streaming() {
+        let a = Stored::new(b"antes");
+        let mut w = writer(
+            vec![ZipEntry::File {
+                path: "a.txt".into(),
+                stored_name: a.0.clone(),
+                modified: Utc::now(),
+            }],
+            0,
+        );
+        w.next_chunk().await.unwrap(); // encabezado local
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(storage::path_for(&a.0))
+            .unwrap()
+            .write_all(b" y despues")
+            .unwrap();
+        let mut result = Ok(None);
+        for _ in 0..3 {
+            result = w.next_chunk().await;
+            if result.is_err() {
+                break;
+            }
+        }
+        assert!(result.is_err());
+    }
+}
@@ -103,13 +103,12 @@ if (registerForm){
   registerForm.addEventListener("submit", async (e)=>{
     e.preventDefault();
     const name = $("#regName").value.trim();
-    const role = $("#regRole").value;
     const email = $("#regEmail").value.trim();
     const pass = $("#regPassword").value;
     const conf = $("#regConfirm").value;
     const terms = $("#terms").checked;
 
-    if (!name || !email || !email.includes("@") || pass.length < 6){
+    if (!name || !email || !email.includes("@") || pass.length < 8){
       toast("danger","Datos inválidos","Completa correctamente.");
       return;
     }
@@ -127,7 +126,6 @@ if (registerForm){
         name,
         email,
         password: pass,
-        role,
       });
 
       // Guardar sesión y entrar